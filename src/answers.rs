@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use verify::Verdict;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    InvalidLine(usize, String),
+    AnswerWithoutDay(usize, String),
+    UnknownStar(usize, String),
+    Unreadable(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidLine(number, line) => {
+                write!(f, "Invalid line {} `{}`", number, line)
+            }
+            ParseError::AnswerWithoutDay(number, line) => {
+                write!(
+                    f,
+                    "Answer on line {} comes before any day `{}`",
+                    number, line
+                )
+            }
+            ParseError::UnknownStar(number, line) => write!(
+                f,
+                "Unknown star on line {} `{}`, expected star_one or star_two",
+                number, line
+            ),
+            ParseError::Unreadable(reason) => write!(f, "Unable to read answers: {}", reason),
+        }
+    }
+}
+
+// Expected answers, from a manifest in a small subset of TOML
+//
+//   [day01]
+//   star_one = 510
+//   star_two = "69074"
+//
+// Answers that aren't numbers have to be quoted, answers with several parts
+// are written like the puzzle wants them, `"83,121"`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Answers {
+    answers: HashMap<(String, usize), String>,
+}
+
+impl Answers {
+    pub fn get(&self, day: &str, star: usize) -> Option<&str> {
+        self.answers
+            .get(&(day.to_owned(), star))
+            .map(|answer| answer.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.answers.is_empty()
+    }
+
+    pub fn check(&self, day: &str, star: usize, answer: &str) -> Verdict {
+        match self.get(day, star) {
+            Some(expected) if expected == answer.trim() => Verdict::Correct,
+            Some(_) => Verdict::Incorrect,
+            None => Verdict::Unknown,
+        }
+    }
+}
+
+fn star(key: &str) -> Option<usize> {
+    match key {
+        "star_one" => Some(1),
+        "star_two" => Some(2),
+        _ => None,
+    }
+}
+
+pub fn parse(manifest: &str) -> Result<Answers, ParseError> {
+    let mut answers = HashMap::new();
+    let mut day = None;
+
+    for (idx, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            day = Some(name.trim().to_owned());
+            continue;
+        }
+
+        let (key, value) = match line.find('=') {
+            Some(split) => (line[..split].trim(), line[split + 1..].trim()),
+            None => return Err(ParseError::InvalidLine(idx + 1, line.to_owned())),
+        };
+        let star = star(key).ok_or_else(|| ParseError::UnknownStar(idx + 1, line.to_owned()))?;
+        let day = day
+            .clone()
+            .ok_or_else(|| ParseError::AnswerWithoutDay(idx + 1, line.to_owned()))?;
+        let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(quoted) => quoted,
+            None if !value.is_empty() && !value.contains(char::is_whitespace) => value,
+            None => return Err(ParseError::InvalidLine(idx + 1, line.to_owned())),
+        };
+
+        answers.insert((day, star), value.to_owned());
+    }
+
+    Ok(Answers { answers })
+}
+
+// The manifest is optional, without one there's nothing to check against
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Answers, ParseError> {
+    match fs::read_to_string(path) {
+        Ok(manifest) => parse(&manifest),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Answers::default()),
+        Err(e) => Err(ParseError::Unreadable(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, parse, ParseError};
+    use std::path::Path;
+    use verify::Verdict;
+
+    static MANIFEST: &str = r#"
+# Answers for my input
+[day01]
+star_one = 510
+star_two = "69074"
+
+[day13]
+star_one = "83,121"
+"#;
+
+    #[test]
+    fn test_parse() {
+        let answers = parse(MANIFEST).unwrap();
+
+        assert_eq!(answers.get("day01", 1), Some("510"));
+        assert_eq!(answers.get("day01", 2), Some("69074"));
+        assert_eq!(answers.get("day13", 1), Some("83,121"));
+        assert_eq!(answers.get("day13", 2), None);
+        assert_eq!(answers.get("day02", 1), None);
+
+        assert_eq!(
+            parse("star_one = 1"),
+            Err(ParseError::AnswerWithoutDay(
+                1,
+                String::from("star_one = 1")
+            ))
+        );
+        assert_eq!(
+            parse("[day01]\nstar_three = 1"),
+            Err(ParseError::UnknownStar(2, String::from("star_three = 1")))
+        );
+        assert_eq!(
+            parse("[day01]\nstar_one 1").unwrap_err().to_string(),
+            "Invalid line 2 `star_one 1`"
+        );
+        assert!(parse("[day01]\nstar_one = 1 2").is_err());
+        assert!(parse("[day01]\nstar_one =").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let answers = parse(MANIFEST).unwrap();
+
+        assert_eq!(answers.check("day01", 1, "510\n"), Verdict::Correct);
+        assert_eq!(answers.check("day01", 2, "510"), Verdict::Incorrect);
+        assert_eq!(answers.check("day02", 1, "510"), Verdict::Unknown);
+    }
+
+    #[test]
+    fn test_load() {
+        assert!(load("does_not_exist.toml").unwrap().is_empty());
+        assert!(load(Path::new(env!("CARGO_MANIFEST_DIR")).join("answers.toml")).is_ok());
+
+        let example = load(Path::new(env!("CARGO_MANIFEST_DIR")).join("answers.toml.example"));
+        assert_eq!(example.unwrap().get("day02", 2), Some("fgij"));
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// State `start` reappears every `period` steps
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cycle {
+    pub start: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    // Where state `step` can be found among the states before the first
+    // repeat.
+    pub fn index(&self, step: usize) -> usize {
+        if step < self.start + self.period {
+            step
+        } else {
+            self.start + (step - self.start) % self.period
+        }
+    }
+}
+
+// Runs until two states have the same key. Returns every state up to but not
+// including the repeat, or `None` if the states run out first.
+pub fn detect<I, K, F>(states: I, key: F) -> Option<(Vec<I::Item>, Cycle)>
+where
+    I: IntoIterator,
+    K: Hash + Eq,
+    F: Fn(&I::Item) -> K,
+{
+    let mut seen = HashMap::<K, usize>::new();
+    let mut history = vec![];
+
+    for (step, state) in states.into_iter().enumerate() {
+        let previous = seen.insert(key(&state), step);
+
+        if let Some(start) = previous {
+            let cycle = Cycle {
+                start,
+                period: step - start,
+            };
+
+            return Some((history, cycle));
+        }
+
+        history.push(state);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, Cycle};
+    use std::iter;
+
+    #[test]
+    fn test_detect() {
+        let states = iter::successors(Some(3_u64), |n| Some(n * n % 20));
+        let (history, cycle) = detect(states, |&n| n).unwrap();
+
+        assert_eq!(history, vec![3, 9, 1]);
+        assert_eq!(
+            cycle,
+            Cycle {
+                start: 2,
+                period: 1
+            }
+        );
+        assert_eq!(detect(vec![1, 2, 3], |&n| n), None);
+    }
+
+    #[test]
+    fn test_index() {
+        let digits = iter::successors(Some(1_u64), |n| Some(n * 10 % 7));
+        let (history, cycle) = detect(digits, |&n| n).unwrap();
+
+        assert_eq!(cycle.period, 6);
+        for step in 0..100 {
+            assert_eq!(
+                history[cycle.index(step)],
+                (0..step).fold(1, |n, _| n * 10 % 7)
+            );
+        }
+    }
+}
@@ -1,19 +1,55 @@
 use std::collections::HashSet;
+use std::fmt;
 
-fn parse<'a>(input: &'a str) -> impl Iterator<Item = i64> + 'a {
+#[derive(Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub token: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid frequency change `{}` on line {}",
+            self.token, self.line
+        )
+    }
+}
+
+// Changes are separated by commas, newlines or any other whitespace, or a mix
+// of them. Line numbers are 1-based to match what editors show.
+fn tokens<'a>(input: &'a str) -> impl Iterator<Item = (usize, &'a str)> + 'a {
     input
-        .split(|c: char| c == ',' || c.is_whitespace())
-        .map(|n| n.trim())
-        .filter(|n| n.len() > 1)
-        .map(|number| number.parse::<i64>().expect("Expected only valid numbers"))
+        .lines()
+        .enumerate()
+        .flat_map(|(idx, line)| {
+            line.split(|c: char| c == ',' || c.is_whitespace())
+                .map(move |token| (idx + 1, token.trim()))
+        })
+        .filter(|(_, token)| !token.is_empty())
+}
+
+pub fn parse_strict(input: &str) -> Result<Vec<i64>, ParseError> {
+    tokens(input)
+        .map(|(line, token)| {
+            token.parse::<i64>().map_err(|_| ParseError {
+                line,
+                token: token.to_owned(),
+            })
+        }).collect()
+}
+
+fn parse(input: &str) -> Vec<i64> {
+    parse_strict(input).unwrap_or_else(|error| panic!("{}", error))
 }
 
 pub fn star_one(input: &str) -> i64 {
-    parse(input).sum()
+    parse(input).iter().sum()
 }
 
 pub fn star_two(input: &str) -> i64 {
-    let instructions = parse(input).collect::<Vec<_>>();
+    let instructions = parse(input);
 
     let mut seen_frequencies = HashSet::new();
     seen_frequencies.insert(0);
@@ -34,7 +70,7 @@ pub fn star_two(input: &str) -> i64 {
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{parse_strict, star_one, star_two, ParseError};
 
     #[test]
     fn test_star_one() {
@@ -51,4 +87,26 @@ mod tests {
         assert_eq!(star_two("-6, +3, +8, +5, -6"), 5);
         assert_eq!(star_two("+7, +7, -2, -7, -4"), 14);
     }
+
+    #[test]
+    fn test_parse_strict() {
+        assert_eq!(parse_strict("+1\n-2\n3"), Ok(vec![1, -2, 3]));
+        assert_eq!(parse_strict("+1, 5,\n-2, +3\n\n"), Ok(vec![1, 5, -2, 3]));
+        assert_eq!(
+            parse_strict("+1, +2\n-3, +x4"),
+            Err(ParseError {
+                line: 2,
+                token: String::from("+x4"),
+            })
+        );
+        assert_eq!(parse_strict("+1 -2 +3"), Ok(vec![1, -2, 3]));
+        assert_eq!(parse_strict("+1\t-2,  +3\n +4 "), Ok(vec![1, -2, 3, 4]));
+        assert_eq!(
+            parse_strict("+1\n+2 + 3"),
+            Err(ParseError {
+                line: 2,
+                token: String::from("+"),
+            })
+        );
+    }
 }
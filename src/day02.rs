@@ -1,12 +1,43 @@
 use std::collections::{HashMap, HashSet};
 
+use rayon::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use parallel;
+
+const PARALLEL_CHUNK_SIZE: usize = 64;
+
+// Ids are compared per extended grapheme cluster rather than per byte or `char`,
+// so `é` counts as a single position regardless of whether it's written
+// precomposed or as `e` followed by a combining accent.
+fn grapheme_spans(id: &str) -> Vec<(usize, usize)> {
+    id.grapheme_indices(true)
+        .map(|(start, grapheme)| (start, start + grapheme.len()))
+        .collect()
+}
+
+fn without_grapheme(id: &str, pos: usize) -> String {
+    id.graphemes(true)
+        .enumerate()
+        .filter(|&(idx, _)| idx != pos)
+        .map(|(_, grapheme)| grapheme)
+        .collect()
+}
+
 fn almost_equal(lhs: &str, rhs: &str) -> Option<usize> {
+    let lhs = lhs.graphemes(true).collect::<Vec<_>>();
+    let rhs = rhs.graphemes(true).collect::<Vec<_>>();
+
+    almost_equal_graphemes(&lhs, &rhs)
+}
+
+fn almost_equal_graphemes(lhs: &[&str], rhs: &[&str]) -> Option<usize> {
     if lhs.len() != rhs.len() {
         return None;
     }
 
     let mut differs_by: Option<usize> = None;
-    for (pos, (r, l)) in rhs.chars().zip(lhs.chars()).enumerate() {
+    for (pos, (r, l)) in rhs.iter().zip(lhs.iter()).enumerate() {
         if r == l {
             continue;
         }
@@ -21,46 +52,131 @@ fn almost_equal(lhs: &str, rhs: &str) -> Option<usize> {
     differs_by
 }
 
-pub fn star_one(input: &str) -> i64 {
-    let counts = input
-        .lines()
-        .map(|id| {
-            let mut map = HashMap::<char, usize>::new();
-
-            id.chars().for_each(|c| {
-                let counter = map.entry(c).or_insert(0);
-
-                *counter += 1
-            });
-
-            let mut found_exactly_two = false;
-            let mut found_exactly_three = false;
-
-            let result = map.iter().fold((0, 0), |acc, (_, &count)| {
-                if count == 3 && !found_exactly_three {
-                    found_exactly_three = true;
-                    (acc.0, acc.1 + 1)
-                } else if count == 2 && !found_exactly_two {
-                    found_exactly_two = true;
-                    (acc.0 + 1, acc.1)
-                } else {
-                    acc
-                }
-            });
-            result
-        }).fold((0, 0), |acc, (two_count, three_count)| {
-            (acc.0 + two_count, acc.1 + three_count)
+pub fn checksum(input: &str, counts: &[usize]) -> usize {
+    let mut matching_ids = vec![0; counts.len()];
+
+    for id in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let mut map = HashMap::<&str, usize>::new();
+
+        id.graphemes(true).for_each(|c| {
+            let counter = map.entry(c).or_insert(0);
+
+            *counter += 1
         });
 
-    counts.0 * counts.1
+        for (idx, count) in counts.iter().enumerate() {
+            if map.values().any(|c| c == count) {
+                matching_ids[idx] += 1;
+            }
+        }
+    }
+
+    matching_ids.into_iter().product()
 }
 
-pub fn star_two(input: &str) -> String {
-    let ids: Vec<_> = input
+pub fn star_one(input: &str) -> usize {
+    checksum(input, &[2, 3])
+}
+
+fn parse_ids(input: &str) -> Vec<&str> {
+    input
         .lines()
-        .filter(|l| l.len() > 0)
-        .map(String::from)
-        .collect();
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+// For every position the ids are hashed with that position blanked out,
+// two distinct ids that collide on the same blanked position differ only there.
+pub fn star_two(input: &str) -> String {
+    let ids = parse_ids(input);
+    let spans = ids.iter().map(|id| grapheme_spans(id)).collect::<Vec<_>>();
+    let longest = spans.iter().map(|s| s.len()).max().unwrap_or(0);
+
+    for pos in 0..longest {
+        let mut seen = HashMap::<(&str, &str), &str>::with_capacity(ids.len());
+
+        for (&id, id_spans) in ids.iter().zip(spans.iter()).filter(|(_, s)| s.len() > pos) {
+            let (start, end) = id_spans[pos];
+            let key = (&id[..start], &id[end..]);
+
+            match seen.insert(key, id) {
+                Some(other) if other != id => return format!("{}{}", key.0, key.1),
+                _ => continue,
+            }
+        }
+    }
+
+    panic!("Expected two ids differing by exactly one character")
+}
+
+pub fn almost_equal_pairs(input: &str) -> Vec<(String, String, usize)> {
+    let ids = parse_ids(input);
+    let spans = ids.iter().map(|id| grapheme_spans(id)).collect::<Vec<_>>();
+    let longest = spans.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut pairs = vec![];
+
+    for pos in 0..longest {
+        let mut groups = HashMap::<(&str, &str), Vec<(usize, &str)>>::new();
+
+        for (idx, &id) in ids
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| spans[idx].len() > pos)
+        {
+            let (start, end) = spans[idx][pos];
+            let group = groups.entry((&id[..start], &id[end..])).or_insert(vec![]);
+
+            for &(other_idx, other) in group.iter() {
+                if other != id {
+                    pairs.push((other_idx, idx, pos));
+                }
+            }
+            group.push((idx, id));
+        }
+    }
+    pairs.sort();
+
+    pairs
+        .into_iter()
+        .map(|(lhs, rhs, pos)| (ids[lhs].to_owned(), ids[rhs].to_owned(), pos))
+        .collect()
+}
+
+// Pairwise search like `star_two_naive`, but with the ids split into chunks
+// that are searched concurrently. When several pairs match the one with the
+// earliest first id is used, no matter which chunk finishes first.
+pub fn star_two_parallel(input: &str) -> String {
+    let ids = parse_ids(input)
+        .into_iter()
+        .map(|id| id.graphemes(true).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let (id, pos) = parallel::install(|| {
+        ids.par_chunks(PARALLEL_CHUNK_SIZE)
+            .enumerate()
+            .find_map_first(|(chunk_idx, chunk)| {
+                chunk.iter().enumerate().find_map(|(idx, id)| {
+                    let offset = chunk_idx * PARALLEL_CHUNK_SIZE + idx + 1;
+
+                    ids[offset..]
+                        .iter()
+                        .find_map(|other| almost_equal_graphemes(id, other))
+                        .map(|pos| (id, pos))
+                })
+            })
+    })
+    .expect("Expected two ids differing by exactly one character");
+
+    id.iter()
+        .enumerate()
+        .filter(|&(idx, _)| idx != pos)
+        .map(|(_, grapheme)| *grapheme)
+        .collect()
+}
+
+pub fn star_two_naive(input: &str) -> String {
+    let ids: Vec<_> = parse_ids(input).into_iter().map(String::from).collect();
     let mut similar_ids = HashSet::<String>::new();
     let mut differ_by = None;
 
@@ -78,15 +194,24 @@ pub fn star_two(input: &str) -> String {
         }
     }
 
-    let mut first = similar_ids.iter().nth(0).unwrap().to_owned();
-    first.remove(differ_by.unwrap());
+    let first = similar_ids.iter().next().unwrap();
 
-    first
+    without_grapheme(first, differ_by.unwrap())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        almost_equal_pairs, checksum, star_one, star_two, star_two_naive, star_two_parallel,
+    };
+
+    static EXAMPLE_TWO: &str = "abcde
+fghij
+klmno
+pqrst
+fguij
+axcye
+wvxyz";
 
     #[test]
     fn test_star_one() {
@@ -96,19 +221,101 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_checksum() {
+        let input = "abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab";
+
+        assert_eq!(checksum(input, &[2, 3]), 12);
+        assert_eq!(checksum(input, &[2]), 4);
+        assert_eq!(checksum(input, &[1, 3]), 18);
+        assert_eq!(checksum(input, &[4]), 0);
+    }
+
     #[test]
     fn test_star_two() {
+        assert_eq!(star_two(EXAMPLE_TWO), "fgij")
+    }
+
+    #[test]
+    fn test_star_two_naive() {
+        assert_eq!(star_two_naive(EXAMPLE_TWO), "fgij")
+    }
+
+    #[test]
+    fn test_star_two_parallel() {
+        assert_eq!(star_two_parallel(EXAMPLE_TWO), "fgij");
+
+        let mut ids = (0..1000)
+            .map(|i| format!("{:04}{:04}", i, i * 7 % 1000))
+            .collect::<Vec<_>>();
+        ids.push(String::from("00500x50"));
+        let input = ids.join("\n");
+
+        assert_eq!(star_two_parallel(&input), "0050050");
+        assert_eq!(star_two(&input), "0050050");
+
+        // A matching pair in every chunk, the first one always wins
+        let ids = (0..1000)
+            .map(|i| match i % 50 {
+                1 => format!("{0:03}{0:03}b", i - 1),
+                _ => format!("{0:03}{0:03}a", i),
+            }).collect::<Vec<_>>();
+        let input = ids.join("\n");
+        let (first, second, pos) = almost_equal_pairs(&input).remove(0);
         assert_eq!(
-            star_two(
-                "abcde
-fghij
-klmno
-pqrst
-fguij
-axcye
-wvxyz"
-            ),
-            "fgij"
-        )
+            (first.as_str(), second.as_str(), pos),
+            ("000000a", "000000b", 6)
+        );
+
+        for _ in 0..20 {
+            assert_eq!(star_two_parallel(&input), "000000");
+        }
+    }
+
+    #[test]
+    fn test_star_two_ignores_duplicates() {
+        assert_eq!(star_two("abcd\nabcd\nxyzw\nxyaw"), "xyw");
+        assert_eq!(star_two_naive("abcd\nabcd\nxyzw\nxyaw"), "xyw");
+    }
+
+    #[test]
+    fn test_almost_equal_pairs() {
+        assert_eq!(
+            almost_equal_pairs(EXAMPLE_TWO),
+            vec![(String::from("fghij"), String::from("fguij"), 2)]
+        );
+        assert_eq!(
+            almost_equal_pairs("abc\nabd\nxyz\nabc\nxbd"),
+            vec![
+                (String::from("abc"), String::from("abd"), 2),
+                (String::from("abd"), String::from("abc"), 2),
+                (String::from("abd"), String::from("xbd"), 0),
+            ]
+        );
+        assert_eq!(almost_equal_pairs("abc\nxyz"), vec![]);
+    }
+
+    #[test]
+    fn test_non_ascii_ids() {
+        let input = "åäöx\nåäüx\nabcd";
+        assert_eq!(star_two(input), "åäx");
+        assert_eq!(star_two_naive(input), "åäx");
+        assert_eq!(star_two_parallel(input), "åäx");
+
+        // `é` as `e` + U+0301 is a single position, as is `e` + U+0300
+        let combining = "ae\u{301}cd\nxyzw\nae\u{300}cd";
+        assert_eq!(star_two(combining), "acd");
+        assert_eq!(star_two_naive(combining), "acd");
+        assert_eq!(
+            almost_equal_pairs(combining),
+            vec![(String::from("ae\u{301}cd"), String::from("ae\u{300}cd"), 1)]
+        );
+
+        // `é` written precomposed has one position, just like `x`
+        assert_eq!(
+            almost_equal_pairs("a\u{e9}c\naxc"),
+            vec![(String::from("a\u{e9}c"), String::from("axc"), 1)]
+        );
+        assert_eq!(checksum("ééa\naab\nxyz", &[2]), 2);
     }
 }
@@ -1,9 +1,158 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
-use regex::Regex;
+use viz::{self, Color};
 
-lazy_static! {
-    static ref PATTERN: Regex = Regex::new(r"#\s*(\d+)\s*@\s*(\d+),(\d+):\s*(\d+)x(\d+)").unwrap();
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ClaimField {
+    Id,
+    Left,
+    Top,
+    Width,
+    Height,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ClaimParseError {
+    // Index of the offending claim in the input, if known
+    pub claim: Option<usize>,
+    pub field: ClaimField,
+    pub input: String,
+}
+
+impl fmt::Display for ClaimParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.claim {
+            Some(idx) => write!(
+                f,
+                "Malformed {:?} in claim {}: `{}`",
+                self.field, idx, self.input
+            ),
+            None => write!(f, "Malformed {:?} in claim: `{}`", self.field, self.input),
+        }
+    }
+}
+
+// Hand-rolled scanner for `#id @ left,top: widthxheight`, whitespace is
+// allowed around the separators.
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            bytes: input.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> bool {
+        self.skip_whitespace();
+
+        if self.bytes.get(self.pos) == Some(&expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let mut value: usize = 0;
+
+        while let Some(digit) = self.bytes.get(self.pos).filter(|b| b.is_ascii_digit()) {
+            value = value
+                .checked_mul(10)?
+                .checked_add((digit - b'0') as usize)?;
+            self.pos += 1;
+        }
+
+        if self.pos == start {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.pos == self.bytes.len()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Rect {
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(left: usize, top: usize, width: usize, height: usize) -> Self {
+        Self {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn right(&self) -> usize {
+        self.left + self.width
+    }
+
+    pub fn bottom(&self) -> usize {
+        self.top + self.height
+    }
+
+    // Edges are half-open, a rect at left 1 with width 2 covers x = 1 and x = 2
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.left && x < self.right() && y >= self.top && y < self.bottom()
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if left < right && top < bottom {
+            Some(Rect::new(left, top, right - left, bottom - top))
+        } else {
+            None
+        }
+    }
+
+    // Every covered (x, y) square inch, row by row
+    pub fn iter_cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (left, right) = (self.left, self.right());
+
+        (self.top..self.bottom()).flat_map(move |y| (left..right).map(move |x| (x, y)))
+    }
+}
+
+#[derive(Debug)]
+pub struct Overlaps {
+    // Disjoint rectangles that together cover every square inch claimed more than once
+    pub regions: Vec<Rect>,
+    // For each claim id, the ids of every other claim it shares at least one square inch with
+    pub intersecting: HashMap<usize, Vec<usize>>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -27,60 +176,232 @@ impl Claim {
     }
 
     pub fn from_string(input: &str) -> Self {
-        let groups = PATTERN.captures(input).unwrap();
+        input
+            .parse::<Claim>()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        Self {
-            id: groups[1].parse::<usize>().expect("Expected an id"),
-            left: groups[2].parse::<usize>().expect("Expected a top value"),
-            top: groups[3].parse::<usize>().expect("Expected a left value"),
-            width: groups[4].parse::<usize>().expect("Expected a width"),
-            height: groups[5].parse::<usize>().expect("Expected a height"),
-        }
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.left, self.top, self.width, self.height)
     }
 
     pub fn area(&self) -> usize {
         self.width * self.height
     }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.rect().contains(x, y)
+    }
+
+    pub fn intersection(&self, other: &Claim) -> Option<Rect> {
+        self.rect().intersection(&other.rect())
+    }
+
+    pub fn iter_cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.rect().iter_cells()
+    }
 }
 
-pub fn star_one(input: &str) -> usize {
-    let claims = input
+impl FromStr for Claim {
+    type Err = ClaimParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut scanner = Scanner::new(input);
+        let error = |field| ClaimParseError {
+            claim: None,
+            field,
+            input: input.to_owned(),
+        };
+
+        if !scanner.expect(b'#') {
+            return Err(error(ClaimField::Id));
+        }
+        let id = scanner.number().ok_or_else(|| error(ClaimField::Id))?;
+
+        if !scanner.expect(b'@') {
+            return Err(error(ClaimField::Left));
+        }
+        let left = scanner.number().ok_or_else(|| error(ClaimField::Left))?;
+
+        if !scanner.expect(b',') {
+            return Err(error(ClaimField::Top));
+        }
+        let top = scanner.number().ok_or_else(|| error(ClaimField::Top))?;
+
+        if !scanner.expect(b':') {
+            return Err(error(ClaimField::Width));
+        }
+        let width = scanner.number().ok_or_else(|| error(ClaimField::Width))?;
+
+        if !scanner.expect(b'x') {
+            return Err(error(ClaimField::Height));
+        }
+        let height = scanner.number().ok_or_else(|| error(ClaimField::Height))?;
+
+        if !scanner.at_end() {
+            return Err(error(ClaimField::Height));
+        }
+
+        Ok(Claim::new(id, left, top, width, height))
+    }
+}
+
+pub fn parse_claims(input: &str) -> Result<Vec<Claim>, ClaimParseError> {
+    input
         .lines()
-        .filter(|l| l.len() > 0)
-        .map(Claim::from_string);
-    let mut coverage = HashMap::<(usize, usize), usize>::new();
+        .filter(|l| !l.trim().is_empty())
+        .enumerate()
+        .map(|(idx, line)| {
+            line.parse::<Claim>().map_err(|error| ClaimParseError {
+                claim: Some(idx),
+                ..error
+            })
+        }).collect()
+}
+
+fn parse(input: &str) -> Vec<Claim> {
+    parse_claims(input).unwrap_or_else(|error| panic!("{}", error))
+}
+
+fn compressed_coordinates<F>(claims: &[Claim], edges: F) -> Vec<usize>
+where
+    F: Fn(&Claim) -> (usize, usize),
+{
+    let mut coordinates = claims
+        .iter()
+        .flat_map(|claim| {
+            let (start, end) = edges(claim);
+            vec![start, end]
+        }).collect::<Vec<_>>();
+    coordinates.sort();
+    coordinates.dedup();
+
+    coordinates
+}
+
+// Compresses the fabric to the distinct claim edges and uses a 2D difference
+// array over the compressed cells, so the cost depends on the number of
+// claims rather than on how many square inches they cover.
+fn overlap_regions(claims: &[Claim]) -> Vec<Rect> {
+    let xs = compressed_coordinates(claims, |c| (c.left, c.left + c.width));
+    let ys = compressed_coordinates(claims, |c| (c.top, c.top + c.height));
+    let mut coverage = vec![vec![0i64; ys.len()]; xs.len()];
 
     for claim in claims {
-        for x in (claim.left + 1)..(claim.left + claim.width + 1) {
-            for y in (claim.top + 1)..(claim.top + claim.height + 1) {
-                let counter = coverage.entry((x, y)).or_insert(0);
+        let x0 = xs.binary_search(&claim.left).unwrap();
+        let x1 = xs.binary_search(&(claim.left + claim.width)).unwrap();
+        let y0 = ys.binary_search(&claim.top).unwrap();
+        let y1 = ys.binary_search(&(claim.top + claim.height)).unwrap();
+
+        coverage[x0][y0] += 1;
+        coverage[x1][y0] -= 1;
+        coverage[x0][y1] -= 1;
+        coverage[x1][y1] += 1;
+    }
+
+    for x in 0..xs.len() {
+        for y in 0..ys.len() {
+            let left = if x > 0 { coverage[x - 1][y] } else { 0 };
+            let above = if y > 0 { coverage[x][y - 1] } else { 0 };
+            let diagonal = if x > 0 && y > 0 {
+                coverage[x - 1][y - 1]
+            } else {
+                0
+            };
+
+            coverage[x][y] += left + above - diagonal;
+        }
+    }
+
+    // Vertically adjacent overlapping cells in the same column are merged
+    let mut regions = vec![];
+    for x in 0..xs.len().saturating_sub(1) {
+        let mut run_start: Option<usize> = None;
+
+        for y in 0..ys.len() {
+            let overlapping = y + 1 < ys.len() && coverage[x][y] > 1;
 
-                *counter += 1;
+            match (overlapping, run_start) {
+                (true, None) => run_start = Some(y),
+                (false, Some(start)) => {
+                    regions.push(Rect::new(
+                        xs[x],
+                        ys[start],
+                        xs[x + 1] - xs[x],
+                        ys[y] - ys[start],
+                    ));
+                    run_start = None;
+                }
+                _ => (),
             }
         }
     }
 
+    regions
+}
+
+pub fn overlaps(input: &str) -> Overlaps {
+    let claims = parse(input);
+    let mut intersecting = claims
+        .iter()
+        .map(|claim| (claim.id, vec![]))
+        .collect::<HashMap<_, _>>();
+
+    for (idx, claim) in claims.iter().enumerate() {
+        let others = claims[idx + 1..]
+            .iter()
+            .filter(|other| claim.intersection(other).is_some());
+
+        for other in others {
+            intersecting.get_mut(&claim.id).unwrap().push(other.id);
+            intersecting.get_mut(&other.id).unwrap().push(claim.id);
+        }
+    }
+    intersecting.values_mut().for_each(|ids| ids.sort());
+
+    Overlaps {
+        regions: overlap_regions(&claims),
+        intersecting,
+    }
+}
+
+pub fn star_one(input: &str) -> usize {
+    let claims = parse(input);
+
+    overlap_regions(&claims).iter().map(|r| r.area()).sum()
+}
+
+pub fn star_one_hashed(input: &str) -> usize {
+    let claims = parse(input);
+    let mut coverage = HashMap::<(usize, usize), usize>::new();
+
+    for claim in &claims {
+        for cell in claim.iter_cells() {
+            let counter = coverage.entry(cell).or_insert(0);
+
+            *counter += 1;
+        }
+    }
+
     coverage
         .iter()
         .fold(0, |acc, (_, &count)| if count > 1 { acc + 1 } else { acc })
 }
 
 pub fn star_two(input: &str) -> usize {
-    let claims: Vec<Claim> = input
-        .lines()
-        .filter(|l| l.len() > 0)
-        .map(Claim::from_string)
-        .collect();
+    let claims = parse(input);
     let mut coverage = HashMap::<(usize, usize), (usize, HashSet<usize>)>::new();
 
     for claim in &claims {
-        for x in (claim.left + 1)..(claim.left + claim.width + 1) {
-            for y in (claim.top + 1)..(claim.top + claim.height + 1) {
-                let counter = coverage.entry((x, y)).or_insert((0, HashSet::new()));
+        for cell in claim.iter_cells() {
+            let counter = coverage.entry(cell).or_insert((0, HashSet::new()));
 
-                (*counter).0 += 1;
-                (*counter).1.insert(claim.id);
-            }
+            counter.0 += 1;
+            counter.1.insert(claim.id);
         }
     }
 
@@ -101,6 +422,108 @@ pub fn star_two(input: &str) -> usize {
     0
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Fabric {
+    Unclaimed,
+    Claimed,
+    Overlapping,
+    IntactEdge,
+    Intact,
+}
+
+impl Fabric {
+    fn to_char(self) -> char {
+        match self {
+            Fabric::Unclaimed => '.',
+            Fabric::Claimed => '#',
+            Fabric::Overlapping => 'X',
+            Fabric::IntactEdge => 'O',
+            Fabric::Intact => 'o',
+        }
+    }
+
+    fn to_color(self) -> Color {
+        match self {
+            Fabric::Unclaimed => (255, 255, 255),
+            Fabric::Claimed => (160, 160, 160),
+            Fabric::Overlapping => (200, 30, 30),
+            Fabric::IntactEdge => (20, 140, 20),
+            Fabric::Intact => (140, 220, 140),
+        }
+    }
+}
+
+// Rows of fabric, claims that don't overlap anything are drawn with an outline
+fn fabric(input: &str) -> Vec<Vec<Fabric>> {
+    let claims = parse(input);
+    let intersecting = overlaps(input).intersecting;
+    let width = claims.iter().map(|c| c.left + c.width).max().unwrap_or(0);
+    let height = claims.iter().map(|c| c.top + c.height).max().unwrap_or(0);
+    let mut grid = vec![vec![Fabric::Unclaimed; width]; height];
+
+    for claim in &claims {
+        for row in &mut grid[claim.top..(claim.top + claim.height)] {
+            for cell in &mut row[claim.left..(claim.left + claim.width)] {
+                *cell = match cell {
+                    Fabric::Unclaimed => Fabric::Claimed,
+                    _ => Fabric::Overlapping,
+                };
+            }
+        }
+    }
+
+    for claim in claims.iter().filter(|c| intersecting[&c.id].is_empty()) {
+        let rows = grid
+            .iter_mut()
+            .enumerate()
+            .skip(claim.top)
+            .take(claim.height);
+
+        for (y, row) in rows {
+            let cells = row
+                .iter_mut()
+                .enumerate()
+                .skip(claim.left)
+                .take(claim.width);
+
+            for (x, cell) in cells {
+                let is_edge = x == claim.left
+                    || y == claim.top
+                    || x == claim.left + claim.width - 1
+                    || y == claim.top + claim.height - 1;
+
+                *cell = if is_edge {
+                    Fabric::IntactEdge
+                } else {
+                    Fabric::Intact
+                };
+            }
+        }
+    }
+
+    grid
+}
+
+pub fn render(input: &str) -> String {
+    fabric(input)
+        .iter()
+        .map(|row| row.iter().map(|&f| f.to_char()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn render_image(input: &str) -> Vec<u8> {
+    let grid = fabric(input);
+    let height = grid.len();
+    let width = grid.first().map(|row| row.len()).unwrap_or(0);
+    let pixels = grid
+        .iter()
+        .flat_map(|row| row.iter().map(|&f| f.to_color()))
+        .collect::<Vec<_>>();
+
+    viz::ppm(width, height, &pixels)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,11 +533,141 @@ mod tests {
         assert_eq!(star_one("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2"), 4)
     }
 
+    #[test]
+    fn test_star_one_hashed() {
+        assert_eq!(
+            star_one_hashed("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2"),
+            4
+        )
+    }
+
+    #[test]
+    fn test_star_one_nested_claims() {
+        let input = "#1 @ 0,0: 10x10\n#2 @ 2,2: 3x3\n#3 @ 3,3: 5x1\n#4 @ 9,9: 1x1\n#5 @ 20,0: 1x1";
+
+        assert_eq!(star_one(input), 13);
+        assert_eq!(star_one_hashed(input), 13);
+    }
+
     #[test]
     fn test_star_two() {
         assert_eq!(star_two("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2"), 3)
     }
 
+    #[test]
+    fn test_overlaps() {
+        let result = overlaps("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 4,4: 1x2");
+
+        assert_eq!(
+            result.regions,
+            vec![Rect::new(3, 3, 1, 2), Rect::new(4, 3, 1, 3)]
+        );
+        assert_eq!(result.intersecting[&1], vec![2, 4]);
+        assert_eq!(result.intersecting[&2], vec![1, 4]);
+        assert_eq!(result.intersecting[&3], vec![]);
+        assert_eq!(result.intersecting[&4], vec![1, 2]);
+    }
+
+    #[test]
+    fn test_render() {
+        let expected = "........
+...####.
+...####.
+.##XX##.
+.##XX##.
+.####OOO
+.####OoO
+.....OOO";
+
+        assert_eq!(
+            render("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 3x3"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_render_image() {
+        let image = render_image("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2");
+
+        assert!(image.starts_with(b"P6\n7 7\n255\n"));
+        assert_eq!(image.len(), 11 + 7 * 7 * 3);
+    }
+
+    #[test]
+    fn test_claim_from_str() {
+        assert_eq!(
+            "  #7@1,2:3x4  ".parse::<Claim>(),
+            Ok(Claim::new(7, 1, 2, 3, 4))
+        );
+        assert_eq!(
+            "#123 @ 3,2: 5x4".parse::<Claim>(),
+            Ok(Claim::new(123, 3, 2, 5, 4))
+        );
+
+        let fields = [
+            ("123 @ 3,2: 5x4", ClaimField::Id),
+            ("#123 3,2: 5x4", ClaimField::Left),
+            ("#123 @ 3;2: 5x4", ClaimField::Top),
+            ("#123 @ 3,: 5x4", ClaimField::Top),
+            ("#123 @ 3,2 5x4", ClaimField::Width),
+            ("#123 @ 3,2: 5*4", ClaimField::Height),
+            ("#123 @ 3,2: 5x4x", ClaimField::Height),
+            ("#123 @ 3,2: 5x99999999999999999999999", ClaimField::Height),
+        ];
+
+        for &(input, field) in fields.iter() {
+            assert_eq!(
+                input.parse::<Claim>(),
+                Err(ClaimParseError {
+                    claim: None,
+                    field,
+                    input: String::from(input),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_claims() {
+        assert_eq!(
+            parse_claims("#1 @ 1,3: 4x4\n\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2"),
+            Err(ClaimParseError {
+                claim: Some(2),
+                field: ClaimField::Height,
+                input: String::from("#3 @ 5,5: 2"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_rect_operations() {
+        let lhs = Claim::new(1, 1, 3, 4, 4);
+        let rhs = Claim::new(2, 3, 1, 4, 4);
+        let other = Claim::new(3, 5, 5, 2, 2);
+
+        assert_eq!(lhs.intersection(&rhs), Some(Rect::new(3, 3, 2, 2)));
+        assert_eq!(rhs.intersection(&lhs), Some(Rect::new(3, 3, 2, 2)));
+        assert_eq!(lhs.intersection(&other), None);
+        // Touching edges don't share any square inches
+        assert_eq!(
+            Rect::new(0, 0, 2, 2).intersection(&Rect::new(2, 0, 2, 2)),
+            None
+        );
+
+        assert!(lhs.contains(1, 3));
+        assert!(lhs.contains(4, 6));
+        assert!(!lhs.contains(5, 6));
+        assert!(!lhs.contains(4, 7));
+        assert!(!lhs.contains(0, 3));
+
+        assert_eq!(
+            Rect::new(1, 2, 2, 2).iter_cells().collect::<Vec<_>>(),
+            vec![(1, 2), (2, 2), (1, 3), (2, 3)]
+        );
+        assert_eq!(other.iter_cells().count(), other.area());
+        assert!(lhs.iter_cells().all(|(x, y)| lhs.contains(x, y)));
+    }
+
     #[test]
     fn test_claim_from_string() {
         assert_eq!(
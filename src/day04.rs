@@ -1,61 +1,131 @@
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Sub;
 
 lazy_static! {
     static ref PATTERN: Regex = Regex::new(r"\[\s*(\d+)\-(\d+)\-(\d+)\s+(\d+):(\d+)\s*").unwrap();
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-struct DateTime {
-    year: usize,
-    month: usize,
-    day: usize,
-    hour: usize,
-    minute: usize,
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+// Days between 1970-01-01 and the given date in the proleptic Gregorian
+// calendar, and the inverse below. See Howard Hinnant's `chrono`-compatible
+// date algorithms for the derivation.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
 }
 
-impl<'a> From<&'a str> for DateTime {
-    fn from(input: &'a str) -> Self {
-        let groups = PATTERN
-            .captures(input)
-            .expect("Expected all date times to match the regex.");
-        assert!(
-            groups.len() == 6,
-            "Expected six groups for each input found {} for {}",
-            groups.len(),
-            input
-        );
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
 
-        Self {
-            year: groups[1].parse::<usize>().expect("Expected a valid year"),
-            month: groups[2].parse::<usize>().expect("Expected a valid month"),
-            day: groups[3].parse::<usize>().expect("Expected a valid day"),
-            hour: groups[4].parse::<usize>().expect("Expected a valid hour"),
-            minute: groups[5].parse::<usize>().expect("Expected a valid minute"),
-        }
+fn days_in_month(year: usize, month: usize) -> usize {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
     }
 }
 
-impl Ord for DateTime {
-    fn cmp(&self, other: &DateTime) -> Ordering {
-        let components = [self.year, self.month, self.day, self.hour, self.minute];
-        let other_components = [other.year, other.month, other.day, other.hour, other.minute];
+// Minutes since 1970-01-01 00:00, negative for dates before that
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DateTime(i64);
+
+impl DateTime {
+    pub fn new(year: usize, month: usize, day: usize, hour: usize, minute: usize) -> Self {
+        let days = days_from_civil(year as i64, month as i64, day as i64);
+
+        DateTime(days * MINUTES_PER_DAY + (hour * 60 + minute) as i64)
+    }
+
+    pub fn from_minutes(minutes_since_epoch: i64) -> Self {
+        DateTime(minutes_since_epoch)
+    }
+
+    pub fn minutes_since_epoch(&self) -> i64 {
+        self.0
+    }
+
+    pub fn date(&self) -> (usize, usize, usize) {
+        let (year, month, day) = civil_from_days(self.0.div_euclid(MINUTES_PER_DAY));
+
+        (year as usize, month as usize, day as usize)
+    }
 
-        components
-            .into_iter()
-            .zip(other_components.into_iter())
-            .map(|(lhs, rhs)| lhs.cmp(rhs))
-            .skip_while(|&order| order == Ordering::Equal)
-            .nth(0)
-            .unwrap_or(Ordering::Equal)
+    pub fn hour(&self) -> usize {
+        (self.0.rem_euclid(MINUTES_PER_DAY) / 60) as usize
+    }
+
+    pub fn minute(&self) -> usize {
+        self.0.rem_euclid(60) as usize
+    }
+
+    fn parse(input: &str) -> Option<Self> {
+        let groups = PATTERN.captures(input)?;
+        let year = groups[1].parse::<usize>().ok()?;
+        let month = groups[2]
+            .parse::<usize>()
+            .ok()
+            .filter(|m| (1..=12).contains(m))?;
+        let day = groups[3]
+            .parse::<usize>()
+            .ok()
+            .filter(|d| (1..=days_in_month(year, month)).contains(d))?;
+        let hour = groups[4].parse::<usize>().ok().filter(|&h| h < 24)?;
+        let minute = groups[5].parse::<usize>().ok().filter(|&m| m < 60)?;
+
+        Some(Self::new(year, month, day, hour, minute))
+    }
+}
+
+impl Sub for DateTime {
+    // Duration in minutes
+    type Output = i64;
+
+    fn sub(self, other: DateTime) -> i64 {
+        self.0 - other.0
     }
 }
 
-impl PartialOrd for DateTime {
-    fn partial_cmp(&self, other: &DateTime) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = self.date();
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            year,
+            month,
+            day,
+            self.hour(),
+            self.minute()
+        )
     }
 }
 
@@ -66,28 +136,25 @@ enum Event {
     StartShift { id: usize },
 }
 
-impl<'a> From<&'a str> for Event {
-    fn from(input: &'a str) -> Self {
+impl Event {
+    fn parse(input: &str) -> Option<Self> {
         if input.contains("falls asleep") {
-            Event::FellAsleep
+            Some(Event::FellAsleep)
         } else if input.contains("wakes up") {
-            Event::WokeUp
-        } else {
+            Some(Event::WokeUp)
+        } else if input.contains("begins shift") {
             let id = input
-                .split("#")
-                .nth(1)
-                .expect(&format!(
-                    "Expected a parsable guard id, but found none in {}",
-                    input
-                )).chars()
+                .split('#')
+                .nth(1)?
+                .chars()
                 .take_while(|c| c.is_numeric())
                 .collect::<String>()
                 .parse::<usize>()
-                .expect(&format!(
-                    "Expected a parsable guard id, but found none in {}",
-                    input
-                ));
-            Event::StartShift { id: id }
+                .ok()?;
+
+            Some(Event::StartShift { id })
+        } else {
+            None
         }
     }
 }
@@ -98,138 +165,292 @@ struct Record {
     event: Event,
 }
 
-impl<'a> From<&'a str> for Record {
-    fn from(input: &str) -> Self {
-        let parts = input.split("]").collect::<Vec<_>>();
-        assert!(
-            parts.len() == 2,
-            "Each record should have two parts when split at `]`. Found {} for {}",
-            input.len(),
-            input
-        );
+impl Record {
+    fn parse(input: &str) -> Option<Self> {
+        let mut parts = input.split(']');
+        let at = DateTime::parse(parts.next()?)?;
+        let event = Event::parse(parts.next()?)?;
 
-        Self {
-            at: DateTime::from(parts[0]),
-            event: Event::from(parts[1]),
+        if parts.next().is_some() {
+            return None;
         }
+
+        Some(Self { at, event })
     }
 }
 
-fn parse(input: &str) -> Vec<Record> {
-    let mut records = input
-        .lines()
-        .filter(|l| l.len() > 0)
-        .map(Record::from)
-        .collect::<Vec<_>>();
-    records.sort_by_key(|r| r.at);
-
-    records
+#[derive(Debug, Eq, PartialEq)]
+pub enum RecordError {
+    Malformed { line: usize, record: String },
+    DuplicateTimestamp(DateTime),
+    NoGuardOnDuty(DateTime),
+    WokeUpWithoutFallingAsleep { guard: usize, at: DateTime },
+    FellAsleepTwice { guard: usize, at: DateTime },
+    AsleepAtShiftChange { guard: usize, at: DateTime },
+    NeverWokeUp { guard: usize, since: DateTime },
+    AsleepOutsideMidnightHour { guard: usize, since: DateTime },
 }
 
-pub fn star_one(input: &str) -> usize {
-    let records = parse(input);
-
-    let mut total_minutes_asleep = HashMap::<usize, usize>::new();
-    let mut asleep_per_minute_count = HashMap::<usize, Vec<usize>>::new();
-    let mut current_asleep_record: Option<Record> = None;
-    let mut active_guard_id: Option<usize> = None;
-
-    for record in records {
-        match record.event {
-            Event::WokeUp => {
-                let asleep_record =
-                    current_asleep_record.expect("Someone must be asleep before waking up");
-
-                match asleep_record.event {
-                    Event::FellAsleep => {
-                        let counter = total_minutes_asleep
-                            .entry(
-                                active_guard_id
-                                    .expect("Can't wake up with no active guard on duty"),
-                            ).or_insert(0);
-
-                        *counter += record.at.minute - asleep_record.at.minute - 1;
-                        let per_minute_count = asleep_per_minute_count
-                            .entry(
-                                active_guard_id
-                                    .expect("Can't wake up with no active guard on duty"),
-                            ).or_insert(vec![0; 60]);
-                        (asleep_record.at.minute..record.at.minute).for_each(|minute| {
-                            per_minute_count[minute] += 1;
-                        });
-                        current_asleep_record = None;
-                    }
-                    _ => assert!(false, "Invalid asleep record {:?}", asleep_record),
-                }
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::Malformed { line, record } => {
+                write!(f, "Malformed record on line {}: `{}`", line, record)
             }
-            Event::FellAsleep => {
-                current_asleep_record = Some(record.clone());
+            RecordError::DuplicateTimestamp(at) => {
+                write!(f, "More than one record at {}", at)
             }
-            Event::StartShift { id } => {
-                active_guard_id = Some(id);
+            RecordError::NoGuardOnDuty(at) => {
+                write!(f, "Sleep event at {} before any guard began a shift", at)
             }
+            RecordError::WokeUpWithoutFallingAsleep { guard, at } => write!(
+                f,
+                "Guard #{} woke up at {} without having fallen asleep",
+                guard, at
+            ),
+            RecordError::FellAsleepTwice { guard, at } => write!(
+                f,
+                "Guard #{} fell asleep at {} while already asleep",
+                guard, at
+            ),
+            RecordError::AsleepAtShiftChange { guard, at } => write!(
+                f,
+                "Guard #{} was still asleep when the next shift began at {}",
+                guard, at
+            ),
+            RecordError::NeverWokeUp { guard, since } => write!(
+                f,
+                "Guard #{} fell asleep at {} and never woke up",
+                guard, since
+            ),
+            RecordError::AsleepOutsideMidnightHour { guard, since } => write!(
+                f,
+                "Guard #{} fell asleep at {} and woke up before the next midnight hour",
+                guard, since
+            ),
         }
     }
+}
 
-    let (id, _) = total_minutes_asleep
-        .iter()
-        .max_by_key(|(_, &minutes)| minutes)
-        .unwrap();
+// Records are kept ordered by timestamp as they are inserted, so there's no
+// separate sorting step and duplicates are caught on insertion.
+fn parse(input: &str) -> Result<BTreeMap<DateTime, Event>, RecordError> {
+    let mut records = BTreeMap::new();
 
-    let (most_slept_minute, _) = asleep_per_minute_count
-        .get(&id)
-        .unwrap()
-        .iter()
-        .enumerate()
-        .max_by_key(|(_, &count)| count)
-        .unwrap();
+    for (idx, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
 
-    id * most_slept_minute
+        let record = Record::parse(line).ok_or_else(|| RecordError::Malformed {
+            line: idx + 1,
+            record: line.to_owned(),
+        })?;
+
+        if records.insert(record.at, record.event).is_some() {
+            return Err(RecordError::DuplicateTimestamp(record.at));
+        }
+    }
+
+    Ok(records)
 }
 
-pub fn star_two(input: &str) -> usize {
-    let records = parse(input);
-
-    let mut asleep_per_minute_count = HashMap::<usize, Vec<usize>>::new();
-    let mut current_asleep_record: Option<Record> = None;
-    let mut active_guard_id: Option<usize> = None;
-
-    for record in records {
-        match record.event {
-            Event::WokeUp => {
-                let asleep_record =
-                    current_asleep_record.expect("Someone must be asleep before waking up");
-
-                match asleep_record.event {
-                    Event::FellAsleep => {
-                        let per_minute_count = asleep_per_minute_count
-                            .entry(
-                                active_guard_id
-                                    .expect("Can't wake up with no active guard on duty"),
-                            ).or_insert(vec![0; 60]);
-                        (asleep_record.at.minute..record.at.minute).for_each(|minute| {
-                            per_minute_count[minute] += 1;
+// A nap from the minute the guard fell asleep up to, but not including, the
+// minute they woke up.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Nap {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Nap {
+    pub fn duration(&self) -> usize {
+        self.end - self.start
+    }
+
+    // Only the midnight hour matters, so sleeping from `asleep_at` until
+    // `woke_up_at` is one nap for every midnight hour that overlaps it.
+    fn split(asleep_at: DateTime, woke_up_at: DateTime) -> Vec<Nap> {
+        let from = asleep_at.minutes_since_epoch();
+        let to = woke_up_at.minutes_since_epoch();
+        let first_day = from.div_euclid(MINUTES_PER_DAY);
+        let last_day = (to - 1).div_euclid(MINUTES_PER_DAY);
+
+        (first_day..=last_day)
+            .filter_map(|day| {
+                let midnight = day * MINUTES_PER_DAY;
+                let start = (from - midnight).max(0);
+                let end = (to - midnight).min(60);
+
+                if start < end {
+                    Some(Nap {
+                        start: start as usize,
+                        end: end as usize,
+                    })
+                } else {
+                    None
+                }
+            }).collect()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Shift {
+    pub guard: usize,
+    pub started_at: DateTime,
+    pub naps: Vec<Nap>,
+}
+
+#[derive(Debug)]
+pub struct GuardSchedule {
+    shifts: Vec<Shift>,
+    sleep_per_minute: HashMap<usize, Vec<usize>>,
+    total_minutes_asleep: HashMap<usize, usize>,
+}
+
+impl GuardSchedule {
+    pub fn parse(input: &str) -> Result<Self, RecordError> {
+        Self::from_records(&parse(input)?)
+    }
+
+    fn from_records(records: &BTreeMap<DateTime, Event>) -> Result<Self, RecordError> {
+        let mut shifts: Vec<Shift> = vec![];
+        let mut fell_asleep_at: Option<DateTime> = None;
+
+        for (&at, event) in records {
+            match *event {
+                Event::StartShift { id } => {
+                    if let (Some(shift), Some(_)) = (shifts.last(), fell_asleep_at) {
+                        return Err(RecordError::AsleepAtShiftChange {
+                            guard: shift.guard,
+                            at,
                         });
-                        current_asleep_record = None;
                     }
-                    _ => assert!(false, "Invalid asleep record {:?}", asleep_record),
+
+                    shifts.push(Shift {
+                        guard: id,
+                        started_at: at,
+                        naps: vec![],
+                    });
+                }
+                Event::FellAsleep => {
+                    let guard = shifts.last().ok_or(RecordError::NoGuardOnDuty(at))?.guard;
+
+                    if fell_asleep_at.is_some() {
+                        return Err(RecordError::FellAsleepTwice { guard, at });
+                    }
+
+                    fell_asleep_at = Some(at);
+                }
+                Event::WokeUp => {
+                    let shift = shifts.last_mut().ok_or(RecordError::NoGuardOnDuty(at))?;
+                    let asleep_at =
+                        fell_asleep_at.ok_or(RecordError::WokeUpWithoutFallingAsleep {
+                            guard: shift.guard,
+                            at,
+                        })?;
+                    let naps = Nap::split(asleep_at, at);
+
+                    if naps.is_empty() {
+                        return Err(RecordError::AsleepOutsideMidnightHour {
+                            guard: shift.guard,
+                            since: asleep_at,
+                        });
+                    }
+                    shift.naps.extend(naps);
+                    fell_asleep_at = None;
                 }
             }
-            Event::FellAsleep => {
-                current_asleep_record = Some(record.clone());
-            }
-            Event::StartShift { id } => {
-                active_guard_id = Some(id);
+        }
+
+        if let (Some(shift), Some(since)) = (shifts.last(), fell_asleep_at) {
+            return Err(RecordError::NeverWokeUp {
+                guard: shift.guard,
+                since,
+            });
+        }
+
+        let mut sleep_per_minute = HashMap::<usize, Vec<usize>>::new();
+        let mut total_minutes_asleep = HashMap::<usize, usize>::new();
+
+        for shift in &shifts {
+            let per_minute_count = sleep_per_minute.entry(shift.guard).or_insert(vec![0; 60]);
+            let total = total_minutes_asleep.entry(shift.guard).or_insert(0);
+
+            for nap in &shift.naps {
+                (nap.start..nap.end).for_each(|minute| per_minute_count[minute] += 1);
+                *total += nap.duration();
             }
         }
+
+        Ok(Self {
+            shifts,
+            sleep_per_minute,
+            total_minutes_asleep,
+        })
+    }
+
+    pub fn shifts(&self) -> &[Shift] {
+        &self.shifts
     }
 
-    let (id, (most_slept_minute, _)) = asleep_per_minute_count
-        .iter()
-        .map(|(id, minutes)| {
-            let result = minutes.iter().enumerate().max_by_key(|&(_, c)| c).unwrap();
-            (id, result)
-        }).max_by_key(|(_, (_, &c))| c)
+    // Sorted ids of every guard that worked at least one shift
+    pub fn guards(&self) -> Vec<usize> {
+        let mut guards = self.sleep_per_minute.keys().cloned().collect::<Vec<_>>();
+        guards.sort();
+
+        guards
+    }
+
+    // How many times the guard was asleep during each minute of the midnight hour
+    pub fn sleep_per_minute(&self, guard: usize) -> Option<&[usize]> {
+        self.sleep_per_minute.get(&guard).map(|counts| &counts[..])
+    }
+
+    pub fn total_minutes_asleep(&self, guard: usize) -> usize {
+        self.total_minutes_asleep.get(&guard).cloned().unwrap_or(0)
+    }
+
+    // The minute the guard was most often asleep and how many times
+    pub fn most_slept_minute(&self, guard: usize) -> Option<(usize, usize)> {
+        self.sleep_per_minute(guard).and_then(|counts| {
+            counts
+                .iter()
+                .cloned()
+                .enumerate()
+                .max_by_key(|&(_, count)| count)
+        })
+    }
+
+    pub fn naps(&self, guard: usize) -> impl Iterator<Item = &Nap> {
+        self.shifts
+            .iter()
+            .filter(move |shift| shift.guard == guard)
+            .flat_map(|shift| shift.naps.iter())
+    }
+}
+
+pub fn star_one(input: &str) -> usize {
+    let schedule = GuardSchedule::parse(input).unwrap_or_else(|error| panic!("{}", error));
+
+    let id = schedule
+        .guards()
+        .into_iter()
+        .max_by_key(|&guard| schedule.total_minutes_asleep(guard))
+        .unwrap();
+    let (most_slept_minute, _) = schedule.most_slept_minute(id).unwrap();
+
+    id * most_slept_minute
+}
+
+pub fn star_two(input: &str) -> usize {
+    let schedule = GuardSchedule::parse(input).unwrap_or_else(|error| panic!("{}", error));
+
+    let (id, (most_slept_minute, _)) = schedule
+        .guards()
+        .into_iter()
+        .map(|guard| (guard, schedule.most_slept_minute(guard).unwrap()))
+        .max_by_key(|&(_, (_, count))| count)
         .unwrap();
 
     id * most_slept_minute
@@ -237,7 +458,7 @@ pub fn star_two(input: &str) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{star_one, star_two, DateTime, GuardSchedule, Nap, RecordError};
     static EXAMPLE: &'static str = r#"
 [1518-11-01 00:30] falls asleep
 [1518-11-01 00:00] Guard #10 begins shift
@@ -267,4 +488,209 @@ mod tests {
     fn test_star_two() {
         assert_eq!(star_two(EXAMPLE), 4455)
     }
+
+    #[test]
+    fn test_guard_schedule() {
+        let schedule = GuardSchedule::parse(EXAMPLE).unwrap();
+
+        assert_eq!(schedule.guards(), vec![10, 99]);
+        assert_eq!(schedule.shifts().len(), 5);
+        assert_eq!(schedule.total_minutes_asleep(10), 50);
+        assert_eq!(schedule.total_minutes_asleep(99), 30);
+        assert_eq!(schedule.total_minutes_asleep(42), 0);
+        assert_eq!(schedule.most_slept_minute(10), Some((24, 2)));
+        assert_eq!(schedule.most_slept_minute(99), Some((45, 3)));
+        assert_eq!(schedule.sleep_per_minute(99).map(|c| c[44]), Some(2));
+        assert_eq!(
+            schedule.naps(10).collect::<Vec<_>>(),
+            vec![
+                &Nap { start: 5, end: 25 },
+                &Nap { start: 30, end: 55 },
+                &Nap { start: 24, end: 29 },
+            ]
+        );
+
+        let (count, total) = schedule.naps(99).fold((0, 0), |(count, total), nap| {
+            (count + 1, total + nap.duration())
+        });
+        assert_eq!(total / count, 10);
+    }
+
+    fn at(day: usize, hour: usize, minute: usize) -> DateTime {
+        DateTime::new(1518, 11, day, hour, minute)
+    }
+
+    #[test]
+    fn test_date_time() {
+        assert_eq!(DateTime::new(1970, 1, 1, 0, 0).minutes_since_epoch(), 0);
+        assert_eq!(
+            DateTime::new(1970, 1, 2, 1, 1).minutes_since_epoch(),
+            1440 + 61
+        );
+        assert_eq!(
+            DateTime::new(1969, 12, 31, 23, 59).minutes_since_epoch(),
+            -1
+        );
+
+        let date_time = DateTime::new(1518, 2, 28, 23, 58);
+        assert_eq!(date_time.date(), (1518, 2, 28));
+        assert_eq!((date_time.hour(), date_time.minute()), (23, 58));
+        assert_eq!(date_time.to_string(), "1518-02-28 23:58");
+
+        // 1518 isn't a leap year, 1600 is
+        let next_day = DateTime::from_minutes(date_time.minutes_since_epoch() + 2);
+        assert_eq!(next_day.to_string(), "1518-03-01 00:00");
+        assert_eq!(next_day - date_time, 2);
+        assert_eq!(DateTime::new(1600, 2, 29, 0, 0).date(), (1600, 2, 29));
+        assert_eq!(
+            DateTime::new(1600, 3, 1, 0, 0) - DateTime::new(1600, 2, 28, 0, 0),
+            2 * 1440
+        );
+
+        assert_eq!(
+            DateTime::parse("[1520-02-29 00:00]"),
+            Some(DateTime::new(1520, 2, 29, 0, 0))
+        );
+        assert_eq!(DateTime::parse("[1500-02-29 00:00]"), None);
+        assert_eq!(DateTime::parse("[1518-06-31 00:00]"), None);
+
+        assert!(DateTime::new(1518, 11, 1, 0, 0) < DateTime::new(1518, 11, 1, 0, 1));
+        assert!(DateTime::new(1518, 10, 31, 23, 59) < DateTime::new(1518, 11, 1, 0, 0));
+        assert!(DateTime::new(1517, 12, 31, 23, 59) < DateTime::new(1518, 1, 1, 0, 0));
+    }
+
+    #[test]
+    fn test_naps_crossing_the_hour() {
+        let input = "[1518-11-01 23:58] Guard #10 begins shift
+[1518-11-01 23:59] falls asleep
+[1518-11-02 00:03] wakes up
+[1518-11-02 00:50] falls asleep
+[1518-11-02 01:10] wakes up";
+        let schedule = GuardSchedule::parse(input).unwrap();
+
+        assert_eq!(
+            schedule.naps(10).collect::<Vec<_>>(),
+            vec![&Nap { start: 0, end: 3 }, &Nap { start: 50, end: 60 }]
+        );
+        assert_eq!(schedule.total_minutes_asleep(10), 13);
+    }
+
+    #[test]
+    fn test_naps_crossing_days() {
+        let cases = vec![
+            (
+                "[1518-11-01 00:30] falls asleep\n[1518-11-01 12:10] wakes up",
+                vec![Nap { start: 30, end: 60 }],
+            ),
+            (
+                "[1518-11-01 00:50] falls asleep\n[1518-11-02 00:10] wakes up",
+                vec![Nap { start: 50, end: 60 }, Nap { start: 0, end: 10 }],
+            ),
+            (
+                "[1518-11-01 23:59] falls asleep\n[1518-11-03 00:03] wakes up",
+                vec![Nap { start: 0, end: 60 }, Nap { start: 0, end: 3 }],
+            ),
+        ];
+
+        for (naps, expected) in cases {
+            let input = format!("[1518-11-01 00:00] Guard #10 begins shift\n{}", naps);
+            let schedule = GuardSchedule::parse(&input).unwrap();
+
+            assert_eq!(schedule.shifts()[0].naps, expected);
+        }
+    }
+
+    #[test]
+    fn test_record_errors() {
+        let cases = vec![
+            (
+                "[1518-02-30 00:00] Guard #10 begins shift",
+                RecordError::Malformed {
+                    line: 1,
+                    record: String::from("[1518-02-30 00:00] Guard #10 begins shift"),
+                },
+            ),
+            (
+                "[1518-04-31 00:00] Guard #10 begins shift",
+                RecordError::Malformed {
+                    line: 1,
+                    record: String::from("[1518-04-31 00:00] Guard #10 begins shift"),
+                },
+            ),
+            (
+                "[1519-02-29 00:00] Guard #10 begins shift",
+                RecordError::Malformed {
+                    line: 1,
+                    record: String::from("[1519-02-29 00:00] Guard #10 begins shift"),
+                },
+            ),
+            (
+                "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05 falls asleep",
+                RecordError::Malformed {
+                    line: 2,
+                    record: String::from("[1518-11-01 00:05 falls asleep"),
+                },
+            ),
+            (
+                "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] snores",
+                RecordError::Malformed {
+                    line: 2,
+                    record: String::from("[1518-11-01 00:05] snores"),
+                },
+            ),
+            (
+                "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:00] falls asleep",
+                RecordError::DuplicateTimestamp(at(1, 0, 0)),
+            ),
+            (
+                "[1518-11-01 00:05] falls asleep\n[1518-11-01 00:10] Guard #10 begins shift",
+                RecordError::NoGuardOnDuty(at(1, 0, 5)),
+            ),
+            (
+                "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] wakes up",
+                RecordError::WokeUpWithoutFallingAsleep {
+                    guard: 10,
+                    at: at(1, 0, 5),
+                },
+            ),
+            (
+                "[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:06] falls asleep",
+                RecordError::FellAsleepTwice {
+                    guard: 10,
+                    at: at(1, 0, 6),
+                },
+            ),
+            (
+                "[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 23:58] Guard #99 begins shift",
+                RecordError::AsleepAtShiftChange {
+                    guard: 10,
+                    at: at(1, 23, 58),
+                },
+            ),
+            (
+                "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep",
+                RecordError::NeverWokeUp {
+                    guard: 10,
+                    since: at(1, 0, 5),
+                },
+            ),
+            (
+                "[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 01:20] falls asleep
+[1518-11-01 01:30] wakes up",
+                RecordError::AsleepOutsideMidnightHour {
+                    guard: 10,
+                    since: at(1, 1, 20),
+                },
+            ),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(GuardSchedule::parse(input).unwrap_err(), expected);
+        }
+    }
 }
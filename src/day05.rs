@@ -1,4 +1,126 @@
-fn reduce(input: &str, remove: Option<char>) -> String {
+use std::collections::HashSet;
+
+use rayon::prelude::*;
+
+use parallel;
+
+pub trait Reacts {
+    fn reacts(&self, first: char, second: char) -> bool;
+}
+
+// The puzzle's rule, the same letter in opposite cases. Uses full Unicode case
+// mapping so other cased alphabets, e.g. `αΑ`, react as well.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct OppositeCase;
+
+impl Reacts for OppositeCase {
+    fn reacts(&self, first: char, second: char) -> bool {
+        first != second && first.to_lowercase().eq(second.to_lowercase())
+    }
+}
+
+impl<F> Reacts for F
+where
+    F: Fn(char, char) -> bool,
+{
+    fn reacts(&self, first: char, second: char) -> bool {
+        self(first, second)
+    }
+}
+
+// A reaction table, the units in each pair react with each other regardless
+// of the order they appear in.
+impl Reacts for HashSet<(char, char)> {
+    fn reacts(&self, first: char, second: char) -> bool {
+        self.contains(&(first, second)) || self.contains(&(second, first))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reduction {
+    pub polymer: String,
+    pub length: usize,
+    pub reactions: usize,
+    // The unit type, in lowercase, that was removed before reducing
+    pub removed: Option<char>,
+}
+
+// Every unit either reacts with the top of the stack, i.e. the last unit that
+// survived so far, or is pushed onto it. This is a single pass over the input.
+fn reduce_units<I, R>(units: I, rules: &R) -> Reduction
+where
+    I: Iterator<Item = char>,
+    R: Reacts,
+{
+    let mut stack: Vec<char> = Vec::with_capacity(units.size_hint().0);
+    let mut reactions = 0;
+
+    for unit in units {
+        match stack.last() {
+            Some(&top) if rules.reacts(top, unit) => {
+                stack.pop();
+                reactions += 1;
+            }
+            _ => stack.push(unit),
+        }
+    }
+
+    Reduction {
+        length: stack.len(),
+        polymer: stack.into_iter().collect(),
+        reactions,
+        removed: None,
+    }
+}
+
+pub fn reduce_with_rules<R: Reacts>(input: &str, rules: &R) -> Reduction {
+    reduce_units(input.chars(), rules)
+}
+
+// In ASCII the two cases of a letter differ only in the 0x20 bit
+fn reacts_ascii(first: u8, second: u8) -> bool {
+    first ^ second == 0x20 && first.is_ascii_alphabetic()
+}
+
+// Same as `reduce_units` with the puzzle's rule, but on raw bytes and into a
+// caller provided stack so repeated reductions can share one buffer. Returns
+// the number of reactions.
+fn reduce_into(units: &[u8], remove: Option<u8>, stack: &mut Vec<u8>) -> usize {
+    let mut reactions = 0;
+    stack.clear();
+
+    for &unit in units {
+        if remove.is_some() && remove == Some(unit.to_ascii_lowercase()) {
+            continue;
+        }
+
+        match stack.last() {
+            Some(&top) if reacts_ascii(top, unit) => {
+                stack.pop();
+                reactions += 1;
+            }
+            _ => stack.push(unit),
+        }
+    }
+
+    reactions
+}
+
+pub fn reduce_bytes(units: &[u8], remove: Option<u8>) -> Reduction {
+    let remove = remove.map(|unit| unit.to_ascii_lowercase());
+    let mut stack = Vec::with_capacity(units.len());
+    let reactions = reduce_into(units, remove, &mut stack);
+
+    Reduction {
+        length: stack.len(),
+        polymer: String::from_utf8(stack)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        reactions,
+        removed: remove.map(char::from),
+    }
+}
+
+pub fn reduce_naive(input: &str, remove: Option<char>) -> String {
     let mut current: String = input
         .chars()
         .filter(|&c| {
@@ -13,7 +135,7 @@ fn reduce(input: &str, remove: Option<char>) -> String {
         let chars = current.chars().collect::<Vec<_>>();
         made_changes = false;
 
-        for idx in 0..chars.len() - 1 {
+        for idx in 0..chars.len().saturating_sub(1) {
             let first = chars[idx];
             let second = chars[idx + 1];
 
@@ -31,26 +153,54 @@ fn reduce(input: &str, remove: Option<char>) -> String {
     current
 }
 
-pub fn star_one(input: &str) -> usize {
-    let result = reduce(input, None);
+pub fn reduction(input: &str) -> Reduction {
+    reduce_bytes(input.trim().as_bytes(), None)
+}
+
+// Removing a unit and reducing gives the same result whether or not the
+// polymer has already been reduced, so every removal starts from the much
+// shorter part one result. The removals are independent and are searched on
+// the shared pool, each worker reusing a single stack.
+pub fn best_removal(input: &str) -> Reduction {
+    let input = input.trim().as_bytes();
+    let mut reduced = Vec::with_capacity(input.len());
+    reduce_into(input, None, &mut reduced);
 
-    result.trim().len()
+    let (unit, _) = parallel::install(|| {
+        (b'a'..=b'z')
+            .into_par_iter()
+            .map_init(
+                || Vec::with_capacity(reduced.len()),
+                |stack, unit| {
+                    reduce_into(&reduced, Some(unit), stack);
+
+                    (unit, stack.len())
+                },
+            ).min_by_key(|&(_, length)| length)
+            .expect("There are always units to remove")
+    });
+
+    // Redone on the original polymer to report the full number of reactions
+    reduce_bytes(input, Some(unit))
 }
 
-pub fn star_two(input: &str) -> usize {
-    let possible_units = (b'a' as u32..=b'z' as u32)
-        .flat_map(std::char::from_u32)
-        .collect::<Vec<_>>();
-    let results = possible_units
-        .into_iter()
-        .map(|c| reduce(input.trim(), Some(c)));
+pub fn star_one(input: &str) -> usize {
+    reduction(input).length
+}
 
-    results.map(|r| r.trim().len()).min().unwrap_or(0)
+pub fn star_two(input: &str) -> usize {
+    best_removal(input).length
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use differential::Rng;
+    use std::collections::HashSet;
+
+    use super::{
+        best_removal, reduce_bytes, reduce_naive, reduce_with_rules, reduction, star_one, star_two,
+        OppositeCase, Reduction,
+    };
 
     #[test]
     fn test_star_one() {
@@ -61,4 +211,99 @@ mod tests {
     fn test_star_two() {
         assert_eq!(star_two("dabAcCaCBAcCcaDA"), 4);
     }
+
+    #[test]
+    fn test_reduce() {
+        assert_eq!(reduce_bytes(b"aA", None).polymer, "");
+        assert_eq!(reduce_bytes(b"abBA", None).polymer, "");
+        assert_eq!(reduce_bytes(b"abAB", None).polymer, "abAB");
+        assert_eq!(reduce_bytes(b"aabAAB", None).polymer, "aabAAB");
+        assert_eq!(
+            reduce_bytes(b"dabAcCaCBAcCcaDA", None).polymer,
+            "dabCBAcaDA"
+        );
+        assert_eq!(
+            reduce_bytes(b"dabAcCaCBAcCcaDA", Some(b'c')).polymer,
+            "daDA"
+        );
+        assert_eq!(
+            reduce_bytes(b"dabAcCaCBAcCcaDA", Some(b'C')).polymer,
+            "daDA"
+        );
+    }
+
+    #[test]
+    fn test_reduce_matches_naive() {
+        // A small alphabet so reactions are frequent
+        let mut rng = Rng::new(5);
+
+        for _ in 0..200 {
+            let len = rng.next(40) as usize + 2;
+            let polymer = (0..len)
+                .map(|_| {
+                    let unit = (b'a' + rng.next(3) as u8) as char;
+
+                    if rng.next(2) == 0 {
+                        unit.to_ascii_uppercase()
+                    } else {
+                        unit
+                    }
+                }).collect::<String>();
+
+            assert_eq!(
+                reduce_bytes(polymer.as_bytes(), None).polymer,
+                reduce_naive(&polymer, None)
+            );
+            assert_eq!(
+                reduce_bytes(polymer.as_bytes(), Some(b'b')).polymer,
+                reduce_naive(&polymer, Some('b'))
+            );
+        }
+    }
+
+    #[test]
+    fn test_reduce_with_rules() {
+        assert_eq!(
+            reduce_with_rules("dabAcCaCBAcCcaDA", &OppositeCase).polymer,
+            "dabCBAcaDA"
+        );
+        assert_eq!(reduce_with_rules("βαΑΒγ", &OppositeCase).polymer, "γ");
+
+        // Same case letters that are adjacent in the alphabet react
+        let adjacent = |first: char, second: char| (first as i32 - second as i32).abs() == 1;
+        assert_eq!(reduce_with_rules("acbdcA", &adjacent).polymer, "aA");
+
+        let table = [('x', 'y'), ('z', 'z')]
+            .iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        assert_eq!(reduce_with_rules("xXyxz", &table).polymer, "xXz");
+        assert_eq!(reduce_with_rules("yzzx", &table).polymer, "");
+    }
+
+    #[test]
+    fn test_reduction() {
+        assert_eq!(
+            reduction("dabAcCaCBAcCcaDA\n"),
+            Reduction {
+                polymer: String::from("dabCBAcaDA"),
+                length: 10,
+                reactions: 3,
+                removed: None,
+            }
+        );
+        assert_eq!(
+            best_removal("dabAcCaCBAcCcaDA"),
+            Reduction {
+                polymer: String::from("daDA"),
+                length: 4,
+                reactions: 3,
+                removed: Some('c'),
+            }
+        );
+        assert_eq!(
+            best_removal("dabAcCaCBAcCcaDA"),
+            reduce_bytes(b"dabAcCaCBAcCcaDA", Some(b'c'))
+        );
+    }
 }
@@ -1,6 +1,8 @@
+use std::collections::{HashMap, HashSet};
 use std::ops::{Index, IndexMut, Range};
+use std::ptr;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Point {
     x: i64,
     y: i64,
@@ -11,6 +13,14 @@ impl Point {
         Self { x, y }
     }
 
+    pub fn x(&self) -> i64 {
+        self.x
+    }
+
+    pub fn y(&self) -> i64 {
+        self.y
+    }
+
     fn manhattan_distance(&self, x: i64, y: i64) -> i64 {
         (self.x - x).abs() + (self.y - y).abs()
     }
@@ -37,6 +47,62 @@ impl<'a> From<&'a str> for Point {
     }
 }
 
+pub trait Metric {
+    fn distance(&self, point: &Point, x: i64, y: i64) -> i64;
+
+    // Single steps such that a point's distance to any cell is the number of
+    // steps on the shortest path between them. Enables filling the grid by
+    // expanding from all points at once rather than comparing every cell
+    // with every point.
+    fn steps(&self) -> Option<&'static [(i64, i64)]> {
+        None
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Manhattan;
+
+impl Metric for Manhattan {
+    fn distance(&self, point: &Point, x: i64, y: i64) -> i64 {
+        point.manhattan_distance(x, y)
+    }
+
+    fn steps(&self) -> Option<&'static [(i64, i64)]> {
+        Some(&[(0, -1), (-1, 0), (1, 0), (0, 1)])
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Chebyshev;
+
+impl Metric for Chebyshev {
+    fn distance(&self, point: &Point, x: i64, y: i64) -> i64 {
+        (point.x - x).abs().max((point.y - y).abs())
+    }
+
+    fn steps(&self) -> Option<&'static [(i64, i64)]> {
+        Some(&[
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ])
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct EuclideanSquared;
+
+impl Metric for EuclideanSquared {
+    fn distance(&self, point: &Point, x: i64, y: i64) -> i64 {
+        (point.x - x).pow(2) + (point.y - y).pow(2)
+    }
+}
+
 pub fn find_extremes(points: &[Point]) -> (Point, Point) {
     let max = Point::new(
         points.iter().max_by_key(|p| p.x).unwrap().x,
@@ -91,6 +157,10 @@ impl<T> Grid<T> {
             (self.min.y..(self.max.y + 1)),
         )
     }
+
+    fn contains(&self, (x, y): (i64, i64)) -> bool {
+        x >= self.min.x && x <= self.max.x && y >= self.min.y && y <= self.max.y
+    }
 }
 
 impl<T> Index<(i64, i64)> for Grid<T> {
@@ -107,7 +177,7 @@ impl<T> IndexMut<(i64, i64)> for Grid<T> {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 enum Location<'a> {
     Unspecified,
     Nearest(&'a Point),
@@ -120,7 +190,76 @@ impl<'a> Default for Location<'a> {
     }
 }
 
-fn fill_grid<'a, 'b>(grid: &'a mut Grid<Location<'b>>, points: &'b [Point]) {
+impl<'a> Location<'a> {
+    // Duplicated input points are distinct points and tie with each other
+    fn merge(&self, other: &Location<'a>) -> Location<'a> {
+        match (self, other) {
+            (Location::Unspecified, _) => other.clone(),
+            (_, Location::Unspecified) => self.clone(),
+            (Location::Nearest(lhs), Location::Nearest(rhs)) if ptr::eq(*lhs, *rhs) => self.clone(),
+            _ => Location::EquallyFar,
+        }
+    }
+}
+
+fn fill_grid<'b, M: Metric>(grid: &mut Grid<Location<'b>>, points: &'b [Point], metric: &M) {
+    match metric.steps() {
+        Some(steps) => expand(grid, points, steps),
+        None => fill_grid_naive(grid, points, metric),
+    }
+}
+
+// Expands from all points simultaneously, one step at a time. A cell's
+// nearest points are exactly those of its neighbours one step closer to them,
+// so a cell reached from differing locations in the same step is equally far
+// from several points.
+fn expand<'a>(grid: &mut Grid<Location<'a>>, points: &'a [Point], steps: &[(i64, i64)]) {
+    let mut distances = Grid::<Option<i64>>::new_with_corners(&grid.max, &grid.min, 0);
+    let mut frontier = Vec::with_capacity(points.len());
+
+    for point in points {
+        let cell = (point.x, point.y);
+
+        if distances[cell].is_none() {
+            distances[cell] = Some(0);
+            frontier.push(cell);
+        }
+        grid[cell] = grid[cell].merge(&Location::Nearest(point));
+    }
+
+    let mut distance = 0;
+    while !frontier.is_empty() {
+        let mut next_frontier = vec![];
+        distance += 1;
+
+        for &(x, y) in &frontier {
+            let location = grid[(x, y)].clone();
+
+            for &(dx, dy) in steps {
+                let neighbour = (x + dx, y + dy);
+                if !grid.contains(neighbour) {
+                    continue;
+                }
+
+                match distances[neighbour] {
+                    None => {
+                        distances[neighbour] = Some(distance);
+                        grid[neighbour] = location.clone();
+                        next_frontier.push(neighbour);
+                    }
+                    Some(d) if d == distance => {
+                        grid[neighbour] = grid[neighbour].merge(&location);
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        frontier = next_frontier;
+    }
+}
+
+fn fill_grid_naive<'b, M: Metric>(grid: &mut Grid<Location<'b>>, points: &'b [Point], metric: &M) {
     let (x_range, y_range) = grid.ranges();
 
     for x in x_range.clone() {
@@ -129,7 +268,7 @@ fn fill_grid<'a, 'b>(grid: &'a mut Grid<Location<'b>>, points: &'b [Point]) {
             let mut location = Location::Unspecified;
 
             for point in points {
-                let distance_to_point = point.manhattan_distance(x, y);
+                let distance_to_point = metric.distance(point, x, y);
 
                 if distance_to_point < distance {
                     location = Location::Nearest(point);
@@ -153,86 +292,112 @@ fn fill_grid<'a, 'b>(grid: &'a mut Grid<Location<'b>>, points: &'b [Point]) {
     }
 }
 
-pub fn star_one(input: &str) -> i64 {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Area {
+    Finite(i64),
+    Infinite,
+}
+
+pub struct AreaMap {
+    areas: Vec<(Point, Area)>,
+    ties: HashSet<(i64, i64)>,
+}
+
+impl AreaMap {
+    // In the same order as the input points
+    pub fn areas(&self) -> &[(Point, Area)] {
+        &self.areas
+    }
+
+    // Cells equally far from several points, limited to the bounding box of
+    // the points padded by one since there are infinitely many of them.
+    pub fn ties(&self) -> &HashSet<(i64, i64)> {
+        &self.ties
+    }
+
+    // From largest to smallest
+    pub fn finite_areas(&self) -> Vec<i64> {
+        let mut sizes = self
+            .areas
+            .iter()
+            .filter_map(|&(_, area)| match area {
+                Area::Finite(size) => Some(size),
+                Area::Infinite => None,
+            }).collect::<Vec<_>>();
+        sizes.sort_by(|a, b| b.cmp(a));
+
+        sizes
+    }
+}
+
+pub fn area_map<M: Metric>(input: &str, metric: &M) -> AreaMap {
     let points = parse(input).collect::<Vec<_>>();
     let (max, min) = find_extremes(&points);
     let mut grid = Grid::<Location>::new_with_corners(&max, &min, 1);
-    let mut potential_points: Vec<Option<Point>> =
-        points.clone().into_iter().map(Option::Some).collect();
     let (x_range, y_range) = grid.ranges();
-    fill_grid(&mut grid, &points);
+    fill_grid(&mut grid, &points, metric);
 
     // Remove outermost points as they escape to infinity by definition
-    // Top and bottom edges
-    for x in x_range.clone() {
-        for y in [y_range.start, y_range.end - 1].iter() {
-            match grid[(x.clone(), y.clone())] {
-                Location::Unspecified => (),
-                Location::EquallyFar => (),
-                Location::Nearest(point) => {
-                    let idx = potential_points
-                        .iter()
-                        .position(|p| p.as_ref().map(|x| x == point).unwrap_or(false));
-
-                    idx.into_iter().for_each(|idx| potential_points[idx] = None);
-                }
-            }
+    // Top and bottom edges, then left and right edges
+    let top_and_bottom = x_range
+        .clone()
+        .flat_map(|x| vec![(x, y_range.start), (x, y_range.end - 1)]);
+    let left_and_right = y_range
+        .clone()
+        .flat_map(|y| vec![(x_range.start, y), (x_range.end - 1, y)]);
+    let mut escaped_points = HashSet::<&Point>::new();
+
+    for cell in top_and_bottom.chain(left_and_right) {
+        if let Location::Nearest(point) = grid[cell] {
+            escaped_points.insert(point);
         }
     }
 
-    // Remove outermost points as they escape to infinity by definition
-    // Left and right edges
-    for y in y_range.clone() {
-        for x in [x_range.start, x_range.end - 1].iter() {
-            match grid[(x.clone(), y.clone())] {
-                Location::Unspecified => (),
-                Location::EquallyFar => (),
-                Location::Nearest(point) => {
-                    let idx = potential_points
-                        .iter()
-                        .position(|p| p.as_ref().map(|x| x == point).unwrap_or(false));
+    let mut area_sizes = HashMap::<&Point, i64>::new();
+    let mut ties = HashSet::new();
 
-                    idx.into_iter().for_each(|idx| potential_points[idx] = None);
+    for x in x_range.clone() {
+        for y in y_range.clone() {
+            match grid[(x, y)] {
+                Location::Unspecified => panic!("Should not still happen"),
+                Location::EquallyFar => {
+                    ties.insert((x, y));
+                }
+                Location::Nearest(point) => {
+                    *area_sizes.entry(point).or_insert(0) += 1;
                 }
             }
         }
     }
 
-    let unescaped_points: Vec<Point> = potential_points.into_iter().flat_map(|x| x).collect();
-    let mut area_sizes = vec![];
+    let areas = points
+        .iter()
+        .map(|point| {
+            let area = if escaped_points.contains(point) {
+                Area::Infinite
+            } else {
+                Area::Finite(area_sizes.get(point).cloned().unwrap_or(0))
+            };
 
-    for point in unescaped_points {
-        let mut area = 0;
+            (point.clone(), area)
+        }).collect();
 
-        for x in x_range.clone() {
-            for y in y_range.clone() {
-                match grid[(x.clone(), y.clone())] {
-                    Location::Unspecified => {
-                        assert!(false, "Should not still happen");
-                        ()
-                    }
-                    Location::EquallyFar => (),
-                    Location::Nearest(grid_point) => {
-                        if &point == grid_point {
-                            area += 1;
-                        }
-                    }
-                }
-            }
-        }
+    AreaMap { areas, ties }
+}
 
-        area_sizes.push(area);
-    }
+pub fn largest_finite_area<M: Metric>(input: &str, metric: &M) -> i64 {
+    area_map(input, metric).finite_areas()[0]
+}
 
-    area_sizes.into_iter().max().unwrap()
+pub fn star_one(input: &str) -> i64 {
+    largest_finite_area(input, &Manhattan)
 }
 
-pub fn star_two(input: &str, target_distance: i64) -> i64 {
+pub fn safe_region_size<M: Metric>(input: &str, target_distance: i64, metric: &M) -> i64 {
     let points = parse(input).collect::<Vec<_>>();
     let (max, min) = find_extremes(&points);
-    let mut grid = Grid::<Location>::new_with_corners(&max, &min, 1);
+    let grid = Grid::<()>::new_with_corners(&max, &min, 1);
     let (x_range, y_range) = grid.ranges();
-    fill_grid(&mut grid, &points);
 
     let mut count = 0;
     for x in x_range.clone() {
@@ -240,7 +405,7 @@ pub fn star_two(input: &str, target_distance: i64) -> i64 {
             let result = points.iter().fold(Some(0), |acc, point| match acc {
                 None => acc,
                 Some(sum) => {
-                    let distance = point.manhattan_distance(x, y);
+                    let distance = metric.distance(point, x, y);
                     if sum + distance < target_distance {
                         Some(sum + distance)
                     } else {
@@ -256,9 +421,14 @@ pub fn star_two(input: &str, target_distance: i64) -> i64 {
     count
 }
 
+pub fn star_two(input: &str, target_distance: i64) -> i64 {
+    safe_region_size(input, target_distance, &Manhattan)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use differential::Rng;
 
     static EXAMPLE: &'static str = "1, 1
 1, 6
@@ -277,6 +447,46 @@ mod tests {
         assert_eq!(star_two(EXAMPLE, 32), 16)
     }
 
+    #[test]
+    fn test_area_map() {
+        let map = area_map(EXAMPLE, &Manhattan);
+        let areas = map
+            .areas()
+            .iter()
+            .map(|(point, area)| ((point.x(), point.y()), *area))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            areas,
+            vec![
+                ((1, 1), Area::Infinite),
+                ((1, 6), Area::Infinite),
+                ((8, 3), Area::Infinite),
+                ((3, 4), Area::Finite(9)),
+                ((5, 5), Area::Finite(17)),
+                ((8, 9), Area::Infinite),
+            ]
+        );
+        assert_eq!(map.finite_areas(), vec![17, 9]);
+        assert_eq!(map.ties().len(), 12);
+        assert!(map.ties().contains(&(2, 5)));
+        assert!(!map.ties().contains(&(5, 5)));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let point = Point::new(1, 1);
+        assert_eq!(Manhattan.distance(&point, 4, 5), 7);
+        assert_eq!(Chebyshev.distance(&point, 4, 5), 4);
+        assert_eq!(EuclideanSquared.distance(&point, 4, 5), 25);
+
+        assert_eq!(largest_finite_area(EXAMPLE, &Manhattan), 17);
+        assert_eq!(largest_finite_area(EXAMPLE, &Chebyshev), 10);
+        assert_eq!(largest_finite_area(EXAMPLE, &EuclideanSquared), 16);
+        assert_eq!(safe_region_size(EXAMPLE, 32, &Manhattan), 16);
+        assert_eq!(safe_region_size(EXAMPLE, 25, &Chebyshev), 32);
+    }
+
     #[test]
     fn grid_construction() {
         let points = parse(EXAMPLE).collect::<Vec<_>>();
@@ -288,4 +498,34 @@ mod tests {
         assert_eq!(x_range, (0..10));
         assert_eq!(y_range, (0..11));
     }
+
+    fn assert_fills_match<M: Metric>(points: &[Point], metric: &M) {
+        let (max, min) = find_extremes(points);
+        let mut grid = Grid::<Location>::new_with_corners(&max, &min, 1);
+        let mut naive_grid = Grid::<Location>::new_with_corners(&max, &min, 1);
+        fill_grid(&mut grid, points, metric);
+        fill_grid_naive(&mut naive_grid, points, metric);
+
+        let (x_range, y_range) = grid.ranges();
+        for x in x_range {
+            for y in y_range.clone() {
+                assert_eq!(grid[(x, y)], naive_grid[(x, y)], "at {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_grid_matches_naive() {
+        // The small range makes ties and duplicates likely
+        let mut rng = Rng::new(6);
+        let mut next = |max: u64| rng.next(max) as i64;
+
+        for _ in 0..50 {
+            let points = (0..next(12) + 1)
+                .map(|_| Point::new(next(20), next(20)))
+                .collect::<Vec<_>>();
+            assert_fills_match(&points, &Manhattan);
+            assert_fills_match(&points, &Chebyshev);
+        }
+    }
 }
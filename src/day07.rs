@@ -1,236 +1,419 @@
-use std::cell::RefCell;
-use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap};
+use std::fmt;
 
+// Steps are identified by their index in `ids`, which is sorted so the
+// alphabetically first step also has the lowest index.
 #[derive(Debug)]
-struct Step {
-    id: char,
-    pub unlock_count: usize,
-    required_by: Vec<Rc<RefCell<Step>>>,
+struct Graph {
+    ids: Vec<char>,
+    required_by: Vec<Vec<usize>>,
+    requires: Vec<Vec<usize>>,
 }
 
-impl Step {
-    fn new(id: char) -> Self {
+impl Graph {
+    fn new(mappings: &[(char, char)]) -> Self {
+        let ids = mappings
+            .iter()
+            .flat_map(|&(id, required_by)| vec![id, required_by])
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let index = |id: char| ids.binary_search(&id).unwrap();
+        let mut required_by = vec![vec![]; ids.len()];
+        let mut requires = vec![vec![]; ids.len()];
+
+        for &(id, other) in mappings {
+            required_by[index(id)].push(index(other));
+            requires[index(other)].push(index(id));
+        }
+
         Self {
-            id,
-            unlock_count: 0,
-            required_by: vec![],
+            ids,
+            required_by,
+            requires,
         }
     }
 
-    fn unlock(&mut self) {
-        if self.unlock_count > 0 {
-            self.unlock_count -= 1;
-        }
+    fn step_count(&self) -> usize {
+        self.ids.len()
     }
 
-    fn set_unlock_count(&mut self, count: usize) {
-        self.unlock_count = count;
+    fn in_degrees(&self) -> Vec<usize> {
+        self.requires
+            .iter()
+            .map(|requires| requires.len())
+            .collect()
     }
 
-    fn is_unlocked(&self) -> bool {
-        self.unlock_count == 0
+    fn ready(&self, in_degrees: &[usize]) -> BinaryHeap<Reverse<usize>> {
+        (0..self.step_count())
+            .filter(|&idx| in_degrees[idx] == 0)
+            .map(Reverse)
+            .collect()
     }
 
-    fn required_by(&self) -> &Vec<Rc<RefCell<Step>>> {
-        &self.required_by
+    // Marks `step` as done, adding the steps that were only waiting on it to
+    // `ready`.
+    fn complete(
+        &self,
+        step: usize,
+        in_degrees: &mut [usize],
+        ready: &mut BinaryHeap<Reverse<usize>>,
+    ) {
+        for &other in &self.required_by[step] {
+            in_degrees[other] -= 1;
+
+            if in_degrees[other] == 0 {
+                ready.push(Reverse(other));
+            }
+        }
     }
 
-    fn add_required_by(&mut self, required_by: Rc<RefCell<Step>>) {
-        self.required_by.push(required_by);
+    fn order(&self) -> Vec<usize> {
+        let mut in_degrees = self.in_degrees();
+        let mut ready = self.ready(&in_degrees);
+        let mut order = Vec::with_capacity(self.step_count());
+
+        while let Some(Reverse(step)) = ready.pop() {
+            order.push(step);
+            self.complete(step, &mut in_degrees, &mut ready);
+        }
+
+        order
     }
-}
 
-impl Ord for Step {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.id.cmp(&other.id)
+    // Any step that never has all its requirements met is either part of or
+    // depends on a cycle. Every such step has a requirement that is also never
+    // met, so walking requirements backwards from one of them eventually runs
+    // into a cycle.
+    fn find_cycle(&self) -> Option<Vec<char>> {
+        let mut unmet = vec![true; self.step_count()];
+        for step in self.order() {
+            unmet[step] = false;
+        }
+
+        let start = unmet.iter().position(|&unmet| unmet)?;
+        let mut path = vec![start];
+        loop {
+            let current = *path.last().unwrap();
+            let previous = *self.requires[current]
+                .iter()
+                .filter(|&&step| unmet[step])
+                .min()
+                .unwrap();
+
+            if let Some(pos) = path.iter().position(|&step| step == previous) {
+                let mut cycle = path.split_off(pos);
+                cycle.reverse();
+                let smallest = (0..cycle.len()).min_by_key(|&idx| cycle[idx]).unwrap();
+                cycle.rotate_left(smallest);
+
+                return Some(cycle.into_iter().map(|step| self.ids[step]).collect());
+            }
+
+            path.push(previous);
+        }
     }
+
+    fn duration(&self, step: usize, base_time: usize) -> usize {
+        base_time + (self.ids[step] as u8 - b'A') as usize + 1
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    Malformed { line: usize, input: String },
+    UnknownStep { line: usize, step: char },
+    // The steps in the order they depend on each other, the last one
+    // requiring the first
+    Cycle(Vec<char>),
 }
 
-impl PartialOrd for Step {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Malformed { line, input } => {
+                write!(f, "Malformed instruction on line {}: `{}`", line, input)
+            }
+            ParseError::UnknownStep { line, step } => write!(
+                f,
+                "Unknown step `{}` on line {}, steps are named A through Z",
+                step, line
+            ),
+            ParseError::Cycle(steps) => {
+                let cycle = steps
+                    .iter()
+                    .chain(steps.first())
+                    .map(|step| step.to_string())
+                    .collect::<Vec<_>>();
+
+                write!(f, "Dependency cycle between steps {}", cycle.join(" -> "))
+            }
+        }
     }
 }
-impl PartialEq for Step {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+
+fn parse_line(line: &str) -> Option<(char, char)> {
+    let single_char = |word: &str| {
+        let mut chars = word.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+    let words = line.split_whitespace().collect::<Vec<_>>();
+
+    match words.as_slice() {
+        ["Step", id, "must", "be", "finished", "before", "step", required_by, "can", "begin."] => {
+            Some((single_char(id)?, single_char(required_by)?))
+        }
+        _ => None,
     }
 }
 
-impl Eq for Step {}
+fn parse_dependencies(input: &str) -> Result<Vec<(char, char)>, ParseError> {
+    let mut mappings = vec![];
 
-fn parse(input: &str) -> Vec<Rc<RefCell<Step>>> {
-    let clean_lines = input
-        .lines()
-        .map(|line| line.trim())
-        .filter(|line| line.len() > 0)
-        .filter(|line| line.starts_with("Step "))
-        .collect::<Vec<_>>();
+    for (idx, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
 
-    let mappings: Vec<(char, char)> = clean_lines
-        .iter()
-        .map(|line| {
-            let id = line
-                .trim_start_matches("Step ")
-                .chars()
-                .nth(0)
-                .expect("Expected to find ids");
+        let (id, required_by) = parse_line(line).ok_or_else(|| ParseError::Malformed {
+            line: idx + 1,
+            input: line.to_owned(),
+        })?;
 
-            let pos = line.rfind(" can begin.").expect(&format!(
-                "Expected the string ` can begin.` in {}, but found nothing",
-                line
-            ));
+        if let Some(&step) = [id, required_by].iter().find(|c| !c.is_ascii_uppercase()) {
+            return Err(ParseError::UnknownStep {
+                line: idx + 1,
+                step,
+            });
+        }
 
-            let id2 = line.chars().nth(pos - 1).unwrap();
-
-            (id, id2)
-        }).collect();
-    let ids = mappings
-        .iter()
-        .flat_map(|(a, b)| vec![a, b])
-        .collect::<HashSet<_>>();
-    let steps = mappings
-        .iter()
-        .flat_map(|(a, b)| vec![a, b])
-        .map(|&id| (id, Rc::new(RefCell::new(Step::new(id)))))
-        .collect::<HashMap<_, _>>();
-
-    let mut no_requirments = ids.clone();
-
-    mappings.iter().for_each(|(id, required_by_id)| {
-        no_requirments.remove(&required_by_id);
-        let other_step = {
-            Rc::clone(steps.get(&required_by_id).expect(&format!(
-                "Expected existing step for id: {}",
-                required_by_id
-            )))
-        };
-
-        if let Some(step) = steps.get(&id) {
-            step.borrow_mut().add_required_by(other_step);
-        }
-    });
-
-    steps.values().for_each(|value| {
-        let requires_count = steps.values().fold(0, |acc, other_value| {
-            if value == other_value {
-                return acc;
-            }
+        mappings.push((id, required_by));
+    }
 
-            if other_value.borrow().required_by().contains(value) {
-                return acc + 1;
-            }
+    // Repeated instructions would otherwise unlock a step too early
+    mappings.sort();
+    mappings.dedup();
 
-            return acc;
-        });
+    Ok(mappings)
+}
 
-        value.borrow_mut().set_unlock_count(requires_count);
-    });
+fn parse(input: &str) -> Result<Graph, ParseError> {
+    let graph = Graph::new(&parse_dependencies(input)?);
 
-    let mut firsts = no_requirments
-        .into_iter()
-        .map(|id| steps.get(&id).unwrap().clone())
-        .collect::<Vec<_>>();
-    firsts.sort_by(|a, b| b.cmp(a));
+    match graph.find_cycle() {
+        Some(cycle) => Err(ParseError::Cycle(cycle)),
+        None => Ok(graph),
+    }
+}
+
+pub fn order(input: &str) -> Result<String, ParseError> {
+    let graph = parse(input)?;
 
-    firsts
+    Ok(graph
+        .order()
+        .into_iter()
+        .map(|step| graph.ids[step])
+        .collect())
 }
 
 pub fn star_one(input: &str) -> String {
-    let mut first_steps = parse(input);
-    first_steps.sort_by(|a, b| b.cmp(a));
-    let mut stack = vec![];
-    for step in first_steps {
-        stack.push(step);
+    order(input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// Each step is labelled with its position in the completion order of part one
+pub fn to_dot(input: &str) -> String {
+    let graph = parse(input).unwrap_or_else(|e| panic!("{}", e));
+    let mut dot = String::from("digraph steps {\n");
+
+    for (idx, step) in graph.order().into_iter().enumerate() {
+        let id = graph.ids[step];
+        dot.push_str(&format!("    {} [label=\"{} ({})\"];\n", id, id, idx + 1));
+    }
+    for (step, required_by) in graph.required_by.iter().enumerate() {
+        for &other in required_by {
+            dot.push_str(&format!(
+                "    {} -> {};\n",
+                graph.ids[step], graph.ids[other]
+            ));
+        }
     }
-    let mut result = String::new();
+    dot.push_str("}\n");
 
-    while !stack.is_empty() {
-        let next = stack.pop().unwrap();
-        result.push(next.borrow().id);
+    dot
+}
 
-        for to_explore in next.borrow().required_by() {
-            to_explore.borrow_mut().unlock();
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Task {
+    pub step: char,
+    pub start: usize,
+    pub end: usize,
+}
 
-            if !stack.contains(&to_explore) && to_explore.borrow().is_unlocked() {
-                stack.push(to_explore.clone());
-            }
-        }
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Schedule {
+    // The tasks each worker performed, in the order they were started
+    pub workers: Vec<Vec<Task>>,
+}
 
-        stack.sort_by(|a, b| b.cmp(a));
+impl Schedule {
+    pub fn total_time(&self) -> usize {
+        self.workers
+            .iter()
+            .flat_map(|tasks| tasks.last())
+            .map(|task| task.end)
+            .max()
+            .unwrap_or(0)
     }
 
-    result
+    // Steps in the order they were started, ties broken by worker
+    pub fn order(&self) -> String {
+        let mut tasks = self
+            .workers
+            .iter()
+            .enumerate()
+            .flat_map(|(worker, tasks)| {
+                tasks
+                    .iter()
+                    .map(move |task| (task.start, worker, task.step))
+            })
+            .collect::<Vec<_>>();
+        tasks.sort();
+
+        tasks.into_iter().map(|(_, _, step)| step).collect()
+    }
 }
 
-pub fn star_two(input: &str, num_workers: usize, base_time: usize) -> i64 {
-    let mut first_steps = parse(input);
-    first_steps.sort_by(|a, b| b.cmp(a));
-    let mut stack = vec![];
-    for step in first_steps {
-        stack.push(step);
-    }
-    let mut result = String::new();
-    let mut time_taken = 0;
-    let mut busy_counters: Vec<(Option<Rc<RefCell<Step>>>, usize)> = vec![(None, 0); num_workers];
-
-    while !stack.is_empty() || busy_counters.iter().any(|&(_, value)| value > 0) {
-        busy_counters = busy_counters
-            .into_iter()
-            .map(|(potential_step, x)| match x.overflowing_sub(1) {
-                (new_value, false) => {
-                    if new_value == 0 {
-                        if let Some(step) = potential_step {
-                            for to_explore in step.borrow().required_by() {
-                                to_explore.borrow_mut().unlock();
-
-                                if !stack.contains(&to_explore) && to_explore.borrow().is_unlocked()
-                                {
-                                    stack.push(to_explore.clone());
-                                }
-                            }
-                        }
-                        (None, 0)
-                    } else {
-                        (potential_step, new_value)
-                    }
+// Whenever workers are idle they pick up the alphabetically first steps
+// that are ready, in the order of the workers. Time only advances to the
+// next moment a worker finishes, since nothing changes in between.
+pub fn schedule(input: &str, num_workers: usize, base_time: usize) -> Result<Schedule, ParseError> {
+    let graph = parse(input)?;
+    let mut in_degrees = graph.in_degrees();
+    let mut ready = graph.ready(&in_degrees);
+    let mut workers = vec![vec![]; num_workers];
+    let mut busy: Vec<Option<(usize, usize)>> = vec![None; num_workers];
+    let mut time = 0;
+
+    loop {
+        for worker in busy.iter_mut() {
+            if let Some((step, end)) = *worker {
+                if end == time {
+                    graph.complete(step, &mut in_degrees, &mut ready);
+                    *worker = None;
                 }
-                (_, true) => (None, 0),
-            }).collect();
-        stack.sort_by(|a, b| b.cmp(a));
+            }
+        }
 
-        let available_worker_ids: Vec<_> = busy_counters
-            .iter()
-            .enumerate()
-            .filter(|(_, &(_, value))| value == 0)
-            .map(|(id, _)| id.clone())
-            .collect();
+        for (idx, worker) in busy.iter_mut().enumerate() {
+            if worker.is_some() {
+                continue;
+            }
 
-        available_worker_ids.iter().for_each(|id| {
-            if stack.is_empty() {
-                return;
+            if let Some(Reverse(step)) = ready.pop() {
+                let end = time + graph.duration(step, base_time);
+                workers[idx].push(Task {
+                    step: graph.ids[step],
+                    start: time,
+                    end,
+                });
+                *worker = Some((step, end));
             }
+        }
 
-            let next = stack.pop().unwrap();
-            result.push(next.borrow().id);
+        match busy
+            .iter()
+            .flat_map(|worker| worker.map(|(_, end)| end))
+            .min()
+        {
+            Some(end) => time = end,
+            None => break,
+        }
+    }
 
-            let work_time = base_time + (next.borrow().id as u32 - 64) as usize;
-            busy_counters[*id] = (Some(Rc::clone(&next)), work_time);
-        });
+    Ok(Schedule { workers })
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CriticalPath {
+    // The longest chain of steps, each requiring the previous one
+    pub steps: Vec<char>,
+    // The minimum time to complete all steps, no matter how many workers
+    pub duration: usize,
+    // How much each step can be delayed without delaying the whole, in
+    // alphabetical order. Zero for the steps on the critical path.
+    pub slack: Vec<(char, usize)>,
+}
+
+pub fn critical_path(input: &str, base_time: usize) -> Result<CriticalPath, ParseError> {
+    let graph = parse(input)?;
+    let order = graph.order();
+    let duration = |step| graph.duration(step, base_time);
 
-        time_taken += 1;
+    let mut earliest_start = vec![0; graph.step_count()];
+    for &step in &order {
+        for &other in &graph.required_by[step] {
+            earliest_start[other] =
+                earliest_start[other].max(earliest_start[step] + duration(step));
+        }
+    }
+    let total = (0..graph.step_count())
+        .map(|step| earliest_start[step] + duration(step))
+        .max()
+        .unwrap_or(0);
+
+    let mut latest_start = vec![0; graph.step_count()];
+    for &step in order.iter().rev() {
+        let latest_end = graph.required_by[step]
+            .iter()
+            .map(|&other| latest_start[other])
+            .min()
+            .unwrap_or(total);
+        latest_start[step] = latest_end - duration(step);
     }
+    let slack = |step: usize| latest_start[step] - earliest_start[step];
 
-    println!("{}", result);
+    let mut steps = vec![];
+    let mut next =
+        (0..graph.step_count()).find(|&step| earliest_start[step] == 0 && slack(step) == 0);
+    while let Some(step) = next {
+        steps.push(graph.ids[step]);
+        let end = earliest_start[step] + duration(step);
 
-    time_taken - 1
+        next = graph.required_by[step]
+            .iter()
+            .cloned()
+            .filter(|&other| earliest_start[other] == end && slack(other) == 0)
+            .min();
+    }
+
+    Ok(CriticalPath {
+        steps,
+        duration: total,
+        slack: (0..graph.step_count())
+            .map(|step| (graph.ids[step], slack(step)))
+            .collect(),
+    })
+}
+
+pub fn star_two(input: &str, num_workers: usize, base_time: usize) -> i64 {
+    schedule(input, num_workers, base_time)
+        .map(|schedule| schedule.total_time() as i64)
+        .unwrap_or_else(|e| panic!("{}", e))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        critical_path, order, schedule, star_one, star_two, to_dot, CriticalPath, ParseError,
+        Schedule, Task,
+    };
     static EXAMPLE: &'static str = "Step C must be finished before step A can begin.
 Step C must be finished before step F can begin.
 Step A must be finished before step B can begin.
@@ -248,4 +431,138 @@ Step F must be finished before step E can begin.";
     fn test_star_two() {
         assert_eq!(star_two(EXAMPLE, 2, 0), 15);
     }
+
+    #[test]
+    fn test_to_dot() {
+        assert_eq!(
+            to_dot(EXAMPLE),
+            "digraph steps {
+    C [label=\"C (1)\"];
+    A [label=\"A (2)\"];
+    B [label=\"B (3)\"];
+    D [label=\"D (4)\"];
+    F [label=\"F (5)\"];
+    E [label=\"E (6)\"];
+    A -> B;
+    A -> D;
+    B -> E;
+    C -> A;
+    C -> F;
+    D -> E;
+    F -> E;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        let task = |step, start, end| Task { step, start, end };
+        let result = schedule(EXAMPLE, 2, 0).unwrap();
+
+        assert_eq!(
+            result,
+            Schedule {
+                workers: vec![
+                    vec![
+                        task('C', 0, 3),
+                        task('A', 3, 4),
+                        task('B', 4, 6),
+                        task('D', 6, 10),
+                        task('E', 10, 15),
+                    ],
+                    vec![task('F', 3, 9)],
+                ],
+            }
+        );
+        assert_eq!(result.total_time(), 15);
+        assert_eq!(result.order(), "CAFBDE");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            order("Step C must be finished before step A can begin.\nStep A must be done"),
+            Err(ParseError::Malformed {
+                line: 2,
+                input: String::from("Step A must be done"),
+            })
+        );
+        assert_eq!(
+            order("Step C must be finished before step A can begin.\nStep C must be finished before step a can begin."),
+            Err(ParseError::UnknownStep { line: 2, step: 'a' })
+        );
+
+        let cycle = "Step C must be finished before step A can begin.
+Step A must be finished before step D can begin.
+Step D must be finished before step B can begin.
+Step B must be finished before step A can begin.
+Step B must be finished before step E can begin.";
+        let error = order(cycle).unwrap_err();
+        assert_eq!(error, ParseError::Cycle(vec!['A', 'D', 'B']));
+        assert_eq!(
+            error.to_string(),
+            "Dependency cycle between steps A -> D -> B -> A"
+        );
+        assert_eq!(
+            schedule(cycle, 2, 0),
+            Err(ParseError::Cycle(vec!['A', 'D', 'B']))
+        );
+        assert_eq!(
+            order("Step F must be finished before step F can begin."),
+            Err(ParseError::Cycle(vec!['F']))
+        );
+    }
+
+    #[test]
+    fn test_critical_path() {
+        assert_eq!(
+            critical_path(EXAMPLE, 0),
+            Ok(CriticalPath {
+                steps: vec!['C', 'F', 'E'],
+                duration: 14,
+                slack: vec![('A', 1), ('B', 3), ('C', 0), ('D', 1), ('E', 0), ('F', 0)],
+            })
+        );
+
+        // With a worker per step nothing ever waits for a worker
+        assert_eq!(schedule(EXAMPLE, 26, 0).unwrap().total_time(), 14);
+
+        let path = critical_path(EXAMPLE, 60).unwrap();
+        assert_eq!(path.steps, vec!['C', 'A', 'D', 'E']);
+        assert_eq!(path.duration, 253);
+    }
+
+    #[test]
+    fn test_dense_graph() {
+        // Every step requires all alphabetically earlier ones
+        let steps = (b'A'..=b'Z').map(char::from).collect::<Vec<_>>();
+        let mut input = String::new();
+        for (idx, first) in steps.iter().enumerate() {
+            for second in steps.iter().skip(idx + 1).rev() {
+                input.push_str(&format!(
+                    "Step {} must be finished before step {} can begin.\n",
+                    first, second
+                ));
+            }
+        }
+
+        let alphabet = steps.iter().collect::<String>();
+        assert_eq!(order(&input), Ok(alphabet.clone()));
+
+        let result = schedule(&input, 5, 60).unwrap();
+        assert_eq!(result.order(), alphabet);
+        assert_eq!(result.total_time(), 26 * 60 + (1..=26).sum::<usize>());
+        assert_eq!(result.workers[0].len(), 26);
+    }
+
+    #[test]
+    fn test_repeated_instructions() {
+        let repeated = format!(
+            "{}\nStep A must be finished before step B can begin.",
+            EXAMPLE
+        );
+
+        assert_eq!(order(&repeated), Ok(String::from("CABDFE")));
+    }
 }
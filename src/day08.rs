@@ -1,28 +1,197 @@
-use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::str::SplitWhitespace;
 
 #[derive(Debug)]
-struct Node {
+pub struct Node {
     metadata: Vec<usize>,
     children: Vec<Node>,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    InvalidNumber {
+        position: usize,
+        token: String,
+    },
+    // The input ended where the header of a node was expected
+    MissingHeader {
+        position: usize,
+    },
+    MissingMetadata {
+        position: usize,
+        expected: usize,
+        found: usize,
+    },
+    // Every child takes at least the two numbers of its header, so a header
+    // can promise more than there is input left.
+    NodeTooLarge {
+        position: usize,
+        child_count: usize,
+        metadata_count: usize,
+        remaining: usize,
+    },
+    TrailingNumbers {
+        position: usize,
+        count: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidNumber { position, token } => {
+                write!(f, "Invalid number `{}` at position {}", token, position)
+            }
+            ParseError::MissingHeader { position } => write!(
+                f,
+                "Expected a node header of two numbers at position {}, but the input ended",
+                position
+            ),
+            ParseError::MissingMetadata {
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Expected {} metadata entries at position {}, but only {} numbers remain",
+                expected, position, found
+            ),
+            ParseError::NodeTooLarge {
+                position,
+                child_count,
+                metadata_count,
+                remaining,
+            } => write!(
+                f,
+                "Node at position {} has {} children and {} metadata entries, \
+                 but only {} numbers remain",
+                position, child_count, metadata_count, remaining
+            ),
+            ParseError::TrailingNumbers { position, count } => write!(
+                f,
+                "Found {} numbers after the end of the tree at position {}",
+                count, position
+            ),
+        }
+    }
+}
+
+// Every child takes at least the two numbers of its header, so a header that
+// needs more than the `remaining` numbers after it can never be complete.
+fn check_header(
+    position: usize,
+    child_count: usize,
+    metadata_count: usize,
+    remaining: usize,
+) -> Result<(), ParseError> {
+    let needed = child_count
+        .checked_mul(2)
+        .and_then(|children| children.checked_add(metadata_count));
+
+    if needed.is_none_or(|needed| needed > remaining) {
+        return Err(ParseError::NodeTooLarge {
+            position,
+            child_count,
+            metadata_count,
+            remaining,
+        });
+    }
+
+    Ok(())
+}
+
+// A node whose children are still being parsed
+struct PartialNode {
+    child_count: usize,
+    metadata_count: usize,
+    children: Vec<Node>,
+}
+
 impl Node {
-    fn new(child_count: usize, metadata_count: usize) -> Node {
-        Self {
-            metadata: Vec::<usize>::with_capacity(metadata_count),
-            children: Vec::<Node>::with_capacity(child_count),
+    pub fn parse(input: &str) -> Result<Node, ParseError> {
+        let numbers = input
+            .split_whitespace()
+            .enumerate()
+            .map(|(position, token)| {
+                token
+                    .parse::<usize>()
+                    .map_err(|_| ParseError::InvalidNumber {
+                        position,
+                        token: token.to_owned(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut position = 0;
+        let read_header = |position: &mut usize| match numbers.get(*position..*position + 2) {
+            Some(header) => {
+                let (child_count, metadata_count) = (header[0], header[1]);
+                let remaining = numbers.len() - *position - 2;
+                check_header(*position, child_count, metadata_count, remaining)?;
+                *position += 2;
+
+                Ok(PartialNode {
+                    child_count,
+                    metadata_count,
+                    children: Vec::with_capacity(child_count),
+                })
+            }
+            None => Err(ParseError::MissingHeader {
+                position: *position,
+            }),
+        };
+        let mut stack = vec![read_header(&mut position)?];
+
+        loop {
+            let node_done = {
+                let top = stack.last().unwrap();
+                top.children.len() == top.child_count
+            };
+
+            if !node_done {
+                let child = read_header(&mut position)?;
+                stack.push(child);
+                continue;
+            }
+
+            let partial = stack.pop().unwrap();
+            let end = position + partial.metadata_count;
+            if end > numbers.len() {
+                return Err(ParseError::MissingMetadata {
+                    position,
+                    expected: partial.metadata_count,
+                    found: numbers.len() - position,
+                });
+            }
+
+            let node = Node {
+                metadata: numbers[position..end].to_vec(),
+                children: partial.children,
+            };
+            position = end;
+
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None if position < numbers.len() => {
+                    return Err(ParseError::TrailingNumbers {
+                        position,
+                        count: numbers.len() - position,
+                    })
+                }
+                None => return Ok(node),
+            }
         }
     }
 
-    fn add_children(&mut self, new_children: Vec<Node>) {
-        self.children.extend(new_children);
+    pub fn metadata(&self) -> &[usize] {
+        &self.metadata
     }
 
-    fn add_metadata(&mut self, new_metadata: Vec<usize>) {
-        self.metadata.extend(new_metadata);
+    pub fn children(&self) -> &[Node] {
+        &self.children
     }
 
-    fn metadata_sum(&self) -> usize {
+    pub fn metadata_sum(&self) -> usize {
         self.metadata.iter().sum()
     }
 
@@ -30,79 +199,294 @@ impl Node {
         !self.children.is_empty()
     }
 
-    fn value(&self) -> usize {
-        if self.has_children() {
-            self.metadata.iter().fold(0, |acc, &index| {
-                if index <= self.children.len() {
-                    acc + self.children[index - 1].value()
-                } else {
-                    acc
+    // Computed bottom up so deep trees don't overflow the stack, when a node
+    // is visited the values of its children are on top of `values`.
+    pub fn value(&self) -> usize {
+        let mut values: Vec<usize> = vec![];
+
+        for node in self.iter_postorder() {
+            let value = if node.has_children() {
+                let children = values.split_off(values.len() - node.children.len());
+
+                node.metadata
+                    .iter()
+                    .filter(|&&index| index >= 1 && index <= children.len())
+                    .map(|&index| children[index - 1])
+                    .sum()
+            } else {
+                node.metadata_sum()
+            };
+
+            values.push(value);
+        }
+
+        values[0]
+    }
+
+    // `{"metadata":[...],"children":[...]}` for every node
+    pub fn to_json(&self) -> String {
+        self.serialize(
+            |node, out| {
+                let metadata = node
+                    .metadata
+                    .iter()
+                    .map(|entry| entry.to_string())
+                    .collect::<Vec<_>>();
+                out.push_str(&format!(
+                    "{{\"metadata\":[{}],\"children\":[",
+                    metadata.join(",")
+                ));
+            },
+            ("", ","),
+            "]}",
+        )
+    }
+
+    // `(node (metadata ...) (children ...))` for every node
+    pub fn to_sexpr(&self) -> String {
+        self.serialize(
+            |node, out| {
+                out.push_str("(node (metadata");
+                for entry in &node.metadata {
+                    out.push_str(&format!(" {}", entry));
                 }
-            })
-        } else {
-            self.metadata_sum()
+                out.push_str(") (children");
+            },
+            (" ", " "),
+            "))",
+        )
+    }
+
+    // Writes `open` for a node, then its children each prefixed by one of
+    // `prefixes`, the first for the first child and the second for the rest,
+    // and finally `close`. Uses an explicit stack for the same reason as the
+    // parser.
+    fn serialize<F>(&self, open: F, prefixes: (&str, &str), close: &str) -> String
+    where
+        F: Fn(&Node, &mut String),
+    {
+        let mut out = String::new();
+        let mut to_visit = vec![(self, 0)];
+        open(self, &mut out);
+
+        while let Some((node, next_child)) = to_visit.pop() {
+            if next_child == node.children.len() {
+                out.push_str(close);
+                continue;
+            }
+
+            let child = &node.children[next_child];
+            out.push_str(if next_child == 0 {
+                prefixes.0
+            } else {
+                prefixes.1
+            });
+            open(child, &mut out);
+
+            to_visit.push((node, next_child + 1));
+            to_visit.push((child, 0));
+        }
+
+        out
+    }
+
+    // Parents before their children, children in order
+    pub fn iter_preorder(&self) -> Preorder<'_> {
+        Preorder {
+            to_visit: vec![self],
         }
     }
 
-    fn recurse(numbers: &mut VecDeque<usize>) -> Node {
-        let child_count = numbers.pop_front().unwrap();
-        let metadata_count = numbers.pop_front().unwrap();
-        let mut node = Self::new(child_count, metadata_count);
+    // Children, in order, before their parents
+    pub fn iter_postorder(&self) -> Postorder<'_> {
+        Postorder {
+            to_visit: vec![(self, 0)],
+        }
+    }
 
-        let children = (0..child_count)
-            .map(|_| Self::recurse(numbers))
-            .into_iter()
-            .collect::<Vec<_>>();
-        let metadata = (0..metadata_count)
-            .map(|_| numbers.pop_front().unwrap())
-            .collect::<Vec<_>>();
+    // Visits every node in preorder along with its depth, the root being at
+    // depth zero.
+    pub fn visit(&self, f: &mut dyn FnMut(&Node, usize)) {
+        let mut to_visit = vec![(self, 0)];
 
-        node.add_children(children);
-        node.add_metadata(metadata);
+        while let Some((next, depth)) = to_visit.pop() {
+            f(next, depth);
 
-        node
+            for child in next.children.iter().rev() {
+                to_visit.push((child, depth + 1));
+            }
+        }
     }
+}
 
-    fn traverse<F>(root: &Node, mut f: F)
-    where
-        F: FnMut(&Self),
-    {
-        let mut to_visit: Vec<&Node> = vec![root];
+pub struct Preorder<'a> {
+    to_visit: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Preorder<'a> {
+    type Item = &'a Node;
 
-        while !to_visit.is_empty() {
-            let next = to_visit.pop().unwrap();
-            f(&next);
+    fn next(&mut self) -> Option<&'a Node> {
+        let next = self.to_visit.pop()?;
+        self.to_visit.extend(next.children.iter().rev());
 
-            for child in next.children.iter() {
-                to_visit.push(child);
+        Some(next)
+    }
+}
+
+// Each entry is a node and the index of the next of its children to visit
+pub struct Postorder<'a> {
+    to_visit: Vec<(&'a Node, usize)>,
+}
+
+impl<'a> Iterator for Postorder<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        loop {
+            let (node, next_child) = self.to_visit.pop()?;
+
+            if next_child == node.children.len() {
+                return Some(node);
             }
+
+            self.to_visit.push((node, next_child + 1));
+            self.to_visit.push((&node.children[next_child], 0));
+        }
+    }
+}
+
+// The derived drop recurses once per level of nesting
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut to_drop = mem::take(&mut self.children);
+
+        while let Some(mut node) = to_drop.pop() {
+            to_drop.append(&mut node.children);
         }
     }
 }
 
 impl<'a> From<&'a str> for Node {
     fn from(input: &'a str) -> Self {
-        let mut numbers = input
-            .split_whitespace()
-            .map(|s| {
-                s.trim()
+        Node::parse(input).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+struct Numbers<'a> {
+    tokens: SplitWhitespace<'a>,
+    position: usize,
+    // How many numbers there are in total, read or not
+    count: usize,
+}
+
+impl<'a> Numbers<'a> {
+    fn next(&mut self) -> Result<Option<usize>, ParseError> {
+        match self.tokens.next() {
+            Some(token) => {
+                let number = token
                     .parse::<usize>()
-                    .expect("Expected only parseable numbers")
-            }).collect::<VecDeque<_>>();
+                    .map_err(|_| ParseError::InvalidNumber {
+                        position: self.position,
+                        token: token.to_owned(),
+                    })?;
+                self.position += 1;
+
+                Ok(Some(number))
+            }
+            None => Ok(None),
+        }
+    }
 
-        let root = Node::recurse(&mut numbers);
+    fn header(&mut self) -> Result<PendingNode, ParseError> {
+        let position = self.position;
+        let missing = || ParseError::MissingHeader { position };
+        let child_count = self.next()?.ok_or_else(missing)?;
+        let metadata_count = self.next()?.ok_or_else(missing)?;
+        check_header(
+            position,
+            child_count,
+            metadata_count,
+            self.count - self.position,
+        )?;
 
-        root
+        Ok(PendingNode {
+            child_count,
+            remaining_children: child_count,
+            metadata_count,
+        })
+    }
+}
+
+struct PendingNode {
+    child_count: usize,
+    remaining_children: usize,
+    metadata_count: usize,
+}
+
+// Computes the metadata sum and the root's value in a single pass over the
+// numbers without building the tree, after counting them so headers are
+// checked the same way `Node::parse` checks them. The values of completed
+// children are kept on a single stack, a node's children being on top of it
+// when the node's metadata is reached.
+pub fn sum_and_value(input: &str) -> Result<(usize, usize), ParseError> {
+    let mut numbers = Numbers {
+        tokens: input.split_whitespace(),
+        position: 0,
+        count: input.split_whitespace().count(),
+    };
+    let mut stack = vec![numbers.header()?];
+    let mut values: Vec<usize> = vec![];
+    let mut metadata_sum = 0;
+
+    loop {
+        if let Some(top) = stack.last_mut().filter(|top| top.remaining_children > 0) {
+            top.remaining_children -= 1;
+            let child = numbers.header()?;
+            stack.push(child);
+            continue;
+        }
+
+        let node = stack.pop().unwrap();
+        let position = numbers.position;
+        let children = values.len() - node.child_count;
+        let mut value = 0;
+
+        for found in 0..node.metadata_count {
+            let entry = numbers.next()?.ok_or(ParseError::MissingMetadata {
+                position,
+                expected: node.metadata_count,
+                found,
+            })?;
+            metadata_sum += entry;
+
+            if node.child_count == 0 {
+                value += entry;
+            } else if entry >= 1 && entry <= node.child_count {
+                value += values[children + entry - 1];
+            }
+        }
+
+        values.truncate(children);
+        values.push(value);
+
+        if stack.is_empty() {
+            let position = numbers.position;
+            let count = numbers.tokens.count();
+
+            return if count > 0 {
+                Err(ParseError::TrailingNumbers { position, count })
+            } else {
+                Ok((metadata_sum, value))
+            };
+        }
     }
 }
 
 pub fn star_one(input: &str) -> usize {
     let tree = Node::from(input);
-    let mut sum = 0;
-
-    Node::traverse(&tree, |node: &Node| sum += node.metadata_sum());
 
-    sum
+    tree.iter_preorder().map(|node| node.metadata_sum()).sum()
 }
 
 pub fn star_two(input: &str) -> usize {
@@ -113,7 +497,7 @@ pub fn star_two(input: &str) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{star_one, star_two, sum_and_value, Node, ParseError};
     static EXAMPLE: &str = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
 
     #[test]
@@ -125,4 +509,149 @@ mod tests {
     fn test_star_two() {
         assert_eq!(star_two(EXAMPLE), 66)
     }
+
+    #[test]
+    fn test_traversals() {
+        let tree = Node::from(EXAMPLE);
+        let sums = |nodes: Vec<&Node>| {
+            nodes
+                .into_iter()
+                .map(|node| node.metadata_sum())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sums(tree.iter_preorder().collect()), vec![4, 33, 2, 99]);
+        assert_eq!(sums(tree.iter_postorder().collect()), vec![33, 99, 2, 4]);
+
+        let mut visited = vec![];
+        tree.visit(&mut |node, depth| visited.push((node.metadata_sum(), depth)));
+        assert_eq!(visited, vec![(4, 0), (33, 1), (2, 1), (99, 2)]);
+
+        assert_eq!(tree.children().len(), 2);
+        assert_eq!(tree.metadata(), &[1, 1, 2]);
+        assert_eq!(tree.children()[1].value(), 0);
+    }
+
+    #[test]
+    fn test_sum_and_value() {
+        assert_eq!(sum_and_value(EXAMPLE), Ok((138, 66)));
+        assert_eq!(sum_and_value("0 3 1 2 3"), Ok((6, 6)));
+        assert_eq!(sum_and_value("1 3 0 1 7 0 1 2"), Ok((10, 7)));
+
+        let inputs = [
+            "1 1 0 x 3",
+            "2 0 1 0 0 0",
+            "2 1 0 1 5",
+            "99999999999999999 1 0 0 1",
+            "1 1 0 18446744073709551615 1",
+            "1 3 0 1 5 1 2",
+            "0 1 5 7 7",
+            "",
+        ];
+        for input in &inputs {
+            assert_eq!(
+                sum_and_value(input).unwrap_err(),
+                Node::parse(input).unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_serialization() {
+        let tree = Node::from(EXAMPLE);
+
+        assert_eq!(
+            tree.to_json(),
+            concat!(
+                r#"{"metadata":[1,1,2],"children":["#,
+                r#"{"metadata":[10,11,12],"children":[]},"#,
+                r#"{"metadata":[2],"children":[{"metadata":[99],"children":[]}]}"#,
+                r#"]}"#
+            )
+        );
+        assert_eq!(
+            tree.to_sexpr(),
+            concat!(
+                "(node (metadata 1 1 2) (children ",
+                "(node (metadata 10 11 12) (children)) ",
+                "(node (metadata 2) (children (node (metadata 99) (children))))",
+                "))"
+            )
+        );
+        assert_eq!(Node::from("0 0").to_sexpr(), "(node (metadata) (children))");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Node::parse("1 1 0 x 3").unwrap_err(),
+            ParseError::InvalidNumber {
+                position: 3,
+                token: String::from("x"),
+            }
+        );
+        // The first child uses up the room the root left for the second
+        assert_eq!(
+            Node::parse("2 0 1 0 0 0").unwrap_err(),
+            ParseError::MissingHeader { position: 6 }
+        );
+        assert_eq!(
+            Node::parse("2 1 0 1 5").unwrap_err(),
+            ParseError::NodeTooLarge {
+                position: 0,
+                child_count: 2,
+                metadata_count: 1,
+                remaining: 3,
+            }
+        );
+        assert_eq!(
+            Node::parse("99999999999999999 1 0 0 1").unwrap_err(),
+            ParseError::NodeTooLarge {
+                position: 0,
+                child_count: 99999999999999999,
+                metadata_count: 1,
+                remaining: 3,
+            }
+        );
+        assert_eq!(
+            Node::parse("1 1 0 18446744073709551615 1")
+                .unwrap_err()
+                .to_string(),
+            "Node at position 2 has 0 children and 18446744073709551615 metadata entries, \
+             but only 1 numbers remain"
+        );
+        assert_eq!(
+            Node::parse("1 3 0 1 5 1 2").unwrap_err(),
+            ParseError::MissingMetadata {
+                position: 5,
+                expected: 3,
+                found: 2,
+            }
+        );
+        assert_eq!(
+            Node::parse("0 1 5 7 7").unwrap_err(),
+            ParseError::TrailingNumbers {
+                position: 3,
+                count: 2,
+            }
+        );
+        assert_eq!(
+            Node::parse("").unwrap_err().to_string(),
+            "Expected a node header of two numbers at position 0, but the input ended"
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested() {
+        // A chain of nodes each with one child and one metadata entry
+        let depth = 500_000;
+        let mut numbers = vec!["1 1"; depth];
+        numbers.push("0 1 5");
+        numbers.extend(vec!["1"; depth]);
+        let input = numbers.join(" ");
+
+        assert_eq!(star_one(&input), 5 + depth);
+        assert_eq!(star_two(&input), 5);
+        assert_eq!(sum_and_value(&input), Ok((5 + depth, 5)));
+    }
 }
@@ -1,134 +1,4 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-
-type NodePointer<T> = Rc<RefCell<Node<T>>>;
-
-struct Node<T> {
-    value: T,
-    next: Option<NodePointer<T>>,
-    previous: Option<NodePointer<T>>,
-}
-
-impl<T> Node<T> {
-    fn new(value: T) -> NodePointer<T> {
-        let node = Rc::new(RefCell::new(Self {
-            value,
-            next: None,
-            previous: None,
-        }));
-
-        {
-            let mut mut_node = node.borrow_mut();
-
-            mut_node.next = Some(node.clone());
-            mut_node.previous = Some(node.clone());
-        }
-
-        node
-    }
-
-    fn next(&self) -> NodePointer<T> {
-        Rc::clone(
-            &self
-                .next
-                .as_ref()
-                .expect("All nodes should have a next pointer"),
-        )
-    }
-
-    fn previous(&self) -> NodePointer<T> {
-        Rc::clone(
-            &self
-                .previous
-                .as_ref()
-                .expect("All nodes should have a next pointer"),
-        )
-    }
-
-    fn value(&self) -> &T {
-        &self.value
-    }
-
-    fn clockwise(&self, distance: usize) -> NodePointer<T> {
-        let mut current: NodePointer<T> = Rc::clone(
-            self.next
-                .as_ref()
-                .expect("All nodes should have a next pointer"),
-        );
-
-        for _ in 0..distance - 1 {
-            current = {
-                let borrowed = current.borrow();
-                Rc::clone(
-                    borrowed
-                        .next
-                        .as_ref()
-                        .expect("All nodes should have a next pointer"),
-                )
-            }
-        }
-
-        current
-    }
-
-    fn counter_clockwise(&self, distance: usize) -> NodePointer<T> {
-        let mut current: NodePointer<T> = Rc::clone(
-            self.previous
-                .as_ref()
-                .expect("All nodes should have a next pointer"),
-        );
-
-        for _ in 0..distance - 1 {
-            current = {
-                let borrowed = current.borrow();
-                Rc::clone(
-                    borrowed
-                        .previous
-                        .as_ref()
-                        .expect("All nodes should have a next pointer"),
-                )
-            };
-        }
-
-        current
-    }
-
-    fn remove(&mut self) -> &T {
-        let previous: NodePointer<T> = Rc::clone(
-            self.previous
-                .as_ref()
-                .expect("All nodes should have a next poiner"),
-        );
-        let next: NodePointer<T> = Rc::clone(
-            self.next
-                .as_ref()
-                .expect("All nodes should have a next poiner"),
-        );
-
-        previous.borrow_mut().next = Some(Rc::clone(&next));
-        next.borrow_mut().previous = Some(previous);
-
-        &self.value
-    }
-
-    fn insert_after(node: NodePointer<T>, value: T) -> NodePointer<T> {
-        let next: NodePointer<T> = Rc::clone(
-            node.borrow()
-                .next
-                .as_ref()
-                .expect("All nodes should have a next poiner"),
-        );
-        let new = Self::new(value);
-
-        node.borrow_mut().next = Some(Rc::clone(&new));
-        next.borrow_mut().previous = Some(Rc::clone(&new));
-
-        new.borrow_mut().previous = Some(Rc::clone(&node));
-        new.borrow_mut().next = Some(Rc::clone(&next));
-
-        new
-    }
-}
+use std::collections::VecDeque;
 
 fn print(marbles: &[usize], current_idx: usize) -> String {
     let repr = marbles
@@ -145,10 +15,53 @@ fn print(marbles: &[usize], current_idx: usize) -> String {
     repr
 }
 
+// Every `divisor`th marble is kept by the player along with the marble
+// `counter_clockwise` steps from the current one. All of the implementations
+// below play by them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rules {
+    pub divisor: usize,
+    pub counter_clockwise: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            divisor: 23,
+            counter_clockwise: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GameResult {
+    pub scores: Vec<usize>,
+    // The first player with the high score
+    pub winner: usize,
+}
+
+impl GameResult {
+    fn new(scores: Vec<usize>) -> Self {
+        let high_score = scores.iter().cloned().max().unwrap();
+        let winner = scores
+            .iter()
+            .position(|&score| score == high_score)
+            .unwrap();
+
+        GameResult { scores, winner }
+    }
+
+    pub fn high_score(&self) -> usize {
+        self.scores[self.winner]
+    }
+}
+
 // This was efficient enough for part 1, but all the removes
 // and inserts being O(n) on `Vec` was way too slow for part 2.
 // Kept for historical purposes
-pub fn solve(num_players: usize, last_marble_points: usize) -> usize {
+pub fn play_naive(num_players: usize, last_marble_points: usize, rules: Rules) -> GameResult {
+    assert!(rules.divisor > 0, "The special rule divisor can't be zero");
+
     let mut scores = vec![0; num_players];
     let mut marbles = vec![0];
     let mut current_idx = 0;
@@ -156,55 +69,152 @@ pub fn solve(num_players: usize, last_marble_points: usize) -> usize {
     marbles.reserve(last_marble_points);
 
     for marble_score in 1..last_marble_points + 1 {
-        if marble_score % 23 != 0 {
-            let insert_at = (current_idx + 1) % marbles.len();
-            current_idx = insert_at + 1;
+        if marble_score % rules.divisor != 0 {
+            if marbles.is_empty() {
+                current_idx = 0;
+            } else {
+                let insert_at = (current_idx + 1) % marbles.len();
+                current_idx = insert_at + 1;
+            }
             marbles.insert(current_idx, marble_score); // O(n)
 
             assert!(marbles[current_idx] == marble_score);
         } else {
             scores[current_player_idx] += marble_score;
-            let seven_counter_clockwise = match current_idx.overflowing_sub(7) {
-                (idx, false) => idx,
-                (overflowed_value, true) => {
-                    marbles.len() - (usize::max_value() - overflowed_value + 1)
-                }
-            };
 
-            scores[current_player_idx] += marbles.remove(seven_counter_clockwise); // O(n)
+            if !marbles.is_empty() {
+                let steps = rules.counter_clockwise % marbles.len();
+                let counter_clockwise = (current_idx + marbles.len() - steps) % marbles.len();
+
+                scores[current_player_idx] += marbles.remove(counter_clockwise); // O(n)
 
-            current_idx = seven_counter_clockwise;
+                // The marble clockwise of the removed one, which has moved
+                // into its place unless it was at the end.
+                current_idx = if marbles.is_empty() {
+                    0
+                } else {
+                    counter_clockwise % marbles.len()
+                };
+            }
         }
 
         current_player_idx = (current_player_idx + 1) % scores.len();
     }
 
-    scores.into_iter().max().unwrap()
+    GameResult::new(scores)
 }
 
-pub fn solve_efficient(num_players: usize, last_marble_points: usize) -> usize {
+pub fn solve_naive(num_players: usize, last_marble_points: usize) -> usize {
+    play_naive(num_players, last_marble_points, Rules::default()).high_score()
+}
+
+// A doubly linked ring where each marble is identified by its value, which
+// is also its index in `links`. Removed marbles keep their slot but are no
+// longer linked to.
+pub fn play_linked(num_players: usize, last_marble_points: usize, rules: Rules) -> GameResult {
+    assert!(rules.divisor > 0, "The special rule divisor can't be zero");
+    assert!(
+        last_marble_points < u32::MAX as usize,
+        "Expected at most {} marbles",
+        u32::MAX
+    );
+
     let mut scores = vec![0; num_players];
     let mut current_player_idx = 0;
-    let mut current: NodePointer<usize> = Node::new(0);
+    // (previous, next) for every marble
+    let mut links: Vec<(u32, u32)> = Vec::with_capacity(last_marble_points + 1);
+    let mut current = 0;
+    let mut marble_count = 1;
+    links.push((0, 0));
+
+    for marble_score in 1..=last_marble_points {
+        let marble = marble_score as u32;
+
+        if marble_score % rules.divisor != 0 {
+            if marble_count == 0 {
+                links.push((marble, marble));
+            } else {
+                let previous = links[current as usize].1;
+                let next = links[previous as usize].1;
 
-    for marble_score in 1..last_marble_points + 1 {
-        if marble_score % 23 != 0 {
-            let node = current.borrow().clockwise(1);
+                links.push((previous, next));
+                links[previous as usize].1 = marble;
+                links[next as usize].0 = marble;
+            }
+            current = marble;
+            marble_count += 1;
+        } else {
+            links.push((marble, marble));
+            scores[current_player_idx] += marble_score;
+
+            if marble_count > 0 {
+                let mut removed = current;
+                for _ in 0..rules.counter_clockwise % marble_count {
+                    removed = links[removed as usize].0;
+                }
+                let (previous, next) = links[removed as usize];
+                links[previous as usize].1 = next;
+                links[next as usize].0 = previous;
+
+                scores[current_player_idx] += removed as usize;
+                current = next;
+                marble_count -= 1;
+            }
+        }
+
+        current_player_idx = (current_player_idx + 1) % scores.len();
+    }
+
+    GameResult::new(scores)
+}
 
-            current = Node::insert_after(node, marble_score);
+pub fn solve_efficient(num_players: usize, last_marble_points: usize) -> usize {
+    play_linked(num_players, last_marble_points, Rules::default()).high_score()
+}
+
+// The back of the deque is always the current marble, moving around the
+// circle is rotating the deque. Both insertion and removal only ever happen
+// next to the current marble so everything is amortized O(1).
+pub fn play(num_players: usize, last_marble_points: usize, rules: Rules) -> GameResult {
+    assert!(rules.divisor > 0, "The special rule divisor can't be zero");
+
+    let mut scores = vec![0; num_players];
+    let mut current_player_idx = 0;
+    let mut marbles = VecDeque::with_capacity(last_marble_points + 1);
+    marbles.push_back(0);
 
-            assert!(current.borrow().value() == &marble_score);
+    for marble_score in 1..=last_marble_points {
+        if marble_score % rules.divisor != 0 {
+            if !marbles.is_empty() {
+                marbles.rotate_left(1);
+            }
+            marbles.push_back(marble_score);
         } else {
             scores[current_player_idx] += marble_score;
-            let node = current.borrow().counter_clockwise(7);
-            current = node.borrow().next();
-            scores[current_player_idx] += node.borrow_mut().remove();
+
+            if !marbles.is_empty() {
+                let distance = rules.counter_clockwise % marbles.len();
+                marbles.rotate_right(distance);
+                scores[current_player_idx] += marbles.pop_back().unwrap();
+            }
+            if !marbles.is_empty() {
+                marbles.rotate_left(1);
+            }
         }
 
         current_player_idx = (current_player_idx + 1) % scores.len();
     }
 
-    scores.into_iter().max().unwrap()
+    GameResult::new(scores)
+}
+
+pub fn solve_deque(num_players: usize, last_marble_points: usize) -> usize {
+    play(num_players, last_marble_points, Rules::default()).high_score()
+}
+
+// The fastest of the implementations, see `bench_day09_implementations`
+pub fn solve(num_players: usize, last_marble_points: usize) -> usize {
+    solve_efficient(num_players, last_marble_points)
 }
 
 #[cfg(test)]
@@ -221,6 +231,26 @@ mod tests {
         assert_eq!(solve(30, 5807), 37305);
     }
 
+    #[test]
+    fn test_star_one_naive() {
+        assert_eq!(solve_naive(9, 25), 32);
+        assert_eq!(solve_naive(10, 1618), 8317);
+        assert_eq!(solve_naive(13, 7999), 146373);
+        assert_eq!(solve_naive(17, 1104), 2764);
+        assert_eq!(solve_naive(21, 6111), 54718);
+        assert_eq!(solve_naive(30, 5807), 37305);
+    }
+
+    #[test]
+    fn test_star_one_deque() {
+        assert_eq!(solve_deque(9, 25), 32);
+        assert_eq!(solve_deque(10, 1618), 8317);
+        assert_eq!(solve_deque(13, 7999), 146373);
+        assert_eq!(solve_deque(17, 1104), 2764);
+        assert_eq!(solve_deque(21, 6111), 54718);
+        assert_eq!(solve_deque(30, 5807), 37305);
+    }
+
     #[test]
     fn test_star_one_efficient() {
         assert_eq!(solve_efficient(9, 25), 32);
@@ -230,4 +260,66 @@ mod tests {
         assert_eq!(solve_efficient(21, 6111), 54718);
         assert_eq!(solve_efficient(30, 5807), 37305);
     }
+
+    #[test]
+    fn test_play() {
+        let result = play(9, 25, Rules::default());
+        assert_eq!(result.scores, vec![0, 0, 0, 0, 32, 0, 0, 0, 0]);
+        assert_eq!(result.winner, 4);
+        assert_eq!(result.high_score(), 32);
+
+        let result = play(10, 1618, Rules::default());
+        assert_eq!(result.high_score(), 8317);
+        assert_eq!(result.scores.iter().filter(|&&s| s == 8317).count(), 1);
+    }
+
+    // Every implementation has to play by the rules it's given
+    fn play_all(num_players: usize, last_marble_points: usize, rules: Rules) -> GameResult {
+        let result = play(num_players, last_marble_points, rules);
+
+        assert_eq!(play_naive(num_players, last_marble_points, rules), result);
+        assert_eq!(play_linked(num_players, last_marble_points, rules), result);
+
+        result
+    }
+
+    #[test]
+    fn test_play_rule_variants() {
+        let never = Rules {
+            divisor: 10_000,
+            counter_clockwise: 7,
+        };
+        let result = play_all(3, 100, never);
+        assert_eq!(result.scores, vec![0, 0, 0]);
+        assert_eq!(result.winner, 0);
+
+        // With the circle at 0 2 1 (3) the fourth marble takes marble 1
+        let rules = Rules {
+            divisor: 4,
+            counter_clockwise: 1,
+        };
+        assert_eq!(play_all(2, 4, rules).scores, vec![0, 4 + 1]);
+
+        // Every marble is special, the first one empties the circle
+        let rules = Rules {
+            divisor: 1,
+            counter_clockwise: 3,
+        };
+        assert_eq!(play_all(2, 3, rules).scores, vec![1 + 3, 2]);
+
+        // Every other marble takes the current one, the one just placed
+        let rules = Rules {
+            divisor: 2,
+            counter_clockwise: 0,
+        };
+        assert_eq!(play_all(2, 5, rules).scores, vec![0, 2 + 1 + 4 + 3]);
+
+        let rules = Rules {
+            divisor: 5,
+            counter_clockwise: 12,
+        };
+        for num_players in 1..6 {
+            play_all(num_players, 200, rules);
+        }
+    }
 }
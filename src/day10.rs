@@ -1,5 +1,238 @@
 use std::ops::Add;
 
+const GLYPH_HEIGHT: usize = 10;
+
+// The font messages are written in. Every column of every glyph has at least
+// one lit pixel.
+#[rustfmt::skip]
+static FONT: &[(char, [&str; GLYPH_HEIGHT])] = &[
+    (
+        'A',
+        [
+            "..##..",
+            ".#..#.",
+            "#....#",
+            "#....#",
+            "#....#",
+            "######",
+            "#....#",
+            "#....#",
+            "#....#",
+            "#....#",
+        ],
+    ),
+    (
+        'B',
+        [
+            "#####.",
+            "#....#",
+            "#....#",
+            "#....#",
+            "#####.",
+            "#....#",
+            "#....#",
+            "#....#",
+            "#....#",
+            "#####.",
+        ],
+    ),
+    (
+        'C',
+        [
+            ".####.",
+            "#....#",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#....#",
+            ".####.",
+        ],
+    ),
+    (
+        'E',
+        [
+            "######",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#####.",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "######",
+        ],
+    ),
+    (
+        'F',
+        [
+            "######",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#####.",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+        ],
+    ),
+    (
+        'G',
+        [
+            ".####.",
+            "#....#",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#..###",
+            "#....#",
+            "#....#",
+            "#...##",
+            ".###.#",
+        ],
+    ),
+    (
+        'H',
+        [
+            "#....#",
+            "#....#",
+            "#....#",
+            "#....#",
+            "######",
+            "#....#",
+            "#....#",
+            "#....#",
+            "#....#",
+            "#....#",
+        ],
+    ),
+    (
+        'J',
+        [
+            "...###",
+            "....#.",
+            "....#.",
+            "....#.",
+            "....#.",
+            "....#.",
+            "....#.",
+            "#...#.",
+            "#...#.",
+            ".###..",
+        ],
+    ),
+    (
+        'K',
+        [
+            "#....#",
+            "#...#.",
+            "#..#..",
+            "#.#...",
+            "##....",
+            "##....",
+            "#.#...",
+            "#..#..",
+            "#...#.",
+            "#....#",
+        ],
+    ),
+    (
+        'L',
+        [
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "######",
+        ],
+    ),
+    (
+        'N',
+        [
+            "#....#",
+            "##...#",
+            "##...#",
+            "#.#..#",
+            "#.#..#",
+            "#..#.#",
+            "#..#.#",
+            "#...##",
+            "#...##",
+            "#....#",
+        ],
+    ),
+    (
+        'P',
+        [
+            "#####.",
+            "#....#",
+            "#....#",
+            "#....#",
+            "#####.",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+            "#.....",
+        ],
+    ),
+    (
+        'R',
+        [
+            "#####.",
+            "#....#",
+            "#....#",
+            "#....#",
+            "#####.",
+            "#..#..",
+            "#...#.",
+            "#...#.",
+            "#....#",
+            "#....#",
+        ],
+    ),
+    (
+        'X',
+        [
+            "#....#",
+            "#....#",
+            ".#..#.",
+            ".#..#.",
+            "..##..",
+            "..##..",
+            ".#..#.",
+            ".#..#.",
+            "#....#",
+            "#....#",
+        ],
+    ),
+    (
+        'Z',
+        [
+            "######",
+            ".....#",
+            ".....#",
+            "....#.",
+            "...#..",
+            "..#...",
+            ".#....",
+            "#.....",
+            "#.....",
+            "######",
+        ],
+    ),
+];
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 struct Vector {
     x: i64,
@@ -56,6 +289,13 @@ impl Particle {
     fn tick(&mut self) {
         self.position = self.position + self.velocity;
     }
+
+    fn position_at(&self, ticks: i64) -> Vector {
+        Vector::new(
+            self.position.x + self.velocity.x * ticks,
+            self.position.y + self.velocity.y * ticks,
+        )
+    }
 }
 
 fn parse(input: &str) -> Vec<Particle> {
@@ -80,13 +320,82 @@ fn parse(input: &str) -> Vec<Particle> {
 }
 
 fn extract_extremes(particles: &[Particle]) -> ((i64, i64), (i64, i64)) {
-    let max_x = particles.iter().map(|p| p.position.x).max().unwrap();
-    let max_y = particles.iter().map(|p| p.position.y).max().unwrap();
+    extremes_at(particles, 0)
+}
+
+fn extremes_at(particles: &[Particle], ticks: i64) -> ((i64, i64), (i64, i64)) {
+    let mut positions = particles.iter().map(|p| p.position_at(ticks));
+    let first = positions.next().unwrap();
+
+    positions.fold(
+        ((first.x, first.x), (first.y, first.y)),
+        |((max_x, min_x), (max_y, min_y)), position| {
+            (
+                (max_x.max(position.x), min_x.min(position.x)),
+                (max_y.max(position.y), min_y.min(position.y)),
+            )
+        },
+    )
+}
+
+fn bounding_area_at(particles: &[Particle], ticks: i64) -> i64 {
+    let ((max_x, min_x), (max_y, min_y)) = extremes_at(particles, ticks);
+
+    (max_x - min_x + 1) * (max_y - min_y + 1)
+}
+
+// Width plus height of the bounding box. Both are the difference between the
+// max and min of linear functions of time, so the spread is convex in time.
+fn spread_at(particles: &[Particle], ticks: i64) -> i64 {
+    let ((max_x, min_x), (max_y, min_y)) = extremes_at(particles, ticks);
+
+    (max_x - min_x) + (max_y - min_y)
+}
+
+// Since the spread is convex it stops shrinking exactly once, the first tick
+// after which it doesn't shrink is found by doubling an upper bound and then
+// binary searching.
+fn estimate_convergence(particles: &[Particle]) -> i64 {
+    let shrinks = |ticks: i64| spread_at(particles, ticks + 1) < spread_at(particles, ticks);
+
+    let mut high = 1;
+    while shrinks(high) {
+        high *= 2;
+    }
 
-    let min_x = particles.iter().map(|p| p.position.x).min().unwrap();
-    let min_y = particles.iter().map(|p| p.position.y).min().unwrap();
+    let mut low = 0;
+    while low < high {
+        let mid = low + (high - low) / 2;
 
-    ((max_x, min_x), (max_y, min_y))
+        if shrinks(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    low
+}
+
+// The particles converge on the message and then drift apart again, so the
+// message appears when the area of their bounding box is the smallest. The
+// smallest spread is close to that moment, the area is minimized from there.
+// Leaves the particles at that moment and returns how many ticks it took.
+fn converge(particles: &mut [Particle]) -> usize {
+    let mut ticks = estimate_convergence(particles);
+
+    while ticks > 0 && bounding_area_at(particles, ticks - 1) < bounding_area_at(particles, ticks) {
+        ticks -= 1;
+    }
+    while bounding_area_at(particles, ticks + 1) < bounding_area_at(particles, ticks) {
+        ticks += 1;
+    }
+
+    for particle in particles.iter_mut() {
+        particle.position = particle.position_at(ticks);
+    }
+
+    ticks as usize
 }
 
 fn format_particles(particles: &[Particle]) -> String {
@@ -107,7 +416,43 @@ fn format_particles(particles: &[Particle]) -> String {
         .join("\n")
 }
 
-pub fn star_one(input: &str, ticks: usize) -> String {
+// Glyphs are separated by columns without lit pixels, unrecognized glyphs
+// are decoded as `?`.
+pub fn decode(rendered: &str) -> String {
+    let rows = rendered
+        .lines()
+        .map(|row| row.trim().as_bytes())
+        .collect::<Vec<_>>();
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let is_lit = |column: usize| rows.iter().any(|row| row.get(column) == Some(&b'#'));
+    let mut text = String::new();
+    let mut column = 0;
+
+    while column < width {
+        if !is_lit(column) {
+            column += 1;
+            continue;
+        }
+
+        let start = column;
+        while column < width && is_lit(column) {
+            column += 1;
+        }
+
+        let glyph = FONT.iter().find(|(_, glyph)| {
+            glyph.len() == rows.len()
+                && glyph
+                    .iter()
+                    .zip(rows.iter())
+                    .all(|(glyph_row, row)| row.get(start..column) == Some(glyph_row.as_bytes()))
+        });
+        text.push(glyph.map(|&(c, _)| c).unwrap_or('?'));
+    }
+
+    text
+}
+
+pub fn render_after(input: &str, ticks: usize) -> String {
     let mut particles = parse(input);
 
     for i in 0..ticks {
@@ -121,9 +466,42 @@ pub fn star_one(input: &str, ticks: usize) -> String {
     result
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Message {
+    pub rendered: String,
+    pub text: String,
+    // How long it takes for the message to appear
+    pub seconds: usize,
+}
+
+// Both parts are answered by the same simulation
+pub fn find_message(input: &str) -> Message {
+    let mut particles = parse(input);
+    let seconds = converge(&mut particles);
+    let rendered = format_particles(&particles);
+
+    Message {
+        text: decode(&rendered),
+        rendered,
+        seconds,
+    }
+}
+
+pub fn message(input: &str) -> String {
+    find_message(input).rendered
+}
+
+pub fn star_one(input: &str) -> String {
+    find_message(input).text
+}
+
+pub fn star_two(input: &str) -> usize {
+    find_message(input).seconds
+}
+
 #[cfg(test)]
 mod tests {
-    use super::star_one;
+    use super::{decode, find_message, message, parse, render_after, star_two, FONT};
     static EXAMPLE: &str = "position=< 9,  1> velocity=< 0,  2>
 position=< 7,  0> velocity=<-1,  0>
 position=< 3, -2> velocity=<-1,  1>
@@ -166,6 +544,60 @@ position=<-3,  6> velocity=< 2, -1>";
 
     #[test]
     fn test_star_one() {
-        assert_eq!(star_one(EXAMPLE, 3), EXEPCTED_OUTPUT)
+        assert_eq!(render_after(EXAMPLE, 3), EXEPCTED_OUTPUT)
+    }
+
+    #[test]
+    fn test_message() {
+        assert_eq!(message(EXAMPLE), EXEPCTED_OUTPUT);
+        assert_eq!(message("position=< 1,  2> velocity=< 0,  0>"), "#");
+    }
+
+    #[test]
+    fn test_star_two() {
+        assert_eq!(star_two(EXAMPLE), 3);
+
+        let found = find_message(EXAMPLE);
+        assert_eq!(found.seconds, 3);
+        assert_eq!(found.rendered, EXEPCTED_OUTPUT);
+        assert_eq!(found.text, "??");
+    }
+
+    #[test]
+    fn test_converge_after_many_ticks() {
+        let ticks = 1_000_000_000;
+        let input = parse(EXAMPLE)
+            .into_iter()
+            .map(|particle| {
+                let position = particle.position_at(3 - ticks);
+                format!(
+                    "position=<{}, {}> velocity=<{}, {}>",
+                    position.x, position.y, particle.velocity.x, particle.velocity.y
+                )
+            }).collect::<Vec<_>>()
+            .join("\n");
+
+        let found = find_message(&input);
+        assert_eq!(found.seconds, ticks as usize);
+        assert_eq!(found.rendered, EXEPCTED_OUTPUT);
+    }
+
+    #[test]
+    fn test_decode() {
+        let glyph = |c: char| FONT.iter().find(|&&(f, _)| f == c).unwrap().1;
+        let rendered = (0..10)
+            .map(|row| {
+                "HELLAXZ"
+                    .chars()
+                    .map(|c| glyph(c)[row])
+                    .collect::<Vec<_>>()
+                    .join("..")
+            }).collect::<Vec<_>>()
+            .join("\n");
+
+        assert_eq!(decode(&rendered), "HELLAXZ");
+
+        // The example's letters are smaller than the font
+        assert_eq!(decode(EXEPCTED_OUTPUT), "??");
     }
 }
@@ -1,3 +1,6 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
 fn nth_digit(number: usize, idx: usize) -> Option<usize> {
     let mut n = number;
     let mut i = 1;
@@ -33,50 +36,122 @@ pub fn build_grid(serial: usize, size: usize) -> Vec<Vec<i64>> {
         }).collect()
 }
 
+// Summed-area table of the grid, `sums[x][y]` holds the total power of every
+// cell left of and above `(x, y)` which makes the power of any square O(1).
+pub struct SummedArea {
+    sums: Vec<Vec<i64>>,
+}
+
+impl SummedArea {
+    pub fn new(grid: &[Vec<i64>]) -> Self {
+        let width = grid.len();
+        let height = grid.first().map(|column| column.len()).unwrap_or(0);
+        let mut sums = vec![vec![0; height + 1]; width + 1];
+
+        for x in 0..width {
+            for y in 0..height {
+                sums[x + 1][y + 1] = grid[x][y] + sums[x][y + 1] + sums[x + 1][y] - sums[x][y];
+            }
+        }
+
+        Self { sums }
+    }
+
+    pub fn power(&self, location: &(usize, usize), window_size: usize) -> i64 {
+        let (x, y) = *location;
+        let (end_x, end_y) = (x + window_size, y + window_size);
+
+        self.sums[end_x][end_y] - self.sums[x][end_y] - self.sums[end_x][y] + self.sums[x][y]
+    }
+}
+
+// Every window position, including the ones touching the last row and column
+fn best_square(sums: &SummedArea, size: usize, window: usize) -> (i64, usize, usize) {
+    (0..=size - window)
+        .flat_map(|x| (0..=size - window).map(move |y| (x, y)))
+        .map(|coordinate| (sums.power(&coordinate, window), coordinate))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(power, (x, y))| (power, x + 1, y + 1))
+        .unwrap()
+}
+
+// Total power of the best `window` sized square and its top left cell
+pub fn best_fixed_window(serial: usize, size: usize, window: usize) -> (i64, usize, usize) {
+    assert!(
+        window > 0 && window <= size,
+        "Window {} doesn't fit in a grid of size {}",
+        window,
+        size
+    );
+    let sums = SummedArea::new(&build_grid(serial, size));
+
+    best_square(&sums, size, window)
+}
+
+// Total power of the best square of any size, its top left cell and its size
+pub fn best_any_window(serial: usize, size: usize) -> (i64, usize, usize, usize) {
+    let sums = SummedArea::new(&build_grid(serial, size));
+
+    (1..=size)
+        .map(|window| {
+            let (power, x, y) = best_square(&sums, size, window);
+
+            (power, x, y, window)
+        }).max_by(|(a, _, _, _), (b, _, _, _)| a.cmp(b))
+        .unwrap()
+}
+
+// The `n` squares with the highest total power across all window sizes, best
+// first. Equally powerful squares are ordered by position and then size.
+pub fn best_squares(serial: usize, size: usize, n: usize) -> Vec<(i64, usize, usize, usize)> {
+    if n == 0 {
+        return vec![];
+    }
+
+    let sums = SummedArea::new(&build_grid(serial, size));
+    // Min-heap of the best squares seen so far, the weakest one is on top
+    let mut best = BinaryHeap::with_capacity(n);
+
+    for window in 1..=size {
+        for x in 0..=size - window {
+            for y in 0..=size - window {
+                let power = sums.power(&(x, y), window);
+                let square = Reverse((power, Reverse((x + 1, y + 1, window))));
+
+                if best.len() < n {
+                    best.push(square);
+                } else if best.peek().map(|weakest| square < *weakest) == Some(true) {
+                    best.pop();
+                    best.push(square);
+                }
+            }
+        }
+    }
+
+    best.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((power, Reverse((x, y, window))))| (power, x, y, window))
+        .collect()
+}
+
 pub fn star_one(serial: usize, size: usize, window: usize) -> (usize, usize) {
-    let grid = build_grid(serial, size);
+    let (_, x, y) = best_fixed_window(serial, size, window);
 
-    let result = (0..size - window)
-        .flat_map(|x| {
-            (0..size - window)
-                .clone()
-                .map(|y| {
-                    let coordinate = (x, y);
-                    return (power(&grid, &coordinate, window), coordinate);
-                }).collect::<Vec<(i64, (usize, usize))>>()
-        }).max_by(|(a, _), (b, _)| a.cmp(b))
-        .and_then(|(power, (x, y))| Some((power, (x + 1, y + 1))))
-        .unwrap();
-
-    result.1
+    (x, y)
 }
 
 pub fn star_two(serial: usize, size: usize) -> (usize, usize, usize) {
-    let grid = build_grid(serial, size);
+    let (_, x, y, window) = best_any_window(serial, size);
 
-    // Who needs smart realisations when you have a fast language and some patience?
-    let (power, (x, y), final_size) = (0..size)
-        .map(|window| {
-            (0..size - window)
-                .flat_map(|x| {
-                    (0..size - window)
-                        .clone()
-                        .map(|y| {
-                            let coordinate = (x, y);
-                            return (power(&grid, &coordinate, window), coordinate);
-                        }).collect::<Vec<(i64, (usize, usize))>>()
-                }).max_by(|(a, _), (b, _)| a.cmp(b))
-                .and_then(|(power, (x, y))| Some((power, (x + 1, y + 1), window)))
-                .unwrap()
-        }).max_by(|(a, _, _), (b, _, _)| a.cmp(b))
-        .unwrap();
-
-    (x, y, final_size)
+    (x, y, window)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{build_grid, nth_digit, power, star_one, star_two};
+    use super::{
+        best_any_window, best_fixed_window, best_squares, build_grid, nth_digit, power, star_one,
+        star_two, SummedArea,
+    };
 
     #[test]
     fn test_star_one() {
@@ -86,9 +161,55 @@ mod tests {
 
     #[test]
     fn test_star_two() {
-        // These two are slow
-        // assert_eq!(star_two(18, 300), (90, 269, 16));
-        // assert_eq!(star_two(42, 300), (232, 251, 12));
+        assert_eq!(star_two(18, 300), (90, 269, 16));
+        assert_eq!(star_two(42, 300), (232, 251, 12));
+    }
+
+    #[test]
+    fn test_best_fixed_window() {
+        assert_eq!(best_fixed_window(18, 300, 3), (29, 33, 45));
+        assert_eq!(best_fixed_window(42, 300, 3), (30, 21, 61));
+
+        // The square covering the whole grid is a valid window
+        let grid = build_grid(18, 300);
+        assert_eq!(
+            best_fixed_window(18, 300, 300),
+            (power(&grid, &(0, 0), 300), 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_best_any_window() {
+        assert_eq!(best_any_window(18, 300), (113, 90, 269, 16));
+        assert_eq!(best_any_window(42, 300), (119, 232, 251, 12));
+
+        // Only a single window fits in a single cell grid
+        let grid = build_grid(18, 1);
+        assert_eq!(best_any_window(18, 1), (grid[0][0], 1, 1, 1));
+    }
+
+    #[test]
+    fn test_best_squares() {
+        let best = best_squares(18, 300, 5);
+
+        assert_eq!(best.len(), 5);
+        assert_eq!(best[0], (113, 90, 269, 16));
+        assert!(best.windows(2).all(|pair| pair[0].0 >= pair[1].0));
+
+        // Every square in a tiny grid, ranked
+        let grid = build_grid(42, 2);
+        let mut expected = vec![
+            (grid[0][0], 1, 1, 1),
+            (grid[0][1], 1, 2, 1),
+            (grid[1][0], 2, 1, 1),
+            (grid[1][1], 2, 2, 1),
+            (power(&grid, &(0, 0), 2), 1, 1, 2),
+        ];
+        expected.sort_by_key(|&(power, x, y, window)| (-power, x, y, window));
+
+        assert_eq!(best_squares(42, 2, 10), expected);
+        assert_eq!(best_squares(42, 2, 2), &expected[..2]);
+        assert_eq!(best_squares(42, 2, 0), vec![]);
     }
 
     #[test]
@@ -114,6 +235,19 @@ mod tests {
         assert_eq!(power(&grid, &coord, 3), 29);
     }
 
+    #[test]
+    fn test_summed_area() {
+        let grid = build_grid(42, 300);
+        let sums = SummedArea::new(&grid);
+
+        for &(location, window) in &[((0, 0), 1), ((20, 60), 3), ((231, 250), 12), ((0, 0), 300)] {
+            assert_eq!(
+                sums.power(&location, window),
+                power(&grid, &location, window)
+            );
+        }
+    }
+
     #[test]
     fn test_nth_digit() {
         assert_eq!(nth_digit(0, 2), None);
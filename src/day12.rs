@@ -1,5 +1,8 @@
-use std::collections::HashSet;
+use std::fmt;
 use std::iter;
+use std::mem;
+
+use cycle;
 
 #[derive(Debug)]
 struct Rule {
@@ -34,123 +37,337 @@ impl Rule {
     }
 }
 
-fn sum(state: &[bool], zero_point: usize, base_idx: i64) -> i64 {
-    state.iter().enumerate().fold(0, |acc, (idx, planted)| {
-        if !planted {
-            acc
-        } else {
-            acc + (base_idx + idx as i64 - zero_point as i64)
+// The rules compiled into a lookup table indexed by a five pot window, the
+// leftmost pot being the most significant bit. Windows without a rule die.
+struct RuleTable([bool; 32]);
+
+impl<'a> From<&'a [Rule]> for RuleTable {
+    fn from(rules: &'a [Rule]) -> Self {
+        let mut table = [false; 32];
+
+        for rule in rules {
+            table[window_index(&rule.pattern)] = rule.replacement;
         }
-    })
+
+        RuleTable(table)
+    }
 }
 
-fn parse(initial_state: &str, rules: &str, padding: usize) -> (Vec<Rule>, Vec<bool>) {
-    let rules = rules
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| l.len() > 0)
-        .map(Rule::from)
-        .collect();
-    let mut state: Vec<bool> = initial_state.trim().chars().map(|c| c == '#').collect();
+fn window_index(pattern: &[bool]) -> usize {
+    pattern
+        .iter()
+        .fold(0, |acc, &planted| (acc << 1) | planted as usize)
+}
 
-    for _ in 0..padding {
-        state.insert(0, false);
-        state.push(false);
-    }
+const WORD_BITS: usize = 64;
 
-    (rules, state)
+// A row of pots packed into words, pot `offset + idx` is bit `idx % 64` of word
+// `idx / 64`. The row only spans from the leftmost to the rightmost plant so
+// it can grow in either direction without being padded up front.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PotState {
+    words: Vec<u64>,
+    len: usize,
+    offset: i64,
 }
 
-fn find_cycle(initial_state: Vec<bool>, rules: &[Rule]) -> usize {
-    let mut state = initial_state;
-    let mut observed_states = HashSet::<Vec<bool>>::new();
+impl PotState {
+    fn with_len(len: usize, offset: i64) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+            len,
+            offset,
+        }
+    }
 
-    for (i, _) in iter::repeat(0).enumerate() {
-        state = next_generation(state, rules);
+    fn from_bools(state: &[bool], offset: i64) -> Self {
+        let mut pots = Self::with_len(state.len(), offset);
 
-        let trimmed_pattern = state
-            .iter()
-            .skip_while(|&x| !x)
-            .map(|&x| x.clone())
-            .collect::<Vec<bool>>();
+        for (idx, &planted) in state.iter().enumerate() {
+            pots.set(idx, planted);
+        }
+
+        pots.trimmed()
+    }
+
+    fn to_bools(&self) -> Vec<bool> {
+        (0..self.len).map(|idx| self.get(idx)).collect()
+    }
+
+    fn get(&self, idx: usize) -> bool {
+        idx < self.len && self.words[idx / WORD_BITS] & (1 << (idx % WORD_BITS)) != 0
+    }
+
+    fn set(&mut self, idx: usize, planted: bool) {
+        if idx >= self.len {
+            self.len = idx + 1;
+            self.words.resize(self.len.div_ceil(WORD_BITS), 0);
+        }
+
+        if planted {
+            self.words[idx / WORD_BITS] |= 1 << (idx % WORD_BITS);
+        } else {
+            self.words[idx / WORD_BITS] &= !(1 << (idx % WORD_BITS));
+        }
+    }
+
+    fn planted_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(word_idx, &word)| {
+            let mut remaining = word;
+
+            iter::from_fn(move || {
+                if remaining == 0 {
+                    return None;
+                }
+
+                let bit = remaining.trailing_zeros() as usize;
+                remaining &= remaining - 1;
 
-        if !observed_states.insert(trimmed_pattern) {
-            return i;
+                Some(word_idx * WORD_BITS + bit)
+            })
+        })
+    }
+
+    // Drops the empty pots on both ends
+    fn trimmed(&self) -> PotState {
+        let first = match self.planted_indices().next() {
+            Some(first) => first,
+            None => return PotState::with_len(0, self.offset),
+        };
+        let last = self.planted_indices().last().unwrap_or(first);
+        let mut pots = PotState::with_len(last - first + 1, self.offset + first as i64);
+
+        for idx in self.planted_indices() {
+            pots.set(idx - first, true);
+        }
+
+        pots
+    }
+
+    // The leftmost pot of the row
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    pub fn is_planted(&self, pot: i64) -> bool {
+        pot >= self.offset && self.get((pot - self.offset) as usize)
+    }
+
+    // The numbers of every pot with a plant, from left to right
+    pub fn planted(&self) -> impl Iterator<Item = i64> + '_ {
+        self.planted_indices()
+            .map(move |idx| self.offset + idx as i64)
+    }
+
+    pub fn sum(&self) -> i64 {
+        self.planted().sum()
+    }
+}
+
+impl fmt::Display for PotState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for idx in 0..self.len {
+            write!(f, "{}", if self.get(idx) { '#' } else { '.' })?;
         }
+
+        Ok(())
     }
+}
 
-    assert!(false, "If you are here something is definitely off");
-    return 0; // This should never happen
+// Every generation starting with the initial state
+pub struct Generations {
+    table: RuleTable,
+    state: PotState,
 }
 
-fn next_generation(mut state: Vec<bool>, rules: &[Rule]) -> Vec<bool> {
-    let state_size = state.len();
+impl Iterator for Generations {
+    type Item = PotState;
+
+    fn next(&mut self) -> Option<PotState> {
+        let next = next_generation_packed(&self.state, &self.table);
 
-    state = state
+        Some(mem::replace(&mut self.state, next))
+    }
+}
+
+pub fn generations(initial_state: &str, rules: &str) -> Generations {
+    let (parsed_rules, initial_parsed_state) = parse(initial_state, rules);
+
+    Generations {
+        table: RuleTable::from(&parsed_rules[..]),
+        state: PotState::from_bools(&initial_parsed_state, 0),
+    }
+}
+
+// Runs `num_generations` generations calling `observer` with the number and
+// state of every generation along the way, including the initial one.
+pub fn observe(
+    initial_state: &str,
+    rules: &str,
+    num_generations: usize,
+    observer: &mut dyn FnMut(usize, &PotState),
+) -> PotState {
+    let mut last = None;
+
+    for (generation, state) in generations(initial_state, rules)
+        .enumerate()
+        .take(num_generations + 1)
+    {
+        observer(generation, &state);
+        last = Some(state);
+    }
+
+    last.unwrap()
+}
+
+fn sum(state: &[bool], offset: i64) -> i64 {
+    state
         .iter()
         .enumerate()
-        .map(|(id, _)| {
-            if id == 0 || id == 1 || id == state_size - 1 || id == state_size - 2 {
-                return false;
-            }
+        .filter(|&(_, &planted)| planted)
+        .map(|(idx, _)| offset + idx as i64)
+        .sum()
+}
 
-            let part = &state[id - 2..id + 3];
+fn parse(initial_state: &str, rules: &str) -> (Vec<Rule>, Vec<bool>) {
+    let rules: Vec<Rule> = rules
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(Rule::from)
+        .collect();
+    let state = initial_state.trim().chars().map(|c| c == '#').collect();
 
-            for rule in rules {
-                if rule.matches(part) {
-                    return rule.replacement;
-                }
-            }
+    assert!(
+        !rules
+            .iter()
+            .any(|rule| rule.replacement && rule.pattern.iter().all(|&planted| !planted)),
+        "A rule planting `.....` would fill infinitely many pots"
+    );
 
-            false
-        }).collect();
+    (rules, state)
+}
 
-    let mut to_append = vec![];
+// The pattern of generation `start` reappears every `period` generations,
+// moved `shift` pots to the right each time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cycle {
+    pub start: usize,
+    pub period: usize,
+    pub shift: i64,
+}
 
-    if state[state.len() - 1] {
-        to_append.push(false);
-    }
+// Runs until a pattern repeats, regardless of where in the row it is. Returns
+// every state up to but not including the repeat.
+fn detect_cycle(generations: Generations) -> (Vec<PotState>, Cycle) {
+    // The repeat isn't part of the history, only where it ended up is needed
+    let mut repeat_offset = 0;
+    let generations = generations.inspect(|state| repeat_offset = state.offset);
+    let (history, cycle) = cycle::detect(generations, |state| (state.words.clone(), state.len))
+        .expect("Generations never run out");
+
+    let cycle = Cycle {
+        start: cycle.start,
+        period: cycle.period,
+        shift: repeat_offset - history[cycle.start].offset,
+    };
+
+    (history, cycle)
+}
+
+pub fn find_cycle(initial_state: &str, rules: &str) -> Cycle {
+    detect_cycle(generations(initial_state, rules)).1
+}
 
-    if state[state.len() - 2] {
-        to_append.push(false);
+// Every pot within two of a plant can change, the row is widened by that much
+// on both sides before applying the rules and trimmed afterwards.
+fn next_generation(state: &[bool], offset: i64, rules: &[Rule]) -> (Vec<bool>, i64) {
+    let padded = [false; 4]
+        .iter()
+        .chain(state)
+        .chain(&[false; 4])
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let next = padded
+        .windows(5)
+        .map(|part| {
+            rules
+                .iter()
+                .find(|rule| rule.matches(part))
+                .map(|rule| rule.replacement)
+                .unwrap_or(false)
+        }).collect::<Vec<_>>();
+
+    let first = next.iter().position(|&planted| planted);
+    let last = next.iter().rposition(|&planted| planted);
+
+    match (first, last) {
+        (Some(first), Some(last)) => (next[first..=last].to_vec(), offset - 2 + first as i64),
+        _ => (vec![], offset),
     }
+}
 
-    if state[state.len() - 3] {
-        to_append.push(false);
+// Same as `next_generation` but the window around each pot is kept in a shift
+// register and looked up in the rule table.
+fn next_generation_packed(state: &PotState, table: &RuleTable) -> PotState {
+    let mut next = PotState::with_len(state.len + 4, state.offset - 2);
+    let mut window = 0;
+
+    // Pot `idx` of the next generation is pot `idx - 2` of this one
+    for idx in 0..next.len {
+        window = ((window << 1) | state.get(idx) as usize) & 31;
+
+        if table.0[window] {
+            next.set(idx, true);
+        }
     }
 
-    state.extend(to_append);
+    next.trimmed()
+}
 
-    state
+pub fn star_one(initial_state: &str, rules: &str, num_generations: usize) -> i64 {
+    generations(initial_state, rules)
+        .nth(num_generations)
+        .unwrap()
+        .sum()
 }
 
-pub fn star_one(initial_state: &str, rules: &str, padding: usize, num_generations: usize) -> i64 {
-    let (parsed_rules, initial_parsed_state) = parse(initial_state, rules, padding);
-    let mut state = initial_parsed_state;
+pub fn star_one_naive(initial_state: &str, rules: &str, num_generations: usize) -> i64 {
+    let (parsed_rules, mut state) = parse(initial_state, rules);
+    let mut offset = 0;
 
     for _ in 0..num_generations {
-        state = next_generation(state, &parsed_rules);
+        let (next, next_offset) = next_generation(&state, offset, &parsed_rules);
+        state = next;
+        offset = next_offset;
     }
 
-    sum(&state, padding, 0)
+    sum(&state, offset)
 }
 
-pub fn star_two(initial_state: &str, rules: &str, padding: usize, num_generations: usize) -> i64 {
-    let (parsed_rules, initial_parsed_state) = parse(initial_state, rules, padding);
-    let mut state = initial_parsed_state.clone();
-    let cycle_at = find_cycle(initial_parsed_state, &parsed_rules);
-    let cycle_idx = cycle_at + (num_generations % cycle_at);
+// Once in a cycle every plant moves `shift` pots per period, so the sum grows
+// linearly with the number of periods left.
+pub fn star_two(initial_state: &str, rules: &str, num_generations: usize) -> i64 {
+    let (history, cycle) = detect_cycle(generations(initial_state, rules));
 
-    for _ in 0..cycle_idx {
-        state = next_generation(state, &parsed_rules);
+    if num_generations < history.len() {
+        return history[num_generations].sum();
     }
 
-    sum(&state, padding, num_generations as i64 - cycle_idx as i64)
+    let periods = (num_generations - cycle.start) / cycle.period;
+    let state = &history[cycle.start + (num_generations - cycle.start) % cycle.period];
+    let plants = state.planted_indices().count() as i64;
+
+    state.sum() + periods as i64 * cycle.shift * plants
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        find_cycle, generations, next_generation, next_generation_packed, observe, parse, star_one,
+        star_one_naive, star_two, Cycle, PotState, RuleTable,
+    };
     static EXAMPLE_RULES: &str = "
 ...## => #
 ..#.. => #
@@ -170,8 +387,160 @@ mod tests {
     #[test]
     fn test_star_one() {
         assert_eq!(
-            star_one("#..#.#..##......###...###", EXAMPLE_RULES, 3, 20),
+            star_one("#..#.#..##......###...###", EXAMPLE_RULES, 20),
+            325
+        );
+        assert_eq!(
+            star_one_naive("#..#.#..##......###...###", EXAMPLE_RULES, 20),
             325
         );
     }
+
+    #[test]
+    fn test_star_two() {
+        let initial = "#..#.#..##......###...###";
+
+        assert_eq!(star_two(initial, EXAMPLE_RULES, 20), 325);
+        assert_eq!(
+            star_two(initial, EXAMPLE_RULES, 50_000_000_000),
+            999999999374
+        );
+
+        for generations in 0..200 {
+            assert_eq!(
+                star_two(initial, EXAMPLE_RULES, generations),
+                star_one(initial, EXAMPLE_RULES, generations)
+            );
+        }
+    }
+
+    #[test]
+    fn test_shifted_cycles() {
+        // A single plant moving left
+        assert_eq!(
+            find_cycle("#", "....# => #"),
+            Cycle {
+                start: 0,
+                period: 1,
+                shift: -2
+            }
+        );
+        assert_eq!(
+            star_two("#", "....# => #", 50_000_000_000),
+            -100_000_000_000
+        );
+
+        // Alternates between `#` and `##` while moving right two pots every
+        // other generation.
+        let rules = "..#.. => #\n.#... => #\n##... => #";
+        assert_eq!(
+            find_cycle("#", rules),
+            Cycle {
+                start: 0,
+                period: 2,
+                shift: 2
+            }
+        );
+        assert_eq!(star_two("#", rules, 50_000_000_000), 50_000_000_000);
+        assert_eq!(star_two("#", rules, 50_000_000_001), 100_000_000_001);
+
+        for generations in 0..50 {
+            assert_eq!(
+                star_two("#", rules, generations),
+                star_one("#", rules, generations)
+            );
+        }
+
+        // Everything dies
+        assert_eq!(star_two("#.#", "", 1000), 0);
+    }
+
+    #[test]
+    fn test_grows_left() {
+        // A single plant moving two pots to the left every generation
+        let rules = "....# => #";
+
+        assert_eq!(star_one("#", rules, 10), -20);
+        assert_eq!(star_one_naive("#", rules, 10), -20);
+        assert_eq!(star_one("#", rules, 1000), -2000);
+        assert_eq!(star_one_naive("#", rules, 1000), -2000);
+    }
+
+    #[test]
+    fn test_pots() {
+        let state = vec![false, true, false, true, true, false, false, true, false];
+        let mut pots = PotState::from_bools(&state, -3);
+
+        assert_eq!(pots.offset, -2);
+        assert_eq!(pots.to_bools(), &state[1..8]);
+        assert_eq!(pots.planted_indices().collect::<Vec<_>>(), vec![0, 2, 3, 6]);
+
+        pots.set(130, true);
+        pots.set(0, false);
+        assert_eq!(pots.len, 131);
+        assert_eq!(pots.sum(), 1 + 4 + 128);
+
+        let pots = pots.trimmed();
+        assert_eq!(pots.offset, 0);
+        assert_eq!(pots.len, 129);
+        assert_eq!(pots.sum(), 1 + 4 + 128);
+    }
+
+    #[test]
+    fn test_generations() {
+        let states = generations("#..#.#..##......###...###", EXAMPLE_RULES)
+            .take(3)
+            .map(|state| (state.offset(), state.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            states,
+            vec![
+                (0, String::from("#..#.#..##......###...###")),
+                (0, String::from("#...#....#.....#..#..#..#")),
+                (0, String::from("##..##...##....#..#..#..##")),
+            ]
+        );
+
+        let state = generations("#..#.#..##......###...###", EXAMPLE_RULES)
+            .nth(20)
+            .unwrap();
+        assert_eq!(state.offset(), -2);
+        assert_eq!(state.to_string(), "#....##....#####...#######....#.#..##");
+        assert!(state.is_planted(-2));
+        assert!(!state.is_planted(-1));
+        assert!(!state.is_planted(-3));
+        assert_eq!(state.planted().take(3).collect::<Vec<_>>(), vec![-2, 3, 4]);
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut sums = vec![];
+        let last = observe(
+            "#..#.#..##......###...###",
+            EXAMPLE_RULES,
+            20,
+            &mut |generation, state| sums.push((generation, state.sum())),
+        );
+
+        assert_eq!(sums.len(), 21);
+        assert_eq!(sums[0], (0, 3 + 5 + 8 + 9 + 16 + 17 + 18 + 22 + 23 + 24));
+        assert_eq!(sums[20], (20, 325));
+        assert_eq!(last.sum(), 325);
+    }
+
+    #[test]
+    fn test_next_generation_packed() {
+        let (rules, initial) = parse("#..#.#..##......###...###", EXAMPLE_RULES);
+        let table = RuleTable::from(&rules[..]);
+        let mut naive = (initial.clone(), 0);
+        let mut packed = PotState::from_bools(&initial, 0);
+
+        for _ in 0..100 {
+            naive = next_generation(&naive.0, naive.1, &rules);
+            packed = next_generation_packed(&packed, &table);
+
+            assert_eq!((packed.to_bools(), packed.offset), naive);
+        }
+    }
 }
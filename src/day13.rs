@@ -1,11 +1,12 @@
-use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
-
-use std::cell::RefCell;
 use std::rc::Rc;
+use std::str::FromStr;
+
+use viz::{self, Color};
 
 type Location = (usize, usize);
+type Grid = Vec<Vec<Option<TrackType>>>;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum Direction {
@@ -45,6 +46,38 @@ impl Direction {
         }
     }
 
+    // Like `along` but `None` when leaving the map to the top or left
+    fn neighbour(&self, location: &Location) -> Option<Location> {
+        let (x, y) = *location;
+        match self {
+            Direction::Up => y.checked_sub(1).map(|y| (x, y)),
+            Direction::Left => x.checked_sub(1).map(|x| (x, y)),
+            _ => Some(self.along(location)),
+        }
+    }
+
+    fn mask(&self) -> u8 {
+        match self {
+            Direction::Up => 1,
+            Direction::Right => 2,
+            Direction::Down => 4,
+            Direction::Left => 8,
+        }
+    }
+
+    fn opposite(&self) -> Self {
+        self.clockwise().clockwise()
+    }
+
+    fn side(&self) -> &'static str {
+        match self {
+            Direction::Up => "above",
+            Direction::Right => "to the right",
+            Direction::Down => "below",
+            Direction::Left => "to the left",
+        }
+    }
+
     fn counter_clockwise(&self) -> Self {
         match self {
             Direction::Up => Direction::Left,
@@ -133,14 +166,19 @@ impl TrackType {
 
 #[derive(Clone, Debug)]
 struct Cart {
+    // Carts are numbered in reading order on the initial map
+    id: usize,
+    location: Location,
     current_direction: Direction,
     current_action: Action,
     is_alive: bool,
 }
 
 impl Cart {
-    fn new(direction: Direction) -> Self {
+    fn new(id: usize, location: Location, direction: Direction) -> Self {
         Self {
+            id,
+            location,
             current_direction: direction,
             current_action: Action::default(),
             is_alive: true,
@@ -156,344 +194,515 @@ impl Cart {
     fn change_direction(&mut self, new_direction: Direction) {
         self.current_direction = new_direction;
     }
+
+    // Turns according to the track the cart is on and moves one step
+    fn step(&mut self, track_type: &Option<TrackType>) {
+        match track_type {
+            Some(TrackType::Intersection) => self.advance(),
+            Some(TrackType::Horizontal) | Some(TrackType::Vertical) => {
+                assert!(
+                    (track_type == &Some(TrackType::Horizontal)
+                        && (self.current_direction == Direction::Left
+                            || self.current_direction == Direction::Right))
+                        || (track_type == &Some(TrackType::Vertical)
+                            && (self.current_direction == Direction::Up
+                                || self.current_direction == Direction::Down))
+                );
+            }
+            Some(TrackType::Curve1) => {
+                // /
+                let new_direction = match self.current_direction {
+                    // /
+                    // |
+                    Direction::Up => Direction::Right,
+
+                    // -/
+                    Direction::Right => Direction::Up,
+
+                    // |
+                    // /
+                    Direction::Down => Direction::Left,
+
+                    // /--
+                    Direction::Left => Direction::Down,
+                };
+                self.change_direction(new_direction);
+            }
+            Some(TrackType::Curve2) => {
+                // \
+                let new_direction = match self.current_direction {
+                    // \
+                    // |
+                    Direction::Up => Direction::Left,
+
+                    // --\
+                    Direction::Right => Direction::Down,
+
+                    // |
+                    // \
+                    Direction::Down => Direction::Right,
+
+                    // \--
+                    Direction::Left => Direction::Up,
+                };
+                self.change_direction(new_direction);
+            }
+            None => panic!("Off the rails at {:?}", self.location),
+        }
+
+        self.location = self.current_direction.along(&self.location);
+    }
 }
 
 struct Track {
-    grid: Vec<Vec<Option<TrackType>>>,
-    carts: HashMap<Location, Vec<Rc<RefCell<Cart>>>>,
+    grid: Rc<Grid>,
+    // Ordered by location, top to bottom and left to right, at the start of
+    // every tick.
+    carts: Vec<Cart>,
+    ticks: usize,
+}
+
+// Cart `carts.0` moved onto cart `carts.1` during tick `tick`, the first tick
+// being tick 1.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Collision {
+    pub tick: usize,
+    pub location: Location,
+    pub carts: (usize, usize),
 }
 
 impl Track {
-    fn has_crash(&self) -> bool {
-        self.carts.iter().any(|(_, carts)| carts.len() > 1)
+    fn num_alive_carts(&self) -> usize {
+        self.carts.iter().filter(|cart| cart.is_alive).count()
     }
 
-    fn crash_location(&self) -> Option<Location> {
-        if !self.has_crash() {
-            None
-        } else {
-            let collisions = self
+    fn state(&self, tick: usize, collisions: Vec<Collision>) -> TrackState {
+        TrackState {
+            tick,
+            grid: Rc::clone(&self.grid),
+            carts: self
                 .carts
                 .iter()
-                .filter(|(_, carts)| carts.len() > 1)
-                .collect::<Vec<_>>();
-
-            assert!(
-                collisions.len() == 1,
-                "Expected one collision found {} in {:?}",
-                collisions.len(),
-                collisions
-            );
-            collisions
-                .into_iter()
-                .nth(0)
-                .map(|(location, _)| location.clone())
+                .filter(|cart| cart.is_alive)
+                .map(|cart| (cart.location, cart.current_direction.to_char()))
+                .collect(),
+            collisions,
         }
     }
 
-    fn num_alive_carts(&self) -> usize {
-        self.carts.values().fold(0, |outer_acc, carts| {
-            outer_acc + carts.iter().fold(
-                0,
-                |acc, cart| if cart.borrow().is_alive { acc + 1 } else { acc },
-            )
-        })
-    }
-
     fn alive_carts_locations(&self) -> Vec<Location> {
         self.carts
             .iter()
-            .flat_map(|(&location, carts)| {
-                let cloned_location = location.clone();
-
-                if carts.iter().any(|cart| cart.borrow().is_alive) {
-                    Some(cloned_location)
-                } else {
-                    None
-                }
-            }).collect()
+            .filter(|cart| cart.is_alive)
+            .map(|cart| cart.location)
+            .collect()
     }
 
-    fn tick(&mut self, halt_on_collision: bool) {
-        let mut order = self
+    // Moves every cart once, returns the collisions in the order they
+    // happened. Both carts in a collision are removed.
+    fn tick(&mut self) -> Vec<Collision> {
+        self.ticks += 1;
+        self.carts.retain(|cart| cart.is_alive);
+        self.carts
+            .sort_by_key(|cart| (cart.location.1, cart.location.0));
+
+        let mut occupied = self
             .carts
             .iter()
-            .filter(|(_, carts)| carts.iter().any(|c| c.borrow().is_alive))
-            .map(|(x, _)| x.clone())
-            .collect::<Vec<Location>>();
-        order.sort_by(|a, b| {
-            let order = a.0.cmp(&b.0);
-            if order != Ordering::Equal {
-                order
-            } else {
-                a.1.cmp(&b.1)
+            .enumerate()
+            .map(|(idx, cart)| (cart.location, idx))
+            .collect::<HashMap<_, _>>();
+        let mut collisions = vec![];
+
+        for idx in 0..self.carts.len() {
+            if !self.carts[idx].is_alive {
+                continue;
+            }
+
+            let from = self.carts[idx].location;
+            let (x, y) = from;
+            self.carts[idx].step(&self.grid[y][x]);
+            let to = self.carts[idx].location;
+            occupied.remove(&from);
+
+            match occupied.remove(&to) {
+                Some(other) => {
+                    self.carts[idx].is_alive = false;
+                    self.carts[other].is_alive = false;
+                    collisions.push(Collision {
+                        tick: self.ticks,
+                        location: to,
+                        carts: (self.carts[idx].id, self.carts[other].id),
+                    });
+                }
+                None => {
+                    occupied.insert(to, idx);
+                }
             }
-        });
+        }
 
-        let mut new_carts = self.carts.clone();
+        collisions
+    }
+}
 
-        'outer: for location in order {
-            let (x, y) = location;
-            let carts = self.carts.get(&location).unwrap().clone();
-            let track_type = &self.grid[y][x];
+impl fmt::Debug for Track {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.state(0, vec![]))
+    }
+}
 
-            for cart in carts.iter() {
-                if !cart.borrow().is_alive {
-                    continue;
-                }
+// The track and carts as they were after `tick` ticks, along with the
+// collisions that happened during that tick.
+pub struct TrackState {
+    tick: usize,
+    grid: Rc<Grid>,
+    carts: Vec<(Location, char)>,
+    collisions: Vec<Collision>,
+}
 
-                let (did_collide, new_location) = match track_type {
-                    Some(TrackType::Intersection) => {
-                        let new_direction = cart
-                            .borrow()
-                            .current_action
-                            .new_direction(&cart.borrow().current_direction);
-                        let new_location = new_direction.along(&location);
-                        let entry = new_carts.entry(new_location).or_insert(vec![]);
-                        cart.borrow_mut().advance();
-                        entry.push(Rc::clone(cart));
-                        let did_collide = entry.iter().filter(|c| c.borrow().is_alive).count() > 1;
-
-                        if did_collide {
-                            entry.iter().for_each(|c| c.borrow_mut().is_alive = false);
-                        }
-
-                        (did_collide, new_location)
-                    }
-                    Some(TrackType::Horizontal) | Some(TrackType::Vertical) => {
-                        assert!(
-                            ((track_type == &Some(TrackType::Horizontal)
-                                && (cart.borrow().current_direction == Direction::Left
-                                    || cart.borrow().current_direction == Direction::Right))
-                                || track_type == &Some(TrackType::Vertical)
-                                    && (cart.borrow().current_direction == Direction::Up
-                                        || cart.borrow().current_direction == Direction::Down))
-                        );
-
-                        let new_location = cart.borrow().current_direction.along(&location);
-                        let entry = new_carts.entry(new_location).or_insert(vec![]);
-                        entry.push(cart.clone());
-                        let did_collide = entry.iter().filter(|c| c.borrow().is_alive).count() > 1;
-
-                        if did_collide {
-                            entry.iter().for_each(|c| c.borrow_mut().is_alive = false);
-                        }
-
-                        (did_collide, new_location)
-                    }
-                    Some(TrackType::Curve1) => {
-                        // /
-                        let new_direction = match cart.borrow().current_direction {
-                            // /
-                            // |
-                            Direction::Up => Direction::Right,
-
-                            // -/
-                            Direction::Right => Direction::Up,
-
-                            // |
-                            // /
-                            Direction::Down => Direction::Left,
-
-                            // /--
-                            Direction::Left => Direction::Down,
-                        };
-                        let new_location = new_direction.along(&location);
-                        let entry = new_carts.entry(new_location).or_insert(vec![]);
-                        cart.borrow_mut().change_direction(new_direction);
-                        entry.push(Rc::clone(cart));
-                        let did_collide = entry.iter().filter(|c| c.borrow().is_alive).count() > 1;
-
-                        if did_collide {
-                            entry
-                                .iter_mut()
-                                .for_each(|c| c.borrow_mut().is_alive = false);
-                        }
-
-                        (did_collide, new_location)
-                    }
-                    Some(TrackType::Curve2) => {
-                        // \
-                        let new_direction = match cart.borrow().current_direction {
-                            // \
-                            // |
-                            Direction::Up => Direction::Left,
-
-                            // --\
-                            Direction::Right => Direction::Down,
-
-                            // |
-                            // \
-                            Direction::Down => Direction::Right,
-
-                            // \--
-                            Direction::Left => Direction::Up,
-                        };
-                        let new_location = new_direction.along(&location);
-                        let entry = new_carts.entry(new_location).or_insert(vec![]);
-                        cart.borrow_mut().change_direction(new_direction);
-                        entry.push(Rc::clone(cart));
-                        let did_collide = entry.iter().filter(|c| c.borrow().is_alive).count() > 1;
-
-                        if did_collide {
-                            entry
-                                .iter_mut()
-                                .for_each(|c| c.borrow_mut().is_alive = false);
-                        }
-
-                        (did_collide, new_location)
-                    }
+impl TrackState {
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
 
-                    None => {
-                        assert!(false, "Off the rails");
-                        (false, (0, 0))
-                    }
-                };
+    pub fn carts(&self) -> Vec<Location> {
+        self.carts.iter().map(|&(location, _)| location).collect()
+    }
 
-                {
-                    let entry = new_carts.entry(new_location).or_insert(vec![]);
-                    if entry.iter().filter(|c| c.borrow().is_alive).count() > 1 {
-                        for cart in entry {
-                            cart.borrow_mut().is_alive = false;
-                        }
-                    }
-                }
+    pub fn collisions(&self) -> &[Collision] {
+        &self.collisions
+    }
 
-                {
-                    let entry = new_carts.entry(location).or_insert(vec![]);
-                    entry.clear();
-                }
+    fn rows(&self) -> Vec<Vec<char>> {
+        let mut rows = self
+            .grid
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|t| t.as_ref().map(|x| x.to_char()).unwrap_or(' '))
+                    .collect::<Vec<_>>()
+            }).collect::<Vec<_>>();
 
-                if did_collide && halt_on_collision {
-                    break 'outer;
-                }
-            }
+        for &((x, y), c) in &self.carts {
+            rows[y][x] = c;
+        }
+
+        for &Collision {
+            location: (x, y), ..
+        } in &self.collisions
+        {
+            rows[y][x] = 'X';
         }
 
-        self.carts = new_carts;
+        rows
+    }
+
+    pub fn render_image(&self) -> Vec<u8> {
+        let rows = self.rows();
+        let height = rows.len();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let pixels = rows
+            .iter()
+            .flat_map(|row| {
+                (0..width).map(move |x| match row.get(x) {
+                    None | Some(' ') => (255, 255, 255),
+                    Some('X') => (240, 180, 0),
+                    Some('^') | Some('>') | Some('v') | Some('<') => (200, 30, 30),
+                    Some(_) => (160, 160, 160),
+                })
+            }).collect::<Vec<Color>>();
+
+        viz::ppm(width, height, &pixels)
     }
 }
 
-impl fmt::Debug for Track {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl fmt::Display for TrackState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "{}",
-            self.grid
-                .iter()
-                .enumerate()
-                .map(|(y, line)| line
-                    .iter()
-                    .enumerate()
-                    .map(|(x, t)| {
-                        let empty_vec = vec![];
-                        let carts = self
-                            .carts
-                            .get(&(x, y))
-                            .map(|carts| carts)
-                            .unwrap_or(&empty_vec);
-
-                        if carts.len() == 1 {
-                            carts[0].borrow().current_direction.to_char()
-                        } else if carts.len() > 1 {
-                            'X'
-                        } else {
-                            t.as_ref().map(|x| x.to_char()).unwrap_or(' ')
-                        }
-                    }).collect::<String>()).collect::<Vec<_>>()
+            self.rows()
+                .into_iter()
+                .map(|row| row.into_iter().collect::<String>())
+                .collect::<Vec<_>>()
                 .join("\n")
         )
     }
 }
 
-impl<'a> From<&'a str> for Track {
-    fn from(input: &'a str) -> Self {
-        let grid: Vec<Vec<(Option<TrackType>, Vec<Cart>)>> = input
+// The state of the track before the first tick and after every tick until
+// at most one cart remains.
+pub struct Ticks {
+    track: Track,
+    tick: usize,
+    finished: bool,
+}
+
+impl Iterator for Ticks {
+    type Item = TrackState;
+
+    fn next(&mut self) -> Option<TrackState> {
+        if self.finished {
+            return None;
+        }
+
+        let collisions = if self.tick > 0 {
+            self.track.tick()
+        } else {
+            vec![]
+        };
+        let state = self.track.state(self.tick, collisions);
+        self.finished = self.track.num_alive_carts() <= 1;
+        self.tick += 1;
+
+        Some(state)
+    }
+}
+
+pub fn ticks(input: &str) -> Ticks {
+    Ticks {
+        track: input.parse().unwrap_or_else(|e| panic!("{}", e)),
+        tick: 0,
+        finished: false,
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    UnknownCharacter {
+        location: Location,
+        character: char,
+    },
+    CartOffTrack {
+        location: Location,
+        cart: char,
+        side: &'static str,
+    },
+    Disconnected {
+        location: Location,
+        track: char,
+        side: &'static str,
+    },
+    InvalidCurve {
+        location: Location,
+        curve: char,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownCharacter {
+                location: (x, y),
+                character,
+            } => write!(f, "Unknown character `{}` at ({}, {})", character, x, y),
+            ParseError::CartOffTrack {
+                location: (x, y),
+                cart,
+                side,
+            } => write!(
+                f,
+                "Cart `{}` at ({}, {}) has no track {} it",
+                cart, x, y, side
+            ),
+            ParseError::Disconnected {
+                location: (x, y),
+                track,
+                side,
+            } => write!(
+                f,
+                "Track `{}` at ({}, {}) doesn't connect to any track {} it",
+                track, x, y, side
+            ),
+            ParseError::InvalidCurve {
+                location: (x, y),
+                curve,
+            } => write!(
+                f,
+                "Curve `{}` at ({}, {}) doesn't connect two pieces of track",
+                curve, x, y
+            ),
+        }
+    }
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+// The directions a piece of track could connect in. Depending on which way
+// they're turned curves could connect in any direction.
+fn connectable(c: char) -> u8 {
+    match c {
+        '-' | '<' | '>' => Direction::Left.mask() | Direction::Right.mask(),
+        '|' | '^' | 'v' => Direction::Up.mask() | Direction::Down.mask(),
+        '+' | '/' | '\\' => DIRECTIONS.iter().fold(0, |acc, dir| acc | dir.mask()),
+        _ => 0,
+    }
+}
+
+// Every piece of track has to connect to a neighbour in each of its
+// directions and every curve has to be turned in a way that connects.
+fn validate_connections(lines: &[Vec<char>]) -> Result<(), ParseError> {
+    let accepts = |location: &Location, dir: &Direction| {
+        dir.neighbour(location)
+            .and_then(|(x, y)| lines.get(y).and_then(|line| line.get(x)))
+            .map(|&c| connectable(c) & dir.opposite().mask() != 0)
+            .unwrap_or(false)
+    };
+
+    // Carts are checked first so that a cart on the wrong kind of track is
+    // reported as such rather than as the track next to it not connecting.
+    let cells = lines
+        .iter()
+        .enumerate()
+        .flat_map(|(y, line)| line.iter().enumerate().map(move |(x, &c)| ((x, y), c)));
+    let (carts, track): (Vec<_>, Vec<_>) = cells.partition(|&(_, c)| Direction::parse(c).is_some());
+
+    for (location, c) in carts.into_iter().chain(track) {
+        match c {
+            ' ' => continue,
+            '/' | '\\' => {
+                let turns: [[Direction; 2]; 2] = if c == '/' {
+                    [
+                        [Direction::Right, Direction::Down],
+                        [Direction::Left, Direction::Up],
+                    ]
+                } else {
+                    [
+                        [Direction::Left, Direction::Down],
+                        [Direction::Right, Direction::Up],
+                    ]
+                };
+
+                if !turns
+                    .iter()
+                    .any(|turn| turn.iter().all(|dir| accepts(&location, dir)))
+                {
+                    return Err(ParseError::InvalidCurve { location, curve: c });
+                }
+            }
+            _ => {
+                let missing = DIRECTIONS
+                    .iter()
+                    .filter(|dir| connectable(c) & dir.mask() != 0)
+                    .find(|dir| !accepts(&location, dir));
+
+                match (missing, Direction::parse(c)) {
+                    (None, _) => continue,
+                    (Some(dir), Some(_)) => {
+                        return Err(ParseError::CartOffTrack {
+                            location,
+                            cart: c,
+                            side: dir.side(),
+                        })
+                    }
+                    (Some(dir), None) => {
+                        return Err(ParseError::Disconnected {
+                            location,
+                            track: c,
+                            side: dir.side(),
+                        })
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl FromStr for Track {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, ParseError> {
+        let lines = input
             .lines()
             .map(|line| line.trim_end())
-            .filter(|line| line.len() > 0)
-            .map(|line| {
-                line.chars()
-                    .map(|c| {
-                        (
-                            TrackType::parse(c),
-                            Direction::parse(c)
-                                .map(|dir| vec![Cart::new(dir)])
-                                .unwrap_or(vec![]),
-                        )
-                    }).collect()
-            }).collect();
-
-        let carts = grid
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        for (y, line) in lines.iter().enumerate() {
+            if let Some(x) = line.iter().position(|&c| c != ' ' && connectable(c) == 0) {
+                return Err(ParseError::UnknownCharacter {
+                    location: (x, y),
+                    character: line[x],
+                });
+            }
+        }
+        validate_connections(&lines)?;
+
+        let carts = lines
             .iter()
             .enumerate()
-            .flat_map(|(y, row)| {
-                row.iter()
+            .flat_map(|(y, line)| {
+                line.iter()
                     .enumerate()
-                    .map(|(x, (_, carts))| {
-                        (
-                            (x, y),
-                            carts
-                                .clone()
-                                .into_iter()
-                                .map(|cart| Rc::new(RefCell::new(cart)))
-                                .collect(),
-                        )
-                    }).collect::<Vec<(Location, Vec<Rc<RefCell<Cart>>>)>>()
-            }).collect();
-
-        Self {
-            grid: grid
-                .into_iter()
-                .map(|row| row.into_iter().map(|(t, _)| t).collect())
-                .collect(),
+                    .filter_map(move |(x, &c)| Direction::parse(c).map(|dir| ((x, y), dir)))
+            }).enumerate()
+            .map(|(id, (location, dir))| Cart::new(id, location, dir))
+            .collect();
+
+        Ok(Self {
+            grid: Rc::new(
+                lines
+                    .iter()
+                    .map(|line| line.iter().cloned().map(TrackType::parse).collect())
+                    .collect(),
+            ),
             carts,
-        }
+            ticks: 0,
+        })
     }
 }
 
+pub fn validate(input: &str) -> Result<(), ParseError> {
+    input.parse::<Track>().map(|_| ())
+}
+
+// Every collision until at most one cart remains
+pub fn collisions(input: &str) -> Vec<Collision> {
+    ticks(input).flat_map(|state| state.collisions).collect()
+}
+
 pub fn star_one(input: &str) -> Location {
-    let mut track = Track::from(input);
+    let mut track: Track = input.parse().unwrap_or_else(|e| panic!("{}", e));
 
-    while !track.has_crash() {
-        track.tick(true);
+    loop {
+        if let Some(collision) = track.tick().first() {
+            return collision.location;
+        }
     }
-
-    track.crash_location().unwrap()
 }
 
 pub fn star_two(input: &str) -> Location {
-    let mut track = Track::from(input);
-    let mut ticks: Vec<String> = vec![];
-    println!("Num alive at start: {}", track.num_alive_carts());
-
-    loop {
-        track.tick(false);
-        let num_alive = track.num_alive_carts();
+    let mut track: Track = input.parse().unwrap_or_else(|e| panic!("{}", e));
 
-        if num_alive == 1 {
-            break;
-        }
-        ticks.push(format!("{:?}", track));
-        assert!(
-            num_alive % 2 == 1,
-            "There should alwasy be an odd number of live carts, but it was {}. Last ticks: \n{}",
-            num_alive,
-            ticks
-                .iter()
-                .skip(ticks.len() - 3)
-                .map(|s| s.to_owned())
-                .collect::<Vec<String>>()
-                .join("\n")
-        );
+    while track.num_alive_carts() > 1 {
+        track.tick();
     }
 
-    track.alive_carts_locations()[0]
+    let locations = track.alive_carts_locations();
+    assert!(
+        locations.len() == 1,
+        "Expected exactly one cart to remain, but there were {}:\n{:?}",
+        locations.len(),
+        track
+    );
+
+    locations[0]
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{collisions, star_one, star_two, ticks, validate, Collision, ParseError, Track};
+    use snapshot::assert_snapshot;
+    use std::str::FromStr;
+
     static EXAMPLE_ONE: &str = "
 /->-\\
 |   |  /----\\
@@ -517,6 +726,127 @@ mod tests {
         assert_eq!(star_one(EXAMPLE_ONE), (7, 3));
     }
 
+    #[test]
+    fn test_tick() {
+        let mut track = Track::from_str("/->-<-\\\n\\-----/").unwrap();
+        assert_eq!(
+            track.tick(),
+            vec![Collision {
+                tick: 1,
+                location: (3, 0),
+                carts: (1, 0)
+            }]
+        );
+        assert_eq!(track.num_alive_carts(), 0);
+
+        // Carts move one at a time so the one in front hasn't moved yet
+        let mut track = Track::from_str("/>>--\\\n|    |\n\\---</").unwrap();
+        assert_eq!(track.tick()[0].location, (2, 0));
+        assert_eq!(track.alive_carts_locations(), vec![(3, 2)]);
+        assert_eq!(format!("{:?}", track), "/----\\\n|    |\n\\--<-/");
+    }
+
+    #[test]
+    fn test_tick_snapshots() {
+        let mut track = Track::from_str(EXAMPLE_ONE).unwrap();
+        let mut frames = vec![format!("{:?}", track)];
+        while track.tick().is_empty() {
+            frames.push(format!("{:?}", track));
+        }
+
+        assert_eq!(frames.len(), 14);
+        assert_snapshot("day13_example_one_ticks", &frames.join("\n\n"));
+    }
+
+    #[test]
+    fn test_ticks() {
+        let states = ticks(EXAMPLE_TWO).collect::<Vec<_>>();
+
+        assert_eq!(states.len(), 4);
+        assert_eq!(states[0].tick(), 0);
+        assert_eq!(states[0].carts().len(), 9);
+        assert_eq!(
+            states[1]
+                .collisions()
+                .iter()
+                .map(|collision| collision.location)
+                .collect::<Vec<_>>(),
+            vec![(2, 0), (2, 4), (6, 4)]
+        );
+        assert_eq!(states[3].carts(), vec![(6, 4)]);
+        assert_eq!(
+            states[1].to_string(),
+            "/-X-\\\n|   |\n| <-+-\\\n| | | |\n\\-X-/ X\n  |   |\n  <--->"
+        );
+
+        let image = states[1].render_image();
+        assert_eq!(&image[..11], b"P6\n7 7\n255\n");
+        assert_eq!(&image[11 + 3 * 2..11 + 3 * 3], &[240, 180, 0]);
+    }
+
+    #[test]
+    fn test_collisions() {
+        let collision = |tick, location, carts| Collision {
+            tick,
+            location,
+            carts,
+        };
+
+        assert_eq!(
+            collisions(EXAMPLE_TWO),
+            vec![
+                collision(1, (2, 0), (1, 0)),
+                collision(1, (2, 4), (5, 4)),
+                collision(1, (6, 4), (6, 3)),
+                collision(3, (2, 4), (7, 2)),
+            ]
+        );
+        assert_eq!(collisions(EXAMPLE_ONE), vec![collision(14, (7, 3), (0, 1))]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(EXAMPLE_ONE), Ok(()));
+        assert_eq!(validate(EXAMPLE_TWO), Ok(()));
+        // Adjacent curves connecting to each other
+        assert_eq!(validate("/\\\n\\/"), Ok(()));
+
+        assert_eq!(
+            validate("/->-\\\n|   *\n\\---/"),
+            Err(ParseError::UnknownCharacter {
+                location: (4, 1),
+                character: '*'
+            })
+        );
+        assert_eq!(
+            validate("/->-\\\n|   |\n\\-- /"),
+            Err(ParseError::Disconnected {
+                location: (2, 2),
+                track: '-',
+                side: "to the right"
+            })
+        );
+        assert_eq!(
+            validate("/-v-\\\n|   |\n\\---/"),
+            Err(ParseError::CartOffTrack {
+                location: (2, 0),
+                cart: 'v',
+                side: "above"
+            })
+        );
+        assert_eq!(
+            validate("/-\\\n|  \n\\-/"),
+            Err(ParseError::InvalidCurve {
+                location: (2, 0),
+                curve: '\\'
+            })
+        );
+        assert_eq!(
+            validate("/-v-\\\n|   |\n\\---/").unwrap_err().to_string(),
+            "Cart `v` at (2, 0) has no track above it"
+        );
+    }
+
     #[test]
     fn test_star_two() {
         assert_eq!(star_two(EXAMPLE_TWO), (6, 4));
@@ -1,3 +1,8 @@
+use memchr::memmem;
+
+// Recipes are made in blocks this large between searches of the scoreboard
+const BLOCK_SIZE: usize = 1 << 16;
+
 #[derive(Debug)]
 struct Elf {
     current_recipe: usize,
@@ -8,70 +13,222 @@ impl Elf {
         Self { current_recipe }
     }
 
-    fn pick_new_recipe(&mut self, scoreboard: &[usize]) {
+    fn pick_new_recipe(&mut self, scoreboard: &[u8]) {
         self.current_recipe =
-            (self.current_recipe + scoreboard[self.current_recipe] + 1) % scoreboard.len();
+            (self.current_recipe + scoreboard[self.current_recipe] as usize + 1) % scoreboard.len();
     }
 }
 
-fn make_new_recipes(elves: &[Elf], scoreboard: &[usize]) -> Vec<usize> {
-    let sum: usize = elves.iter().map(|e| scoreboard[e.current_recipe]).sum();
+// Every digit of the sum of the elves' current scores is a new recipe
+fn make_new_recipes(elves: &[Elf], scoreboard: &mut Vec<u8>) {
+    let sum: usize = elves
+        .iter()
+        .map(|e| scoreboard[e.current_recipe] as usize)
+        .sum();
+    let start = scoreboard.len();
+    let mut remaining = sum;
+
+    loop {
+        scoreboard.push((remaining % 10) as u8);
+        remaining /= 10;
 
-    sum.to_string()
-        .chars()
-        .map(|c| c.to_digit(10).unwrap() as usize)
-        .collect()
+        if remaining == 0 {
+            break;
+        }
+    }
+
+    scoreboard[start..].reverse();
 }
 
-pub fn star_one(recipes_to_make: usize) -> String {
-    let mut scoreboard: Vec<usize> = vec![3, 7];
-    let mut elves = vec![Elf::new(0), Elf::new(1)];
+// Knuth-Morris-Pratt matcher that's fed one digit at a time, so finding the
+// pattern never requires looking back at the scoreboard.
+struct Matcher<'a> {
+    pattern: &'a [u8],
+    // Length of the longest proper prefix of `pattern[..=i]` that is also a
+    // suffix of it
+    failure: Vec<usize>,
+    matched: usize,
+}
 
-    while scoreboard.len() < recipes_to_make + 10 {
-        let new_recipes = make_new_recipes(&elves, &scoreboard);
-        scoreboard.extend(new_recipes);
+impl<'a> Matcher<'a> {
+    fn new(pattern: &'a [u8]) -> Self {
+        let mut failure = vec![0; pattern.len()];
+        let mut len = 0;
 
-        for elf in &mut elves {
-            elf.pick_new_recipe(&scoreboard);
+        for idx in 1..pattern.len() {
+            while len > 0 && pattern[idx] != pattern[len] {
+                len = failure[len - 1];
+            }
+            if pattern[idx] == pattern[len] {
+                len += 1;
+            }
+            failure[idx] = len;
+        }
+
+        Self {
+            pattern,
+            failure,
+            matched: 0,
         }
     }
 
-    let correction = scoreboard.len() - recipes_to_make - 10;
-    scoreboard[scoreboard.len() - 10 - correction..scoreboard.len() - correction]
-        .iter()
-        .map(|d| d.to_string())
-        .collect()
+    // Whether the digits pushed so far end with the pattern
+    fn push(&mut self, digit: u8) -> bool {
+        if self.matched == self.pattern.len() {
+            self.matched = self.failure[self.matched - 1];
+        }
+        while self.matched > 0 && self.pattern[self.matched] != digit {
+            self.matched = self.failure[self.matched - 1];
+        }
+        if self.pattern[self.matched] == digit {
+            self.matched += 1;
+        }
+
+        self.matched == self.pattern.len()
+    }
 }
 
-pub fn star_two(input: &[usize]) -> usize {
-    let mut scoreboard: Vec<usize> = vec![3, 7];
-    let mut elves = vec![Elf::new(0), Elf::new(1)];
+// Yields the scores on the scoreboard in order, making new recipes as needed
+struct Recipes {
+    scoreboard: Vec<u8>,
+    elves: Vec<Elf>,
+    next: usize,
+}
+
+impl Recipes {
+    // The elves start at the first `elves` recipes of the scoreboard
+    fn new(initial: &[u8], elves: usize, capacity: usize) -> Self {
+        assert!(
+            elves > 0 && elves <= initial.len(),
+            "Expected between 1 and {} elves, got {}",
+            initial.len(),
+            elves
+        );
+        assert!(
+            initial.iter().all(|&score| score < 10),
+            "Scores are single digits, got {:?}",
+            initial
+        );
+        let mut scoreboard = Vec::with_capacity(capacity.max(initial.len()));
+        scoreboard.extend(initial);
+
+        Self {
+            scoreboard,
+            elves: (0..elves).map(Elf::new).collect(),
+            next: 0,
+        }
+    }
+}
 
-    'outer: loop {
-        let new_recipes = make_new_recipes(&elves, &scoreboard);
-        for new_recipe in new_recipes {
-            scoreboard.push(new_recipe);
+impl Recipes {
+    // Makes at least `count` more recipes
+    fn make_recipes(&mut self, count: usize) {
+        let target = self.scoreboard.len() + count;
 
-            if scoreboard.len() >= input.len() {
-                let sequence = &scoreboard[scoreboard.len() - input.len()..scoreboard.len()];
+        while self.scoreboard.len() < target {
+            make_new_recipes(&self.elves, &mut self.scoreboard);
 
-                if sequence == input {
-                    break 'outer;
-                }
+            for elf in &mut self.elves {
+                elf.pick_new_recipe(&self.scoreboard);
             }
         }
+    }
+}
 
-        for elf in &mut elves {
-            elf.pick_new_recipe(&scoreboard);
+impl Iterator for Recipes {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.next == self.scoreboard.len() {
+            self.make_recipes(1);
         }
+
+        self.next += 1;
+
+        Some(self.scoreboard[self.next - 1])
     }
+}
+
+pub fn recipes() -> impl Iterator<Item = u8> {
+    recipes_with(&[3, 7], 2)
+}
+
+pub fn recipes_with(initial: &[u8], elves: usize) -> impl Iterator<Item = u8> {
+    Recipes::new(initial, elves, initial.len())
+}
 
-    scoreboard.len() - input.len()
+// The ten scores following the first `recipes_to_make` recipes
+pub fn scores_after(initial: &[u8], elves: usize, recipes_to_make: usize) -> String {
+    Recipes::new(initial, elves, recipes_to_make + 11)
+        .skip(recipes_to_make)
+        .take(10)
+        .map(|d| d.to_string())
+        .collect()
+}
+
+// How many recipes there are before `sequence` first appears
+// A sequence of n digits is expected to show up within about 10^n recipes
+fn expected_recipes(sequence: &[u8]) -> usize {
+    10usize
+        .saturating_pow(sequence.len() as u32 + 1)
+        .min(1 << 26)
+}
+
+// Recipes are made a block at a time and only the part of the scoreboard that
+// could contain a new match is searched after each block.
+pub fn recipes_before(initial: &[u8], elves: usize, sequence: &[u8]) -> usize {
+    assert!(!sequence.is_empty(), "Expected a non empty sequence");
+
+    let finder = memmem::Finder::new(sequence);
+    let mut recipes = Recipes::new(initial, elves, expected_recipes(sequence));
+    let mut searched = 0;
+
+    loop {
+        if let Some(found) = finder.find(&recipes.scoreboard[searched..]) {
+            return searched + found;
+        }
+
+        // A match could start in the last `sequence.len() - 1` recipes
+        searched = (recipes.scoreboard.len() + 1).saturating_sub(sequence.len());
+        recipes.make_recipes(BLOCK_SIZE);
+    }
+}
+
+// Like `recipes_before` but checks for the sequence after every recipe
+pub fn recipes_before_streaming(initial: &[u8], elves: usize, sequence: &[u8]) -> usize {
+    assert!(!sequence.is_empty(), "Expected a non empty sequence");
+
+    let mut matcher = Matcher::new(sequence);
+
+    Recipes::new(initial, elves, expected_recipes(sequence))
+        .position(|recipe| matcher.push(recipe))
+        .map(|end| end + 1 - sequence.len())
+        .unwrap()
+}
+
+pub fn star_one(recipes_to_make: usize) -> String {
+    scores_after(&[3, 7], 2, recipes_to_make)
+}
+
+pub fn star_two(input: &str) -> usize {
+    let sequence = input
+        .trim()
+        .chars()
+        .map(|c| {
+            c.to_digit(10)
+                .unwrap_or_else(|| panic!("Invalid digit `{}` in `{}`", c, input.trim()))
+                as u8
+        }).collect::<Vec<_>>();
+
+    recipes_before(&[3, 7], 2, &sequence)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        recipes, recipes_before, recipes_before_streaming, recipes_with, scores_after, star_one,
+        star_two, Matcher, BLOCK_SIZE,
+    };
 
     #[test]
     fn test_star_one() {
@@ -83,10 +240,86 @@ mod tests {
 
     #[test]
     fn test_star_two() {
-        assert_eq!(star_two(&[5, 1, 5, 8, 9]), 9);
-        assert_eq!(star_two(&[0, 1, 2, 4, 5]), 5);
-        assert_eq!(star_two(&[9, 2, 5, 1, 0]), 18);
-        assert_eq!(star_two(&[5, 9, 4, 1, 4]), 2018);
-        assert_eq!(star_two(&[1, 2, 4, 5, 1, 5]), 6);
+        assert_eq!(star_two("51589"), 9);
+        assert_eq!(star_two("01245"), 5);
+        assert_eq!(star_two("92510"), 18);
+        assert_eq!(star_two("59414\n"), 2018);
+        assert_eq!(star_two("124515"), 6);
+        assert_eq!(star_two("37"), 0);
+        assert_eq!(star_two("71"), 1);
+    }
+
+    #[test]
+    fn test_other_kitchens() {
+        // Three elves starting on 3, 7 and 1 make 11, after which they all
+        // end up on the same recipe.
+        assert_eq!(
+            recipes_with(&[3, 7, 1], 3).take(8).collect::<Vec<_>>(),
+            vec![3, 7, 1, 1, 1, 3, 9, 3]
+        );
+        assert_eq!(scores_after(&[3, 7, 1], 3, 3), "1139327273");
+        assert_eq!(recipes_before(&[3, 7, 1], 3, &[2, 7, 2, 1]), 13);
+        assert_eq!(recipes_before_streaming(&[3, 7, 1], 3, &[2, 7, 2, 1]), 13);
+
+        // The default kitchen
+        assert_eq!(scores_after(&[3, 7], 2, 9), star_one(9));
+        assert_eq!(recipes_before(&[3, 7], 2, &[5, 1, 5, 8, 9]), 9);
+
+        // Nine elves on nines make recipes with three digit sums
+        assert_eq!(
+            recipes_with(&[9; 12], 12)
+                .skip(12)
+                .take(3)
+                .collect::<Vec<_>>(),
+            vec![1, 0, 8]
+        );
+    }
+
+    #[test]
+    fn test_recipes() {
+        assert_eq!(
+            recipes().take(20).collect::<Vec<_>>(),
+            vec![3, 7, 1, 0, 1, 0, 1, 2, 4, 5, 1, 5, 8, 9, 1, 6, 7, 7, 9, 2]
+        );
+    }
+
+    #[test]
+    fn test_recipes_before() {
+        // Sequences that start in one block and end in the next
+        let scores = recipes().take(BLOCK_SIZE * 3).collect::<Vec<_>>();
+
+        for &start in &[BLOCK_SIZE - 3, BLOCK_SIZE * 2 - 1, BLOCK_SIZE + 100] {
+            let sequence = &scores[start..start + 40];
+            let expected = recipes_before_streaming(&[3, 7], 2, sequence);
+
+            assert!(expected <= start);
+            assert_eq!(recipes_before(&[3, 7], 2, sequence), expected);
+        }
+
+        // Found on the initial scoreboard
+        assert_eq!(recipes_before(&[3, 7], 2, &[7]), 1);
+        assert_eq!(recipes_before_streaming(&[3, 7], 2, &[7]), 1);
+    }
+
+    #[test]
+    fn test_matcher() {
+        let pattern = [1, 1, 2, 1, 1, 2, 1, 1, 3];
+        let mut matcher = Matcher::new(&pattern);
+        let input = [1, 1, 2, 1, 1, 2, 1, 1, 2, 1, 1, 3, 1, 1, 3];
+        let matches = input
+            .iter()
+            .enumerate()
+            .filter(|&(_, &digit)| matcher.push(digit))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+
+        assert_eq!(matches, vec![11]);
+
+        let mut matcher = Matcher::new(&[1, 1]);
+        let matches = [1, 1, 1, 0, 1, 1]
+            .iter()
+            .map(|&digit| matcher.push(digit))
+            .collect::<Vec<_>>();
+        assert_eq!(matches, vec![false, true, true, false, false, true]);
     }
 }
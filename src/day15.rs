@@ -1,14 +1,19 @@
-use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::iter;
-use std::rc::Rc;
+use std::str::FromStr;
+
+use rayon::prelude::*;
+
+use parallel;
+use search::first_success;
 
 // x, y pair
 type Location = (usize, usize);
-type UnitPointer = Rc<RefCell<Unit>>;
+// Index of a unit in `GameState::units`
+type UnitId = usize;
 
 fn reading_order(lhs: &Location, rhs: &Location) -> Ordering {
     let order = lhs.1.cmp(&rhs.1);
@@ -20,7 +25,7 @@ fn reading_order(lhs: &Location, rhs: &Location) -> Ordering {
 }
 
 #[derive(Eq, PartialEq, Clone)]
-enum UnitType {
+pub enum UnitType {
     Elf,
     Goblin,
 }
@@ -37,15 +42,17 @@ impl fmt::Debug for UnitType {
 #[derive(Debug, Clone)]
 struct Unit {
     unit_type: UnitType,
+    location: Location,
     health: usize,
     strength: usize,
     is_dead: bool,
 }
 
 impl Unit {
-    fn new(unit_type: UnitType) -> Self {
+    fn new(unit_type: UnitType, location: Location) -> Self {
         Self {
             unit_type,
+            location,
             health: 200,
             strength: 3,
             is_dead: false,
@@ -88,92 +95,216 @@ impl Unit {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum Position {
     Wall,
     Open,
-    Occupied(UnitPointer),
+    Occupied(UnitId),
 }
 
 impl Position {
     fn parse(input: char) -> Option<Self> {
         match input {
             '#' => Some(Position::Wall),
-            '.' => Some(Position::Open),
-            'G' => Some(Position::Occupied(Rc::new(RefCell::new(Unit::new(
-                UnitType::Goblin,
-            ))))),
-            'E' => Some(Position::Occupied(Rc::new(RefCell::new(Unit::new(
-                UnitType::Elf,
-            ))))),
+            '.' | 'G' | 'E' => Some(Position::Open),
             _ => None,
         }
     }
+}
 
-    fn to_char(&self) -> char {
-        match self {
-            Position::Wall => '#',
-            Position::Open => '.',
-            Position::Occupied(occupant) => occupant.borrow().to_char(),
+// Units are kept in an arena for the whole battle, the dead ones included,
+// and the grid refers to the living ones by their index in it.
+#[derive(Clone)]
+struct GameState {
+    grid: Vec<Vec<Position>>,
+    units: Vec<Unit>,
+    full_rounds: usize,
+}
+
+impl<'a> From<&'a str> for GameState {
+    fn from(input: &'a str) -> Self {
+        let mut units = vec![];
+
+        let grid = input
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(x, c)| {
+                        let pos = Position::parse(c)
+                            .unwrap_or_else(|| panic!("Unexpected position {}", c));
+                        let unit_type = match c {
+                            'G' => Some(UnitType::Goblin),
+                            'E' => Some(UnitType::Elf),
+                            _ => None,
+                        };
+
+                        match unit_type {
+                            Some(unit_type) => {
+                                units.push(Unit::new(unit_type, (x, y)));
+
+                                Position::Occupied(units.len() - 1)
+                            }
+                            None => pos,
+                        }
+                    }).collect()
+            }).collect();
+
+        Self {
+            grid,
+            units,
+            full_rounds: 0,
         }
     }
 }
 
-impl Clone for Position {
-    fn clone(&self) -> Self {
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    MissingRound,
+    InvalidUnit { line: usize, text: String },
+    UnknownCharacter { location: Location, character: char },
+    UnitMismatch { location: Location },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Position::Wall => Position::Wall,
-            Position::Open => Position::Open,
-            Position::Occupied(occupant) => {
-                Position::Occupied(Rc::new(RefCell::new(occupant.borrow().clone())))
+            ParseError::MissingRound => write!(f, "Expected a snapshot to start with `round <n>`"),
+            ParseError::InvalidUnit { line, text } => {
+                write!(f, "Invalid unit `{}` on line {}", text, line)
             }
+            ParseError::UnknownCharacter {
+                location: (x, y),
+                character,
+            } => write!(f, "Unknown character `{}` at ({}, {})", character, x, y),
+            ParseError::UnitMismatch { location: (x, y) } => write!(
+                f,
+                "The map and the list of units disagree about ({}, {})",
+                x, y
+            ),
         }
     }
 }
 
-impl fmt::Debug for Position {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Position::Wall => write!(f, "#"),
-            Position::Open => write!(f, "."),
-            Position::Occupied(occupant) => write!(f, "{:?}", occupant.borrow()),
+fn parse_unit(line: usize, text: &str) -> Result<Unit, ParseError> {
+    let invalid = || ParseError::InvalidUnit {
+        line,
+        text: text.to_owned(),
+    };
+    let parts = text.split_whitespace().collect::<Vec<_>>();
+
+    match parts.as_slice() {
+        [kind, location, health, strength] => {
+            let unit_type = match *kind {
+                "G" => UnitType::Goblin,
+                "E" => UnitType::Elf,
+                _ => return Err(invalid()),
+            };
+            let mut coordinates = location.split(',').map(|c| c.parse::<usize>());
+            let location = match (coordinates.next(), coordinates.next(), coordinates.next()) {
+                (Some(Ok(x)), Some(Ok(y)), None) => (x, y),
+                _ => return Err(invalid()),
+            };
+            let health = health.parse::<usize>().map_err(|_| invalid())?;
+            let strength = strength.parse::<usize>().map_err(|_| invalid())?;
+
+            Ok(Unit {
+                unit_type,
+                location,
+                health,
+                strength,
+                is_dead: health == 0,
+            })
         }
+        _ => Err(invalid()),
     }
 }
 
-struct GameState {
-    grid: Vec<Vec<Position>>,
-    combatants: HashMap<Location, UnitPointer>,
-}
+// A snapshot is the number of full rounds, the map and then every unit, dead
+// or alive, with its location, hit points and strength:
+//
+// round 2
+// #####
+// #GE.#
+// #####
+// G 1,1 194 3
+// E 2,1 200 3
+impl FromStr for GameState {
+    type Err = ParseError;
+
+    fn from_str(snapshot: &str) -> Result<Self, ParseError> {
+        let mut lines = snapshot
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
 
-impl<'a> From<&'a str> for GameState {
-    fn from(input: &'a str) -> Self {
-        let mut combatants = HashMap::new();
+        let full_rounds = lines
+            .next()
+            .and_then(|(_, line)| {
+                let mut parts = line.split_whitespace();
 
-        Self {
-            grid: input
-                .lines()
-                .map(|line| line.trim())
-                .filter(|line| line.len() > 0)
-                .enumerate()
-                .map(|(y, line)| {
-                    line.chars()
-                        .enumerate()
-                        .map(|(x, c)| {
-                            let pos =
-                                Position::parse(c).expect(&format!("Unexpected position {}", c));
-
-                            match &pos {
-                                Position::Occupied(occupant) => {
-                                    combatants.insert((x, y), Rc::clone(&occupant));
-                                }
-                                _ => {}
-                            };
-
-                            pos
-                        }).collect()
-                }).collect(),
-            combatants: combatants,
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some("round"), Some(rounds), None) => rounds.parse().ok(),
+                    _ => None,
+                }
+            }).ok_or(ParseError::MissingRound)?;
+
+        let (map, units): (Vec<_>, Vec<_>) = lines.partition(|(_, line)| line.starts_with('#'));
+        let units = units
+            .into_iter()
+            .map(|(line, text)| parse_unit(line, text))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut grid = vec![];
+        let mut unclaimed = HashMap::<Location, char>::new();
+        for (y, (_, line)) in map.into_iter().enumerate() {
+            let mut row = vec![];
+
+            for (x, c) in line.chars().enumerate() {
+                match Position::parse(c) {
+                    Some(position) => row.push(position),
+                    None => {
+                        return Err(ParseError::UnknownCharacter {
+                            location: (x, y),
+                            character: c,
+                        })
+                    }
+                }
+
+                if c == 'G' || c == 'E' {
+                    unclaimed.insert((x, y), c);
+                }
+            }
+
+            grid.push(row);
+        }
+
+        for (id, unit) in units.iter().enumerate().filter(|(_, unit)| unit.is_alive()) {
+            let (x, y) = unit.location;
+
+            if unclaimed.remove(&unit.location) != Some(unit.to_char()) {
+                return Err(ParseError::UnitMismatch {
+                    location: unit.location,
+                });
+            }
+            grid[y][x] = Position::Occupied(id);
         }
+
+        if let Some(location) = unclaimed.keys().min_by(|lhs, rhs| reading_order(lhs, rhs)) {
+            return Err(ParseError::UnitMismatch {
+                location: *location,
+            });
+        }
+
+        Ok(Self {
+            grid,
+            units,
+            full_rounds,
+        })
     }
 }
 
@@ -210,41 +341,41 @@ impl GameState {
             })
     }
 
-    fn prioritized_enemy(
-        &self,
-        unit: &Unit,
-        unit_location: &Location,
-    ) -> Option<(Location, UnitPointer)> {
-        let mut enemies_in_range: Vec<(Location, UnitPointer)> = self
+    fn unit_at(&self, location: &Location) -> Option<UnitId> {
+        match self.grid[location.1][location.0] {
+            Position::Occupied(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    // The ids of every living unit
+    fn combatants<'a>(&'a self) -> impl Iterator<Item = UnitId> + 'a {
+        self.units
+            .iter()
+            .enumerate()
+            .filter(|(_, unit)| unit.is_alive())
+            .map(|(id, _)| id)
+    }
+
+    fn prioritized_enemy(&self, unit: &Unit, unit_location: &Location) -> Option<UnitId> {
+        let mut enemies_in_range: Vec<UnitId> = self
             .in_range(unit_location, false)
-            .flat_map(|(x, y)| match self.combatants.get(&(x, y)) {
-                Some(occupant) => {
-                    if occupant.borrow().unit_type != unit.unit_type {
-                        Some(((x, y), Rc::clone(&occupant)))
-                    } else {
-                        None
-                    }
-                }
-                _ => None,
-            }).collect();
+            .flat_map(|location| self.unit_at(&location))
+            .filter(|&id| self.units[id].unit_type != unit.unit_type)
+            .collect();
 
-        if enemies_in_range.is_empty() {
-            None
-        } else if enemies_in_range.len() == 1 {
-            enemies_in_range.into_iter().nth(0)
-        } else {
-            enemies_in_range.sort_by(|(lhs_location, lhs), (rhs_location, rhs)| {
-                let ordering = lhs.borrow().health.cmp(&rhs.borrow().health);
+        enemies_in_range.sort_by(|&lhs, &rhs| {
+            let (lhs, rhs) = (&self.units[lhs], &self.units[rhs]);
+            let ordering = lhs.health.cmp(&rhs.health);
 
-                if ordering != Ordering::Equal {
-                    ordering
-                } else {
-                    reading_order(lhs_location, rhs_location)
-                }
-            });
+            if ordering != Ordering::Equal {
+                ordering
+            } else {
+                reading_order(&lhs.location, &rhs.location)
+            }
+        });
 
-            enemies_in_range.into_iter().nth(0)
-        }
+        enemies_in_range.into_iter().nth(0)
     }
 
     fn enemies_alive(&self, unit: &Unit) -> bool {
@@ -255,53 +386,56 @@ impl GameState {
     }
 
     fn num_combatants_alive(&self, combatant_type: UnitType) -> usize {
-        self.combatants.values().fold(0, |acc, unit| {
-            if unit.borrow().unit_type == combatant_type && unit.borrow().is_alive() {
-                acc + 1
-            } else {
-                acc
-            }
-        })
+        self.combatants()
+            .filter(|&id| self.units[id].unit_type == combatant_type)
+            .count()
     }
 
-    fn possible_targets(&self, unit: &Unit) -> Vec<(Location, UnitPointer)> {
-        self.combatants
-            .iter()
-            .filter(|(_, other_unit)| unit.unit_type != other_unit.borrow().unit_type)
-            .map(|(location, other)| (location.clone(), Rc::clone(other)))
+    fn possible_targets(&self, unit: &Unit) -> Vec<Location> {
+        self.combatants()
+            .map(|id| &self.units[id])
+            .filter(|other_unit| unit.unit_type != other_unit.unit_type)
+            .map(|other| other.location)
             .collect()
     }
 
     fn cheat(&self, new_elf_strength: usize) -> Self {
-        let mut combatants = HashMap::new();
-        let grid = self
-            .grid
-            .clone()
-            .into_iter()
-            .enumerate()
-            .map(|(y, row)| {
-                row.clone()
-                    .into_iter()
-                    .enumerate()
-                    .map(|(x, pos)| {
-                        let new_pos = pos.clone();
-
-                        match pos {
-                            Position::Occupied(occupant) => {
-                                if occupant.borrow().unit_type == UnitType::Elf {
-                                    occupant.borrow_mut().strength = new_elf_strength;
-                                }
+        let mut state = self.clone();
 
-                                combatants.insert((x, y), Rc::clone(&occupant));
-                            }
-                            _ => {}
-                        };
+        for unit in state.units.iter_mut() {
+            if unit.unit_type == UnitType::Elf {
+                unit.strength = new_elf_strength;
+            }
+        }
 
-                        new_pos
-                    }).collect()
-            }).collect();
+        state
+    }
 
-        Self { grid, combatants }
+    // Deals damage from `attacker` to `defender`, removing the defender from
+    // the grid if it dies.
+    fn attack(&mut self, attacker: UnitId, defender: UnitId, record: &mut dyn FnMut(Event)) {
+        let round = self.full_rounds + 1;
+        let strength = self.units[attacker].strength;
+        let died = self.units[defender].take_damage(strength);
+
+        record(Event::Attack {
+            round,
+            unit: attacker,
+            target: defender,
+            damage: strength,
+            remaining_health: self.units[defender].health,
+        });
+
+        if died {
+            let (x, y) = self.units[defender].location;
+            self.grid[y][x] = Position::Open;
+
+            record(Event::Death {
+                round,
+                unit: defender,
+                location: (x, y),
+            });
+        }
     }
 
     fn calculate_distance_grid(&self, from: &Location) -> Option<Vec<Vec<Option<usize>>>> {
@@ -375,34 +509,42 @@ impl GameState {
     }
 
     fn turn(&mut self) -> (bool, Option<UnitType>) {
-        let mut unit_locations: Vec<(Location, UnitPointer)> = self
-            .combatants
-            .iter()
-            .map(|(l, combatant)| (l.clone(), Rc::clone(combatant)))
-            .collect();
-        unit_locations.sort_by(|(a, _), (b, _)| reading_order(a, b));
+        self.turn_recording(&mut |_| {})
+    }
+
+    fn turn_recording(&mut self, record: &mut dyn FnMut(Event)) -> (bool, Option<UnitType>) {
+        let round = self.full_rounds + 1;
+        let mut unit_order: Vec<UnitId> = self.combatants().collect();
+        unit_order
+            .sort_by(|&a, &b| reading_order(&self.units[a].location, &self.units[b].location));
+
+        record(Event::Order {
+            round,
+            units: unit_order.clone(),
+        });
+
+        for id in unit_order.into_iter() {
+            if !self.enemies_alive(&self.units[id]) {
+                let winner = self.units[id].unit_type.clone();
+                record(Event::End {
+                    round,
+                    winner: winner.clone(),
+                });
 
-        for (unit_location, unit) in unit_locations.into_iter() {
-            if !self.enemies_alive(&unit.borrow()) {
-                return (false, Some(unit.borrow().unit_type.clone()));
+                return (false, Some(winner));
             }
 
-            if unit.borrow().is_dead() {
+            if self.units[id].is_dead() {
                 continue;
             }
 
-            let enemy = self.prioritized_enemy(&unit.borrow(), &unit_location);
+            let unit_location = self.units[id].location;
+            let enemy = self.prioritized_enemy(&self.units[id], &unit_location);
 
-            if enemy.is_some() {
-                let (enemy_location, e) = enemy.unwrap();
-                let died = e.borrow_mut().take_damage(unit.borrow().strength);
-
-                if died {
-                    self.combatants.remove(&enemy_location);
-                    self.grid[enemy_location.1][enemy_location.0] = Position::Open;
-                }
+            if let Some(enemy) = enemy {
+                self.attack(id, enemy, record);
             } else {
-                let possible_targets = self.possible_targets(&unit.borrow());
+                let possible_targets = self.possible_targets(&self.units[id]);
 
                 if possible_targets.is_empty() {
                     continue;
@@ -417,7 +559,7 @@ impl GameState {
 
                 let mut possible_targets_with_distance = possible_targets
                     .iter()
-                    .flat_map(|(enemy_location, _)| self.in_range(enemy_location, true))
+                    .flat_map(|enemy_location| self.in_range(enemy_location, true))
                     .flat_map(|target_location| {
                         match distance_grid[target_location.1][target_location.0] {
                             None => None,
@@ -444,35 +586,24 @@ impl GameState {
 
                 possible_first_moves.sort_by(|(lhs, _), (rhs, _)| reading_order(lhs, rhs));
 
-                possible_first_moves
-                    .into_iter()
-                    .nth(0)
-                    .iter()
-                    .for_each(|(_, new_location)| {
-                        // Delete old location
-                        self.combatants.remove(&unit_location);
-                        self.grid[unit_location.1][unit_location.0] = Position::Open;
-
-                        // Add new location
-                        self.grid[new_location.1][new_location.0] =
-                            Position::Occupied(Rc::clone(&unit));
-
-                        self.combatants
-                            .insert(new_location.clone(), Rc::clone(&unit));
-
-                        let new_enemy = self.prioritized_enemy(&unit.borrow(), &new_location);
-
-                        if new_enemy.is_some() {
-                            let (new_enemy_location, ne) = new_enemy.unwrap();
-                            let died = ne.borrow_mut().take_damage(unit.borrow().strength);
-
-                            if died {
-                                self.combatants.remove(&new_enemy_location);
-                                self.grid[new_enemy_location.1][new_enemy_location.0] =
-                                    Position::Open;
-                            }
-                        }
+                if let Some(&(&target, new_location)) = possible_first_moves.first() {
+                    self.grid[unit_location.1][unit_location.0] = Position::Open;
+                    self.grid[new_location.1][new_location.0] = Position::Occupied(id);
+                    self.units[id].location = new_location;
+
+                    record(Event::Move {
+                        round,
+                        unit: id,
+                        from: unit_location,
+                        target,
+                        to: new_location,
                     });
+
+                    if let Some(new_enemy) = self.prioritized_enemy(&self.units[id], &new_location)
+                    {
+                        self.attack(id, new_enemy, record);
+                    }
+                }
             }
         }
 
@@ -481,26 +612,81 @@ impl GameState {
             self.num_combatants_alive(UnitType::Elf),
         );
 
+        self.full_rounds += 1;
+
         if goblins_left == 0 || elves_left == 0 {
-            if goblins_left == 0 {
-                (true, Some(UnitType::Elf))
+            let winner = if goblins_left == 0 {
+                UnitType::Elf
             } else {
-                (true, Some(UnitType::Goblin))
-            }
+                UnitType::Goblin
+            };
+            record(Event::End {
+                round,
+                winner: winner.clone(),
+            });
+
+            (true, Some(winner))
         } else {
             (true, None)
         }
     }
 
+    // The living units in reading order
+    fn survivors(&self) -> Vec<UnitStatus> {
+        let mut units = self
+            .combatants()
+            .map(|id| &self.units[id])
+            .map(|unit| UnitStatus {
+                unit_type: unit.unit_type.clone(),
+                location: unit.location,
+                health: unit.health,
+            }).collect::<Vec<_>>();
+        units.sort_by(|lhs, rhs| reading_order(&lhs.location, &rhs.location));
+
+        units
+    }
+
+    fn num_dead(&self, unit_type: UnitType) -> usize {
+        self.units
+            .iter()
+            .filter(|unit| unit.unit_type == unit_type && unit.is_dead())
+            .count()
+    }
+
+    fn snapshot(&self) -> String {
+        let units = self.units.iter().map(|unit| {
+            format!(
+                "{} {},{} {} {}",
+                unit.to_char(),
+                unit.location.0,
+                unit.location.1,
+                unit.health,
+                unit.strength
+            )
+        });
+
+        iter::once(format!("round {}", self.full_rounds))
+            .chain(iter::once(format!("{:?}", self)))
+            .chain(units)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn round(&self, is_final: bool) -> Round {
+        Round {
+            completed: self.full_rounds,
+            is_final,
+            map: format!("{:?}", self),
+            units: self.survivors(),
+        }
+    }
+
     fn remaining_health_for_faction(&self, faction: UnitType) -> usize {
-        self.combatants.values().fold(0, |acc, unit| {
-            let borrowed_unit = unit.borrow();
-            if borrowed_unit.unit_type == faction && borrowed_unit.is_alive() {
-                acc + borrowed_unit.health
-            } else {
-                acc
-            }
-        })
+        self.combatants()
+            .map(|id| &self.units[id])
+            .filter(|unit| unit.unit_type == faction)
+            .map(|unit| unit.health)
+            .sum()
     }
 }
 
@@ -511,72 +697,386 @@ impl fmt::Debug for GameState {
             "{}",
             self.grid
                 .iter()
-                .map(|row| row.iter().map(|pos| pos.to_char()).collect::<String>())
-                .collect::<Vec<String>>()
+                .map(|row| {
+                    row.iter()
+                        .map(|pos| match pos {
+                            Position::Wall => '#',
+                            Position::Open => '.',
+                            Position::Occupied(id) => self.units[*id].to_char(),
+                        }).collect::<String>()
+                }).collect::<Vec<String>>()
                 .join("\n")
         )
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnitStatus {
+    pub unit_type: UnitType,
+    pub location: Location,
+    pub health: usize,
+}
+
+// The battlefield after `completed` full rounds. The final round of a battle
+// usually ends part way through, in which case it's reported with the number
+// of rounds that were completed before it, just like the puzzle does.
+pub struct Round {
+    completed: usize,
+    is_final: bool,
+    map: String,
+    units: Vec<UnitStatus>,
+}
+
+impl Round {
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.is_final
+    }
+
+    pub fn map(&self) -> &str {
+        &self.map
+    }
+
+    // The surviving units in reading order
+    pub fn units(&self) -> &[UnitStatus] {
+        &self.units
+    }
+}
+
+// Formatted like the traces in the puzzle description, with the hit points
+// of the units on each row listed to the right of it.
+impl fmt::Display for Round {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.completed {
+            0 if !self.is_final => writeln!(f, "Initially:")?,
+            1 => writeln!(f, "After 1 round:")?,
+            n => writeln!(f, "After {} rounds:", n)?,
+        }
+
+        let rows = self
+            .map
+            .lines()
+            .enumerate()
+            .map(|(y, row)| {
+                let hit_points = self
+                    .units
+                    .iter()
+                    .filter(|unit| unit.location.1 == y)
+                    .map(|unit| format!("{:?}({})", unit.unit_type, unit.health))
+                    .collect::<Vec<_>>();
+
+                if hit_points.is_empty() {
+                    row.to_owned()
+                } else {
+                    format!("{}   {}", row, hit_points.join(", "))
+                }
+            }).collect::<Vec<_>>();
+
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+// The battlefield before the first round and after every round until the
+// battle is over.
+pub struct Rounds {
+    state: GameState,
+    started: bool,
+    finished: bool,
+}
+
+impl Rounds {
+    // A snapshot of the battle as of the last round, which `resume` can pick
+    // up from.
+    pub fn snapshot(&self) -> String {
+        self.state.snapshot()
+    }
+
+    // Fights the rest of the battle
+    pub fn finish(self) -> BattleOutcome {
+        fight(self.state, false).unwrap()
+    }
+}
+
+impl Iterator for Rounds {
+    type Item = Round;
+
+    fn next(&mut self) -> Option<Round> {
+        if self.finished {
+            return None;
+        }
+
+        if !self.started {
+            self.started = true;
+            return Some(self.state.round(false));
+        }
+
+        let (_, winning_faction) = self.state.turn();
+        self.finished = winning_faction.is_some();
+
+        Some(self.state.round(self.finished))
+    }
+}
+
+pub fn replay(input: &str) -> Rounds {
+    Rounds {
+        state: GameState::from(input),
+        started: false,
+        finished: false,
+    }
+}
+
+// Continues a battle from a snapshot taken with `Rounds::snapshot`, starting
+// with the state in the snapshot itself.
+pub fn resume(snapshot: &str) -> Result<Rounds, ParseError> {
+    Ok(Rounds {
+        state: snapshot.parse()?,
+        started: false,
+        finished: false,
+    })
+}
+
+// The "After N rounds" traces for the given rounds, to compare against the
+// ones in the puzzle description. When the battle ends part way through a
+// round the final state is the one shown for the rounds completed before it.
+pub fn trace(input: &str, rounds: &[usize]) -> String {
+    let mut traced: Vec<Round> = vec![];
+
+    for round in replay(input).filter(|round| rounds.contains(&round.completed)) {
+        match traced.last_mut() {
+            Some(last) if last.completed == round.completed => *last = round,
+            _ => traced.push(round),
+        }
+    }
+
+    traced
+        .iter()
+        .map(|round| round.to_string())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// Every decision made during a battle. Units are identified by their
+// position in reading order at the start of the battle and rounds are
+// numbered from 1.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Event {
+    // The order in which the units take their turns
+    Order {
+        round: usize,
+        units: Vec<usize>,
+    },
+    // A step from `from` to `to` on the way to the square `target` next to
+    // an enemy
+    Move {
+        round: usize,
+        unit: usize,
+        from: Location,
+        target: Location,
+        to: Location,
+    },
+    Attack {
+        round: usize,
+        unit: usize,
+        target: usize,
+        damage: usize,
+        remaining_health: usize,
+    },
+    Death {
+        round: usize,
+        unit: usize,
+        location: Location,
+    },
+    End {
+        round: usize,
+        winner: UnitType,
+    },
+}
+
+// One event per line as `key=value` pairs, to make it easy to diff or
+// process with other tools.
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Order { round, units } => write!(
+                f,
+                "round={} event=order units={}",
+                round,
+                units
+                    .iter()
+                    .map(|unit| unit.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Event::Move {
+                round,
+                unit,
+                from,
+                target,
+                to,
+            } => write!(
+                f,
+                "round={} event=move unit={} from={},{} target={},{} to={},{}",
+                round, unit, from.0, from.1, target.0, target.1, to.0, to.1
+            ),
+            Event::Attack {
+                round,
+                unit,
+                target,
+                damage,
+                remaining_health,
+            } => write!(
+                f,
+                "round={} event=attack unit={} target={} damage={} hp={}",
+                round, unit, target, damage, remaining_health
+            ),
+            Event::Death {
+                round,
+                unit,
+                location,
+            } => write!(
+                f,
+                "round={} event=death unit={} at={},{}",
+                round, unit, location.0, location.1
+            ),
+            Event::End { round, winner } => {
+                write!(f, "round={} event=end winner={:?}", round, winner)
+            }
+        }
+    }
+}
+
+pub fn events(input: &str, elf_strength: usize) -> Vec<Event> {
+    let mut state = GameState::from(input).cheat(elf_strength);
+    let mut events = vec![];
+
+    loop {
+        let (_, winning_faction) = state.turn_recording(&mut |event| events.push(event));
+
+        if winning_faction.is_some() {
+            break;
+        }
+    }
+
+    events
+}
+
+pub fn event_trace(input: &str, elf_strength: usize) -> String {
+    events(input, elf_strength)
+        .iter()
+        .map(|event| event.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BattleOutcome {
+    pub full_rounds: usize,
+    pub winner: UnitType,
+    pub remaining_health: usize,
+    pub survivors: Vec<UnitStatus>,
+    pub elf_deaths: usize,
+}
+
+impl BattleOutcome {
+    // The puzzle's outcome, full rounds times the remaining hit points
+    pub fn outcome(&self) -> usize {
+        self.full_rounds * self.remaining_health
+    }
+}
+
+// Runs the battle to the end, or until the first elf dies if
+// `stop_on_elf_death` is set in which case there's no outcome.
+fn fight(mut state: GameState, stop_on_elf_death: bool) -> Option<BattleOutcome> {
+    loop {
+        let (_, winning_faction) = state.turn();
+
+        if stop_on_elf_death && state.num_dead(UnitType::Elf) > 0 {
+            return None;
+        }
+
+        if let Some(winner) = winning_faction {
+            return Some(BattleOutcome {
+                full_rounds: state.full_rounds,
+                remaining_health: state.remaining_health_for_faction(winner.clone()),
+                winner,
+                survivors: state.survivors(),
+                elf_deaths: state.num_dead(UnitType::Elf),
+            });
+        }
+    }
+}
+
+pub fn simulate(input: &str, elf_strength: usize) -> BattleOutcome {
+    fight(GameState::from(input).cheat(elf_strength), false).unwrap()
+}
+
 pub fn star_one(input: &str) -> usize {
-    let mut state = GameState::from(input);
-    let (completed_turns, winning_faction) = iter::repeat(0)
-        .enumerate()
-        .map(|(id, _)| {
-            let (_, turn_result) = state.turn();
+    simulate(input, 3).outcome()
+}
 
-            (id, turn_result)
-        }).skip_while(|(_, turn_result)| turn_result.is_none())
-        .nth(0)
-        .map(|(turns, end_result)| (turns, end_result))
-        .unwrap();
+// The outcome of the battle with the elves at `strength`, provided that the
+// elves win without a single loss.
+fn flawless_victory(initial_state: &GameState, strength: usize) -> Option<usize> {
+    fight(initial_state.cheat(strength), true).map(|outcome| outcome.outcome())
+}
 
-    completed_turns * state.remaining_health_for_faction(winning_faction.unwrap())
+// Goblins start with 200 hit points, so any two strengths that need the same
+// number of hits to kill a goblin play out exactly the same battle.
+fn hits_to_kill(strength: usize) -> usize {
+    200_usize.div_ceil(strength)
 }
 
 pub fn star_two(input: &str) -> usize {
     let initial_state = GameState::from(input);
-    let number_of_elves_in_combat = initial_state.num_combatants_alive(UnitType::Elf);
+    let mut outcomes = HashMap::<usize, Option<usize>>::new();
+    let mut battle = |strength: usize| {
+        *outcomes
+            .entry(hits_to_kill(strength))
+            .or_insert_with(|| flawless_victory(&initial_state, strength))
+    };
+
+    // A win isn't guaranteed to stay a win at higher strengths, so the weaker
+    // strengths just below are checked too. Within a `hits_to_kill` class
+    // that's free and it usually stops at the first class below.
+    let mut strength = first_success(4, &mut |strength| battle(strength).is_some());
+    while strength > 4 && battle(strength - 1).is_some() {
+        strength -= 1;
+    }
 
-    let (completed_turns, adjusted_strength, winning_faction, final_state) = iter::repeat(0)
-        .enumerate()
-        .map(|(strength_increase, _)| {
-            let adjusted_strength = 4 + strength_increase;
-            let mut state = initial_state.cheat(adjusted_strength);
+    battle(strength).unwrap()
+}
 
-            let (completed_turns, winning_faction) = iter::repeat(0)
-                .enumerate()
-                .map(|(id, _)| {
-                    let (full_turn, turn_result) = state.turn();
+// Simulates the candidate strengths concurrently, each on its own copy of
+// the initial state, without relying on the outcome being monotone. Only the
+// smallest strength of every `hits_to_kill` class needs simulating and from
+// 200 onwards every strength kills a goblin in a single hit.
+pub fn star_two_parallel(input: &str) -> usize {
+    let initial_state = GameState::from(input);
 
-                    if state.num_combatants_alive(UnitType::Elf) < number_of_elves_in_combat {
-                        (id, Some(UnitType::Goblin))
-                    } else {
-                        let turn_count = if full_turn { id + 1 } else { id };
-                        (turn_count, turn_result)
-                    }
-                }).skip_while(|(_, turn_result)| turn_result.is_none())
-                .nth(0)
-                .unwrap();
-
-            (
-                completed_turns,
-                adjusted_strength,
-                winning_faction.unwrap(),
-                Some(state),
-            )
-        }).skip_while(|(_, _, turn_result, _)| turn_result == &UnitType::Goblin)
-        .nth(0)
-        .unwrap();
+    parallel::install(|| {
+        (4..=200usize)
+            .into_par_iter()
+            .filter(|&strength| hits_to_kill(strength) != hits_to_kill(strength - 1))
+            .find_map_first(|strength| flawless_victory(&initial_state, strength))
+    }).expect("Expected the elves to win without losses at some strength")
+}
+
+pub fn star_two_linear(input: &str) -> usize {
+    let initial_state = GameState::from(input);
 
-    completed_turns * final_state
+    (4..)
+        .flat_map(|strength| flawless_victory(&initial_state, strength))
+        .next()
         .unwrap()
-        .remaining_health_for_faction(winning_faction)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use snapshot::assert_snapshot;
 
     static EXAMPLE_ONE: &str = "
 #######
@@ -688,6 +1188,237 @@ mod tests {
         assert_eq!(star_two(EXAMPLE_TEN), 1140);
     }
 
+    #[test]
+    fn test_star_two_linear() {
+        for example in &[
+            EXAMPLE_SIX,
+            EXAMPLE_SEVEN,
+            EXAMPLE_EIGHT,
+            EXAMPLE_NINE,
+            EXAMPLE_TEN,
+        ] {
+            assert_eq!(star_two_linear(example), star_two(example));
+            assert_eq!(star_two_parallel(example), star_two(example));
+        }
+    }
+
+    #[test]
+    fn test_hits_to_kill() {
+        assert_eq!(hits_to_kill(3), 67);
+        assert_eq!(hits_to_kill(4), 50);
+        assert_eq!(hits_to_kill(199), 2);
+        assert_eq!(hits_to_kill(200), 1);
+    }
+
+    #[test]
+    fn test_simulate() {
+        let outcome = simulate(EXAMPLE_SIX, 3);
+
+        assert_eq!(outcome.full_rounds, 47);
+        assert_eq!(outcome.winner, UnitType::Goblin);
+        assert_eq!(outcome.remaining_health, 590);
+        assert_eq!(outcome.elf_deaths, 2);
+        assert_eq!(outcome.outcome(), 27730);
+        assert_eq!(
+            outcome
+                .survivors
+                .iter()
+                .map(|unit| (unit.location, unit.health))
+                .collect::<Vec<_>>(),
+            vec![((1, 1), 200), ((2, 2), 131), ((5, 3), 59), ((5, 5), 200)]
+        );
+
+        let outcome = simulate(EXAMPLE_SIX, 15);
+        assert_eq!(outcome.full_rounds, 29);
+        assert_eq!(outcome.winner, UnitType::Elf);
+        assert_eq!(outcome.remaining_health, 172);
+        assert_eq!(outcome.elf_deaths, 0);
+    }
+
+    #[test]
+    fn test_rounds_snapshot() {
+        // The rounds the puzzle description shows for this example
+        let mut state = GameState::from(EXAMPLE_SIX);
+        let mut rounds = vec![format!("Initially:\n{:?}", state)];
+        for round in 1..=47 {
+            state.turn();
+
+            if [1, 2, 23, 24, 25, 26, 27, 28, 47].contains(&round) {
+                rounds.push(format!("After {} rounds:\n{:?}", round, state));
+            }
+        }
+
+        assert_snapshot("day15_example_six_rounds", &rounds.join("\n\n"));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut rounds = replay("#####\n#GE.#\n#####");
+        rounds.nth(2);
+
+        assert_eq!(
+            rounds.snapshot(),
+            "round 2\n#####\n#GE.#\n#####\nG 1,1 194 3\nE 2,1 194 3"
+        );
+
+        let state = GameState::from_str(&rounds.snapshot()).unwrap();
+        assert_eq!(state.snapshot(), rounds.snapshot());
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut rounds = replay(EXAMPLE_SIX);
+        let after_23 = rounds.nth(23).unwrap();
+        let snapshot = rounds.snapshot();
+
+        let mut resumed = resume(&snapshot).unwrap();
+        assert_eq!(resumed.next().unwrap().to_string(), after_23.to_string());
+        assert_eq!(resumed.finish(), simulate(EXAMPLE_SIX, 3));
+
+        // Dead units and strengths survive the round trip
+        let mut rounds = replay(EXAMPLE_SIX);
+        rounds.nth(40);
+        let snapshot = rounds.snapshot();
+        assert!(snapshot.contains("E 4,2 0 3"));
+        assert_eq!(resume(&snapshot).unwrap().finish(), rounds.finish());
+    }
+
+    #[test]
+    fn test_resume_errors() {
+        assert_eq!(resume("#####").err(), Some(ParseError::MissingRound));
+        assert_eq!(
+            resume("round 1\n#G.#\nG 1,1 two 3").err(),
+            Some(ParseError::InvalidUnit {
+                line: 3,
+                text: String::from("G 1,1 two 3"),
+            })
+        );
+        assert_eq!(
+            resume("round 1\n#G?#\nG 1,0 200 3").err(),
+            Some(ParseError::UnknownCharacter {
+                location: (2, 0),
+                character: '?',
+            })
+        );
+        assert_eq!(
+            resume("round 1\n#G.#\nE 1,0 200 3").err(),
+            Some(ParseError::UnitMismatch { location: (1, 0) })
+        );
+        assert_eq!(
+            resume("round 1\n#GG#\nG 1,0 200 3").err(),
+            Some(ParseError::UnitMismatch { location: (2, 0) })
+        );
+        assert_eq!(
+            resume("round 1\n#G.#\nG 1,0 200 3\nE 2,0 0 3")
+                .unwrap()
+                .snapshot(),
+            "round 1\n#G.#\nG 1,0 200 3\nE 2,0 0 3"
+        );
+    }
+
+    #[test]
+    fn test_events() {
+        let input = "
+#######
+#E..G.#
+#...#.#
+#.G.#G#
+#######";
+        let trace = event_trace(input, 3);
+        let mut lines = trace.lines();
+
+        assert_eq!(lines.next(), Some("round=1 event=order units=0,1,2,3"));
+        assert_eq!(
+            lines.next(),
+            Some("round=1 event=move unit=0 from=1,1 target=3,1 to=2,1")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("round=1 event=move unit=1 from=4,1 target=3,1 to=3,1")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("round=1 event=attack unit=1 target=0 damage=3 hp=197")
+        );
+
+        let events = events(input, 3);
+        let deaths = events
+            .iter()
+            .filter(|event| matches!(event, Event::Death { .. }))
+            .count();
+        assert_eq!(deaths, 1);
+        assert_eq!(
+            events.last(),
+            Some(&Event::End {
+                round: simulate(input, 3).full_rounds + 1,
+                winner: UnitType::Goblin,
+            })
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let expected = "Initially:
+#######
+#.G...#   G(200)
+#...EG#   E(200), G(200)
+#.#.#G#   G(200)
+#..G#E#   G(200), E(200)
+#.....#
+#######
+
+After 1 round:
+#######
+#..G..#   G(200)
+#...EG#   E(197), G(197)
+#.#G#G#   G(200), G(197)
+#...#E#   E(197)
+#.....#
+#######
+
+After 2 rounds:
+#######
+#...G.#   G(200)
+#..GEG#   G(200), E(188), G(194)
+#.#.#G#   G(194)
+#...#E#   E(194)
+#.....#
+#######
+
+After 47 rounds:
+#######
+#G....#   G(200)
+#.G...#   G(131)
+#.#.#G#   G(59)
+#...#.#
+#....G#   G(200)
+#######";
+
+        assert_eq!(trace(EXAMPLE_SIX, &[0, 1, 2, 47]), expected);
+    }
+
+    #[test]
+    fn test_replay() {
+        let rounds = replay(EXAMPLE_SIX).collect::<Vec<_>>();
+        let last = rounds.last().unwrap();
+
+        assert_eq!(rounds.len(), 48);
+        assert!(last.is_final());
+        assert_eq!(last.completed(), 47);
+        assert_eq!(
+            last.units()[0],
+            UnitStatus {
+                unit_type: UnitType::Goblin,
+                location: (1, 1),
+                health: 200,
+            }
+        );
+        assert_eq!(
+            rounds[1].map(),
+            "#######\n#..G..#\n#...EG#\n#.#G#G#\n#...#E#\n#.....#\n#######"
+        );
+    }
+
     #[test]
     fn first_move_on_shortest_path() {
         let input = "
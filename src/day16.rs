@@ -1,216 +1,17 @@
 use std::collections::{HashMap, HashSet};
-use std::ops::{Index, IndexMut};
+use std::fmt;
 
-#[derive(Copy, Clone, Debug)]
-struct RegisterIndex(usize);
-
-type RegisterType = i64;
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-enum Opcode {
-    Addr,
-    Addi,
-
-    Mulr,
-    Muli,
-
-    Banr,
-    Bani,
-
-    Borr,
-    Bori,
-
-    Setr,
-    Seti,
-
-    Gtir,
-    Gtri,
-    Gtrr,
-
-    Eqir,
-    Eqri,
-    Eqrr,
-}
-
-#[derive(Debug)]
-enum Value {
-    Immediate(RegisterType),
-    FromRegister(RegisterIndex),
-}
-
-#[derive(Debug)]
-struct Instruction {
-    opcode: Opcode,
-    first_operand: Value,
-    second_operand: Option<Value>,
-    destination: RegisterIndex,
-}
-
-lazy_static! {
-    static ref OPCODE_MAPPINGS: HashMap<Opcode, (bool, bool)> = vec![
-        (Opcode::Addr, true, true),
-        (Opcode::Addi, true, false),
-        (Opcode::Mulr, true, true),
-        (Opcode::Muli, true, false),
-        (Opcode::Banr, true, true),
-        (Opcode::Bani, true, false),
-        (Opcode::Borr, true, true),
-        (Opcode::Bori, true, false),
-        (Opcode::Setr, true, false),
-        (Opcode::Seti, false, false),
-        (Opcode::Gtir, false, true),
-        (Opcode::Gtri, true, false),
-        (Opcode::Gtrr, true, true),
-        (Opcode::Eqir, false, true),
-        (Opcode::Eqri, true, false),
-        (Opcode::Eqrr, true, true),
-    ].into_iter()
-    .map(|(opcode, first_is_ref, second_is_ref)| (opcode, (first_is_ref, second_is_ref)))
-    .collect();
-}
-
-impl Instruction {
-    fn new(
-        opcode: Opcode,
-        first_operand: usize,
-        second_operand: usize,
-        destination: usize,
-    ) -> Self {
-        let (first_is_ref, second_is_ref) = OPCODE_MAPPINGS.get(&opcode).unwrap();
-
-        Self {
-            opcode,
-            first_operand: if *first_is_ref {
-                Value::FromRegister(RegisterIndex(first_operand))
-            } else {
-                Value::Immediate(first_operand as i64)
-            },
-            second_operand: if *second_is_ref {
-                Some(Value::FromRegister(RegisterIndex(second_operand)))
-            } else {
-                Some(Value::Immediate(second_operand as i64))
-            },
-            destination: RegisterIndex(destination),
-        }
-    }
-
-    fn from_potential_instruction(instruction: &[usize]) -> Vec<Self> {
-        vec![
-            (Opcode::Addr, true, true),
-            (Opcode::Addi, true, false),
-            (Opcode::Mulr, true, true),
-            (Opcode::Muli, true, false),
-            (Opcode::Banr, true, true),
-            (Opcode::Bani, true, false),
-            (Opcode::Borr, true, true),
-            (Opcode::Bori, true, false),
-            (Opcode::Setr, true, false),
-            (Opcode::Seti, false, false),
-            (Opcode::Gtir, false, true),
-            (Opcode::Gtri, true, false),
-            (Opcode::Gtrr, true, true),
-            (Opcode::Eqir, false, true),
-            (Opcode::Eqri, true, false),
-            (Opcode::Eqrr, true, true),
-        ].into_iter()
-        .map(|(opcode, first_is_ref, second_is_ref)| Self {
-            opcode,
-            first_operand: if first_is_ref {
-                Value::FromRegister(RegisterIndex(instruction[1]))
-            } else {
-                Value::Immediate(instruction[1] as i64)
-            },
-            second_operand: if second_is_ref {
-                Some(Value::FromRegister(RegisterIndex(instruction[2])))
-            } else {
-                Some(Value::Immediate(instruction[2] as i64))
-            },
-            destination: RegisterIndex(instruction[3]),
-        }).collect()
-    }
-}
-
-struct Machine {
-    registers: [RegisterType; 4],
-}
-
-impl Machine {
-    fn new() -> Self {
-        Self { registers: [0; 4] }
-    }
-
-    fn set_register_state(&mut self, values: &[RegisterType]) {
-        assert!(
-            values.len() == 4,
-            "Cannot set registers unless length matches"
-        );
-
-        self.registers[0] = values[0];
-        self.registers[1] = values[1];
-        self.registers[2] = values[2];
-        self.registers[3] = values[3];
-    }
-
-    fn execute(&mut self, instruction: &Instruction) {
-        let a = self.get_value(&instruction.first_operand);
-        let b = instruction
-            .second_operand
-            .as_ref()
-            .map(|value| self.get_value(&value));
-        let c = instruction.destination;
-
-        match instruction.opcode {
-            Opcode::Addr | Opcode::Addi => self[c] = a + b.unwrap(),
-
-            Opcode::Mulr | Opcode::Muli => self[c] = a * b.unwrap(),
-
-            Opcode::Banr | Opcode::Bani => self[c] = a & b.unwrap(),
-
-            Opcode::Borr | Opcode::Bori => self[c] = a | b.unwrap(),
-
-            Opcode::Setr | Opcode::Seti => self[c] = a,
-
-            Opcode::Gtir | Opcode::Gtri | Opcode::Gtrr => {
-                self[c] = if a > b.unwrap() { 1 } else { 0 }
-            }
-
-            Opcode::Eqir | Opcode::Eqri | Opcode::Eqrr => {
-                self[c] = if a == b.unwrap() { 1 } else { 0 }
-            }
-        }
-    }
-
-    fn get_value(&self, value: &Value) -> RegisterType {
-        match value {
-            Value::Immediate(v) => v.clone(),
-            Value::FromRegister(idx) => self[*idx],
-        }
-    }
-}
-
-impl Index<RegisterIndex> for Machine {
-    type Output = RegisterType;
-
-    fn index(&self, register_index: RegisterIndex) -> &RegisterType {
-        &self.registers[register_index.0]
-    }
-}
-
-impl IndexMut<RegisterIndex> for Machine {
-    fn index_mut(&mut self, register_index: RegisterIndex) -> &mut RegisterType {
-        &mut self.registers[register_index.0]
-    }
-}
+use elfcode::{Instruction, Machine, Opcode, Register, OPCODES};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct Observation {
-    before: Vec<RegisterType>,
+    before: Vec<Register>,
     instruction: Vec<usize>,
-    after: Vec<RegisterType>,
+    after: Vec<Register>,
 }
 
 impl Observation {
-    fn new(before: Vec<RegisterType>, instruction: Vec<usize>, after: Vec<RegisterType>) -> Self {
+    fn new(before: Vec<Register>, instruction: Vec<usize>, after: Vec<Register>) -> Self {
         Self {
             before,
             instruction,
@@ -219,6 +20,30 @@ impl Observation {
     }
 }
 
+impl Observation {
+    // The opcodes that could have produced the observed change
+    fn matching_opcodes<'a>(&'a self) -> impl Iterator<Item = Opcode> + 'a {
+        OPCODES.iter().cloned().filter(move |&opcode| {
+            let instruction = Instruction::new(
+                opcode,
+                self.instruction[1],
+                self.instruction[2],
+                self.instruction[3],
+            );
+
+            if !instruction.fits(self.before.len()) {
+                return false;
+            }
+
+            let mut machine = Machine::new(self.before.len());
+            machine.set_register_state(&self.before);
+            machine.execute(&instruction);
+
+            machine.registers() == &self.after[..]
+        })
+    }
+}
+
 impl<'a> From<&'a [&'a str]> for Observation {
     fn from(input: &'a [&'a str]) -> Self {
         let iterator = input
@@ -227,7 +52,7 @@ impl<'a> From<&'a [&'a str]> for Observation {
             .filter(|l| l.len() > 0)
             .enumerate();
 
-        let parsed_expectations: Vec<Vec<RegisterType>> = iterator
+        let parsed_expectations: Vec<Vec<Register>> = iterator
             .clone()
             .filter(|(id, _)| id == &0 || id == &2)
             .map(|(_, line)| {
@@ -239,7 +64,7 @@ impl<'a> From<&'a [&'a str]> for Observation {
                     .split(" ")
                     .filter(|s| s.len() > 0)
                     .map(|s| {
-                        s.trim().parse::<RegisterType>().expect(&format!(
+                        s.trim().parse::<Register>().expect(&format!(
                             "Expected valid input when parsing before/after 🤷‍♂️ in `{}`",
                             s
                         ))
@@ -258,12 +83,8 @@ impl<'a> From<&'a [&'a str]> for Observation {
 
         assert!(parsed_expectations.len() == 2, "Invalid input {:?}", input);
         assert!(
-            parsed_expectations[0].len() == 4,
-            "Invalid input {:?}",
-            input
-        );
-        assert!(
-            parsed_expectations[1].len() == 4,
+            !parsed_expectations[0].is_empty()
+                && parsed_expectations[0].len() == parsed_expectations[1].len(),
             "Invalid input {:?}",
             input
         );
@@ -277,102 +98,196 @@ impl<'a> From<&'a [&'a str]> for Observation {
     }
 }
 
-pub fn star_one(input: &str) -> i64 {
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DeductionError {
+    Unsatisfiable {
+        number: usize,
+    },
+    Ambiguous {
+        number: usize,
+        opcodes: (Opcode, Opcode),
+    },
+}
+
+impl fmt::Display for DeductionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeductionError::Unsatisfiable { number } => write!(
+                f,
+                "No opcode is left for opcode number {} that agrees with all observations",
+                number
+            ),
+            DeductionError::Ambiguous {
+                number,
+                opcodes: (first, second),
+            } => write!(
+                f,
+                "Opcode number {} could be either {:?} or {:?}",
+                number, first, second
+            ),
+        }
+    }
+}
+
+// Tries to assign `number` an opcode, moving numbers that already have one
+// onto other candidates if needed. Every opcode is considered at most once per
+// search, `banned` rules out a single assignment.
+fn augment(
+    number: usize,
+    candidates: &HashMap<usize, HashSet<Opcode>>,
+    assigned: &mut HashMap<Opcode, usize>,
+    visited: &mut HashSet<Opcode>,
+    banned: Option<(usize, Opcode)>,
+) -> bool {
+    for &opcode in OPCODES
+        .iter()
+        .filter(|opcode| candidates[&number].contains(opcode))
+    {
+        if banned == Some((number, opcode)) || !visited.insert(opcode) {
+            continue;
+        }
+
+        let reassigned = match assigned.get(&opcode).cloned() {
+            None => true,
+            Some(other) => augment(other, candidates, assigned, visited, banned),
+        };
+
+        if reassigned {
+            assigned.insert(opcode, number);
+            return true;
+        }
+    }
+
+    false
+}
+
+// Matches opcode numbers with opcodes such that every number gets one of its
+// candidates and no two numbers share an opcode. The matching is built one
+// augmenting path at a time and is then unique exactly when no number can be
+// moved to another opcode, which is checked by banning each assignment in
+// turn.
+fn match_opcodes(
+    candidates: &HashMap<usize, HashSet<Opcode>>,
+) -> Result<HashMap<usize, Opcode>, DeductionError> {
+    let mut numbers = candidates.keys().cloned().collect::<Vec<_>>();
+    numbers.sort();
+
+    let mut assigned = HashMap::<Opcode, usize>::new();
+    for &number in &numbers {
+        if !augment(number, candidates, &mut assigned, &mut HashSet::new(), None) {
+            return Err(DeductionError::Unsatisfiable { number });
+        }
+    }
+
+    let mappings = assigned
+        .iter()
+        .map(|(&opcode, &number)| (number, opcode))
+        .collect::<HashMap<_, _>>();
+
+    for &number in &numbers {
+        let opcode = mappings[&number];
+        let mut alternative = assigned.clone();
+        alternative.remove(&opcode);
+
+        let banned = Some((number, opcode));
+        if augment(
+            number,
+            candidates,
+            &mut alternative,
+            &mut HashSet::new(),
+            banned,
+        ) {
+            let other = alternative
+                .iter()
+                .find(|&(_, &assigned_number)| assigned_number == number)
+                .map(|(&other, _)| other)
+                .unwrap();
+
+            return Err(DeductionError::Ambiguous {
+                number,
+                opcodes: (opcode, other),
+            });
+        }
+    }
+
+    Ok(mappings)
+}
+
+// The puzzle input is the observations, a few blank lines and then the
+// program. Splits it into the observations and the program, which starts
+// right after the last `After:` line.
+pub fn split_input(input: &str) -> (&str, &str) {
+    let end = input
+        .rfind("After:")
+        .map(|start| {
+            input[start..]
+                .find('\n')
+                .map(|offset| start + offset)
+                .unwrap_or(input.len())
+        }).unwrap_or(0);
+
+    (&input[..end], &input[end..])
+}
+
+fn parse_observations(input: &str) -> Vec<Observation> {
     let cleaned_lines = input
         .lines()
         .map(|line| line.trim())
         .filter(|line| line.len() > 0)
         .collect::<Vec<_>>();
-    let observations = cleaned_lines
+
+    cleaned_lines
         .chunks(3)
         .map(|chunk| Observation::from(chunk))
-        .collect::<Vec<_>>();
+        .collect()
+}
+
+// Works with both the observations on their own and the full puzzle input
+pub fn star_one(input: &str) -> i64 {
+    let (observations, _) = split_input(input);
 
-    observations
+    parse_observations(observations)
         .into_iter()
-        .map(|observation| {
-            let potential_instructions =
-                Instruction::from_potential_instruction(&observation.instruction);
-
-            potential_instructions
-                .into_iter()
-                .flat_map(|instruction| {
-                    let mut machine = Machine::new();
-                    machine.set_register_state(&observation.before);
-
-                    machine.execute(&instruction);
-
-                    if machine.registers[..] == observation.after[..] {
-                        Some(instruction)
-                    } else {
-                        None
-                    }
-                }).collect::<Vec<_>>()
-        }).fold(
+        .map(|observation| observation.matching_opcodes().count())
+        .fold(
             0,
-            |acc, matched_ops| if matched_ops.len() >= 3 { acc + 1 } else { acc },
+            |acc, matched_ops| if matched_ops >= 3 { acc + 1 } else { acc },
         )
 }
 
-pub fn star_two(observations: &str, program_source: &str) -> i64 {
-    let cleaned_lines = observations
-        .lines()
-        .map(|line| line.trim())
-        .filter(|line| line.len() > 0)
-        .collect::<Vec<_>>();
-    let observations = cleaned_lines
-        .chunks(3)
-        .map(|chunk| Observation::from(chunk))
-        .collect::<Vec<_>>();
-
-    let mut observed_opcodes: HashMap<usize, HashSet<Opcode>> = HashMap::new();
-
-    observations.into_iter().for_each(|observation| {
-        let potential_instructions =
-            Instruction::from_potential_instruction(&observation.instruction);
+// The opcode behind every opcode number seen in the observations, each
+// number's opcode has to agree with all of its observations.
+pub fn deduce_opcode_mapping(observations: &str) -> Result<HashMap<usize, Opcode>, DeductionError> {
+    deduce(&parse_observations(observations))
+}
 
-        potential_instructions.into_iter().for_each(|instruction| {
-            let mut machine = Machine::new();
-            machine.set_register_state(&observation.before);
+fn deduce(observations: &[Observation]) -> Result<HashMap<usize, Opcode>, DeductionError> {
+    let mut candidates: HashMap<usize, HashSet<Opcode>> = HashMap::new();
 
-            machine.execute(&instruction);
+    observations.iter().for_each(|observation| {
+        let matching = observation.matching_opcodes().collect::<HashSet<_>>();
+        let entry = candidates
+            .entry(observation.instruction[0])
+            .or_insert_with(|| matching.clone());
 
-            if machine.registers[..] == observation.after[..] {
-                let entry = observed_opcodes
-                    .entry(observation.instruction[0])
-                    .or_insert(HashSet::new());
-                entry.insert(instruction.opcode);
-            }
-        });
+        entry.retain(|opcode| matching.contains(opcode));
     });
 
-    let mut mappings = HashMap::<usize, Opcode>::new();
-
-    while !observed_opcodes.is_empty() {
-        let (opcode_number, current_opcode) = {
-            let (opcode_number, opcodes): (usize, HashSet<Opcode>) = observed_opcodes
-                .iter()
-                .filter(|(_, opcodes)| opcodes.len() == 1)
-                .map(|(number, opcodes)| (number.clone(), opcodes.clone()))
-                .nth(0)
-                .expect(&format!(
-                "There should always be an Opcode that only maps to a single opcode number.\n {:?}",
-                observed_opcodes
-            ));
-            let current_opcode = opcodes.iter().nth(0).map(|code| code.clone()).unwrap();
-
-            (opcode_number, current_opcode)
-        };
+    match_opcodes(&candidates)
+}
 
-        mappings.insert(opcode_number.clone(), current_opcode);
-        observed_opcodes.remove(&opcode_number);
-
-        {
-            for (_, opcodes) in observed_opcodes.iter_mut() {
-                opcodes.remove(&current_opcode);
-            }
-        }
-    }
+fn run_program(
+    observations: &str,
+    program_source: &str,
+    observe: &mut dyn FnMut(usize, &Instruction, &Machine),
+) -> i64 {
+    let observations = parse_observations(observations);
+    let mappings = deduce(&observations).unwrap_or_else(|e| panic!("{}", e));
+    // The program runs on a machine like the one observed
+    let num_registers = observations
+        .first()
+        .map(|observation| observation.before.len())
+        .unwrap_or(4);
 
     let instructions = program_source
         .lines()
@@ -392,14 +307,41 @@ pub fn star_two(observations: &str, program_source: &str) -> i64 {
                 instruction[2],
                 instruction[3],
             )
-        });
-    let mut machine = Machine::new();
+        }).collect::<Vec<_>>();
+    let mut machine = Machine::new(num_registers);
+    machine.run_observed(&instructions, observe);
 
-    for instruction in instructions {
-        machine.execute(&instruction);
-    }
+    machine[0]
+}
+
+pub fn star_two(observations: &str, program_source: &str) -> i64 {
+    run_program(observations, program_source, &mut |_, _, _| {})
+}
+
+// The answer along with the registers after every instruction of the
+// program, to check the deduced opcodes against by hand.
+pub fn star_two_traced(observations: &str, program_source: &str) -> (i64, String) {
+    let mut trace = vec![];
+    let result = run_program(
+        observations,
+        program_source,
+        &mut |idx, instruction, machine| {
+            trace.push(format!(
+                "{:>4}  {:<16}{:?}",
+                idx,
+                instruction.to_string(),
+                machine.registers()
+            ))
+        },
+    );
+
+    (result, trace.join("\n"))
+}
+
+pub fn star_two_combined(input: &str) -> i64 {
+    let (observations, program_source) = split_input(input);
 
-    machine.registers[0]
+    star_two(observations, program_source)
 }
 
 #[cfg(test)]
@@ -411,4 +353,165 @@ mod tests {
         let input = ["Before: [3, 3, 0, 2]", "10 2 0 1", "After:  [3, 0, 0, 2]"];
         let observation = Observation::from(&input[..]);
     }
+
+    #[test]
+    fn test_six_register_observation() {
+        let input = [
+            "Before: [1, 2, 3, 4, 5, 6]",
+            "9 4 5 5",
+            "After:  [1, 2, 3, 4, 5, 11]",
+        ];
+        let observation = Observation::from(&input[..]);
+
+        assert_eq!(observation.before, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            observation.matching_opcodes().collect::<Vec<_>>(),
+            vec![Opcode::Addr]
+        );
+    }
+
+    fn candidates(sets: &[&[Opcode]]) -> HashMap<usize, HashSet<Opcode>> {
+        sets.iter()
+            .enumerate()
+            .map(|(number, opcodes)| (number, opcodes.iter().cloned().collect()))
+            .collect()
+    }
+
+    static AMBIGUOUS_OBSERVATIONS: &str = "
+Before: [3, 2, 1, 1]
+9 2 1 2
+After:  [3, 2, 2, 1]
+
+Before: [3, 2, 1, 1]
+4 2 1 2
+After:  [3, 2, 2, 1]";
+
+    fn observations() -> String {
+        format!(
+            "{}\n\n{}",
+            AMBIGUOUS_OBSERVATIONS,
+            "
+Before: [0, 2, 1, 1]
+4 1 3 0
+After:  [5, 2, 1, 1]
+
+Before: [0, 2, 3, 1]
+9 1 2 0
+After:  [6, 2, 3, 1]"
+        )
+    }
+
+    #[test]
+    fn test_deduce_opcode_mapping() {
+        let expected = vec![(4, Opcode::Addi), (9, Opcode::Mulr)];
+        assert_eq!(
+            deduce_opcode_mapping(&observations()),
+            Ok(expected.into_iter().collect())
+        );
+        assert_eq!(
+            deduce_opcode_mapping(AMBIGUOUS_OBSERVATIONS),
+            Err(DeductionError::Ambiguous {
+                number: 4,
+                opcodes: (Opcode::Mulr, Opcode::Addi),
+            })
+        );
+    }
+
+    #[test]
+    fn test_star_two_traced() {
+        let (result, trace) = star_two_traced(&observations(), "4 0 5 0\n9 0 0 1\n4 1 2 0");
+
+        assert_eq!(result, 27);
+        assert_eq!(
+            trace,
+            "   0  addi 0 5 0      [5, 0, 0, 0]
+   1  mulr 0 0 1      [5, 25, 0, 0]
+   2  addi 1 2 0      [27, 25, 0, 0]"
+        );
+    }
+
+    #[test]
+    fn test_split_input() {
+        let input = "Before: [3, 2, 1, 1]
+9 2 1 2
+After:  [3, 2, 2, 1]
+
+
+
+9 0 1 2
+9 2 3 0";
+        let (observations, program) = split_input(input);
+
+        assert_eq!(
+            observations,
+            "Before: [3, 2, 1, 1]\n9 2 1 2\nAfter:  [3, 2, 2, 1]"
+        );
+        assert_eq!(program, "\n\n\n\n9 0 1 2\n9 2 3 0");
+        assert_eq!(split_input(observations), (observations, ""));
+        assert_eq!(split_input("9 0 1 2"), ("", "9 0 1 2"));
+    }
+
+    #[test]
+    fn test_match_opcodes() {
+        let mappings = match_opcodes(&candidates(&[
+            &[Opcode::Addr, Opcode::Mulr, Opcode::Seti],
+            &[Opcode::Addr, Opcode::Mulr],
+            &[Opcode::Mulr],
+        ]))
+        .unwrap();
+
+        assert_eq!(mappings[&0], Opcode::Seti);
+        assert_eq!(mappings[&1], Opcode::Addr);
+        assert_eq!(mappings[&2], Opcode::Mulr);
+
+        let mappings = match_opcodes(&candidates(&[
+            &[Opcode::Seti, Opcode::Eqrr],
+            &[Opcode::Seti],
+        ]))
+        .unwrap();
+        assert_eq!(mappings[&0], Opcode::Eqrr);
+    }
+
+    #[test]
+    fn test_match_opcodes_ambiguous() {
+        assert_eq!(
+            match_opcodes(&candidates(&[
+                &[Opcode::Addr, Opcode::Mulr],
+                &[Opcode::Addr, Opcode::Mulr],
+                &[Opcode::Seti],
+            ])),
+            Err(DeductionError::Ambiguous {
+                number: 0,
+                opcodes: (Opcode::Mulr, Opcode::Addr),
+            })
+        );
+
+        // Two opcodes that no other number needs
+        assert_eq!(
+            match_opcodes(&candidates(&[
+                &[Opcode::Seti, Opcode::Eqrr],
+                &[Opcode::Addr]
+            ])),
+            Err(DeductionError::Ambiguous {
+                number: 0,
+                opcodes: (Opcode::Seti, Opcode::Eqrr),
+            })
+        );
+    }
+
+    #[test]
+    fn test_match_opcodes_unsatisfiable() {
+        assert_eq!(
+            match_opcodes(&candidates(&[
+                &[Opcode::Addr, Opcode::Mulr],
+                &[Opcode::Addr],
+                &[Opcode::Addr, Opcode::Mulr],
+            ])),
+            Err(DeductionError::Unsatisfiable { number: 2 })
+        );
+        assert_eq!(
+            match_opcodes(&candidates(&[&[Opcode::Addr], &[]])),
+            Err(DeductionError::Unsatisfiable { number: 1 })
+        );
+    }
 }
@@ -1,22 +1,767 @@
-pub fn star_one(input: &str) -> i64 {
-    0
+use std::fmt;
+use std::str::FromStr;
+
+use viz::{self, Color};
+
+const SPRING: (usize, usize) = (500, 0);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Tile {
+    Sand,
+    Clay,
+    Flowing,
+    Settled,
+}
+
+impl Tile {
+    fn is_wet(self) -> bool {
+        self == Tile::Flowing || self == Tile::Settled
+    }
+
+    // Whether water on top of this tile stays there instead of falling
+    fn supports(self) -> bool {
+        self == Tile::Clay || self == Tile::Settled
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Tile::Sand => '.',
+            Tile::Clay => '#',
+            Tile::Flowing => '|',
+            Tile::Settled => '~',
+        }
+    }
+
+    fn to_color(self) -> Color {
+        match self {
+            Tile::Sand => (240, 225, 180),
+            Tile::Clay => (130, 80, 40),
+            Tile::Flowing => (120, 190, 250),
+            Tile::Settled => (20, 70, 200),
+        }
+    }
 }
 
-pub fn star_two(input: &str) -> i64 {
-    0
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Algorithm {
+    // Moves the water a tile at a time until nothing changes, slow but
+    // simple enough to be obviously right.
+    Stepwise,
+    // Falls until blocked and then spreads a whole row at a time
+    Scanline,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    InvalidVein(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidVein(line) => write!(f, "Invalid clay vein `{}`", line),
+        }
+    }
+}
+
+// A vertical or horizontal line of clay, inclusive at both ends
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Vein {
+    xs: (usize, usize),
+    ys: (usize, usize),
+}
+
+fn parse_range(input: &str) -> Option<(usize, usize)> {
+    let mut parts = input.split("..").map(|part| part.trim().parse::<usize>());
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(start)), None, None) => Some((start, start)),
+        (Some(Ok(start)), Some(Ok(end)), None) if start <= end => Some((start, end)),
+        _ => None,
+    }
+}
+
+impl FromStr for Vein {
+    type Err = ParseError;
+
+    // Either `x=495, y=2..7` or `y=7, x=495..501`
+    fn from_str(line: &str) -> Result<Self, ParseError> {
+        let invalid = || ParseError::InvalidVein(line.to_owned());
+        let mut xs = None;
+        let mut ys = None;
+
+        for part in line.split(',') {
+            let mut assignment = part.trim().splitn(2, '=');
+            let (range, value) = match (assignment.next(), assignment.next()) {
+                (Some("x"), Some(value)) if xs.is_none() => (&mut xs, value),
+                (Some("y"), Some(value)) if ys.is_none() => (&mut ys, value),
+                _ => return Err(invalid()),
+            };
+            *range = Some(parse_range(value).ok_or_else(invalid)?);
+        }
+
+        match (xs, ys) {
+            (Some(xs), Some(ys)) => Ok(Self { xs, ys }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// The slice of ground that contains all the clay, with a column of sand on
+// either side for water to spill into. While recording every change to a
+// tile is kept, along with where each frame of an animation ends.
+#[derive(Clone)]
+pub struct Ground {
+    tiles: Vec<Vec<Tile>>,
+    min_x: usize,
+    min_y: usize,
+    max_y: usize,
+    sources: Vec<(usize, usize)>,
+    history: Option<Vec<(usize, usize, Tile)>>,
+    frame_ends: Vec<usize>,
+}
+
+impl Ground {
+    fn new(veins: &[Vein]) -> Self {
+        let min_x = veins.iter().map(|v| v.xs.0).fold(SPRING.0, usize::min) - 1;
+        let max_x = veins.iter().map(|v| v.xs.1).fold(SPRING.0, usize::max) + 1;
+        let min_y = veins.iter().map(|v| v.ys.0).min().unwrap_or(0);
+        let max_y = veins.iter().map(|v| v.ys.1).max().unwrap_or(0);
+
+        let mut tiles = vec![vec![Tile::Sand; max_x - min_x + 1]; max_y + 1];
+        for vein in veins {
+            for row in &mut tiles[vein.ys.0..=vein.ys.1] {
+                for tile in &mut row[vein.xs.0 - min_x..=vein.xs.1 - min_x] {
+                    *tile = Tile::Clay;
+                }
+            }
+        }
+
+        Self {
+            tiles,
+            min_x,
+            min_y,
+            max_y,
+            sources: vec![SPRING],
+            history: None,
+            frame_ends: vec![],
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> Tile {
+        self.tiles[y][x - self.min_x]
+    }
+
+    fn tile(&self, x: usize, y: usize) -> Option<Tile> {
+        if x < self.min_x || x > self.max_x() || y > self.max_y {
+            None
+        } else {
+            Some(self.get(x, y))
+        }
+    }
+
+    pub fn is_wet(&self, x: usize, y: usize) -> bool {
+        self.tile(x, y).is_some_and(Tile::is_wet)
+    }
+
+    pub fn is_settled(&self, x: usize, y: usize) -> bool {
+        self.tile(x, y) == Some(Tile::Settled)
+    }
+
+    pub fn is_clay(&self, x: usize, y: usize) -> bool {
+        self.tile(x, y) == Some(Tile::Clay)
+    }
+
+    pub fn reached(&self) -> usize {
+        self.count(Tile::is_wet)
+    }
+
+    pub fn retained(&self) -> usize {
+        self.count(|tile| tile == Tile::Settled)
+    }
+
+    // Makes room for clay between `min_x` and `max_x` down to `max_y`, keeping
+    // a column of sand on either side.
+    fn grow(&mut self, min_x: usize, max_x: usize, max_y: usize) {
+        if min_x <= self.min_x {
+            let extra = self.min_x - min_x + 1;
+            for row in &mut self.tiles {
+                row.splice(0..0, vec![Tile::Sand; extra]);
+            }
+            self.min_x -= extra;
+        }
+
+        if max_x >= self.max_x() {
+            let extra = max_x - self.max_x() + 1;
+            for row in &mut self.tiles {
+                row.extend(vec![Tile::Sand; extra]);
+            }
+        }
+
+        if max_y > self.max_y {
+            let width = self.tiles[0].len();
+            self.tiles.resize(max_y + 1, vec![Tile::Sand; width]);
+            self.max_y = max_y;
+        }
+    }
+
+    // Adds a vein of clay, given like in the puzzle input, and reflows the
+    // water below it. Water that was resting on anything in the reflowed
+    // rows is reflowed too since it might settle now, and when a basin fills
+    // all the way up to the top of the reflowed rows the row above it is.
+    pub fn add_vein(&mut self, vein: &str) -> Result<(), ParseError> {
+        let vein = vein.trim().parse::<Vein>()?;
+        let old_max_y = self.max_y;
+        self.grow(vein.xs.0, vein.xs.1, vein.ys.1);
+        self.min_y = self.min_y.min(vein.ys.0);
+        for y in vein.ys.0..=vein.ys.1 {
+            for x in vein.xs.0..=vein.xs.1 {
+                self.set(x, y, Tile::Clay);
+            }
+        }
+
+        let mut top = vein.ys.0.min(old_max_y + 1).saturating_sub(1);
+        loop {
+            while top > 0
+                && (self.min_x..=self.max_x())
+                    .any(|x| self.get(x, top - 1).is_wet() && self.get(x, top).supports())
+            {
+                top -= 1;
+            }
+
+            // Only falling water is left directly above the reflowed rows
+            let feeds = (self.min_x..=self.max_x())
+                .filter(|&x| top > 0 && self.get(x, top - 1) == Tile::Flowing)
+                .map(|x| (x, top))
+                .collect::<Vec<_>>();
+
+            self.reflow(top, &feeds);
+
+            if !feeds.iter().any(|&(x, y)| self.get(x, y) == Tile::Settled) {
+                return Ok(());
+            }
+        }
+    }
+
+    // Dries every row from `top` down and pours the water back in
+    fn reflow(&mut self, top: usize, feeds: &[(usize, usize)]) {
+        for y in top..=self.max_y {
+            for x in self.min_x..=self.max_x() {
+                if self.get(x, y).is_wet() {
+                    self.set(x, y, Tile::Sand);
+                }
+            }
+        }
+
+        let sources = self.sources.clone();
+        for &(x, y) in feeds
+            .iter()
+            .chain(sources.iter().filter(|&&(_, y)| y >= top))
+        {
+            self.pour(x, y);
+        }
+    }
+
+    // Adds another spring, only the water coming from it is flowed
+    pub fn add_source(&mut self, x: usize, y: usize) {
+        self.grow(x, x, 0);
+        self.sources.push((x, y));
+        self.pour(x, y);
+    }
+
+    fn pour(&mut self, x: usize, y: usize) {
+        if y <= self.max_y && self.get(x, y) == Tile::Sand {
+            self.flow_scanline(x, y);
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, tile: Tile) {
+        self.tiles[y][x - self.min_x] = tile;
+
+        if let Some(history) = self.history.as_mut() {
+            history.push((x, y, tile));
+        }
+    }
+
+    fn end_frame(&mut self) {
+        if let Some(history) = self.history.as_ref() {
+            if self.frame_ends.last() != Some(&history.len()) {
+                self.frame_ends.push(history.len());
+            }
+        }
+    }
+
+    fn max_x(&self) -> usize {
+        self.min_x + self.tiles[0].len() - 1
+    }
+
+    // Moves the water one step at a time until nothing changes anymore.
+    // Flowing water falls onto sand below it and spreads sideways when it's
+    // supported, a row of flowing water that's supported everywhere and
+    // enclosed by clay on both ends settles.
+    fn flow_stepwise(&mut self, spring: (usize, usize)) {
+        self.set(spring.0, spring.1, Tile::Flowing);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for y in 0..self.max_y {
+                for x in self.min_x..=self.max_x() {
+                    if self.get(x, y) != Tile::Flowing {
+                        continue;
+                    }
+
+                    let below = self.get(x, y + 1);
+                    if below == Tile::Sand {
+                        self.set(x, y + 1, Tile::Flowing);
+                        changed = true;
+                    } else if below.supports() {
+                        for side in &[x - 1, x + 1] {
+                            if self.get(*side, y) == Tile::Sand {
+                                self.set(*side, y, Tile::Flowing);
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+
+                changed |= self.settle_row(y);
+            }
+        }
+    }
+
+    // Water falls from `(x, top)` until it lands on something. If that's clay
+    // or settled water it spreads out over the row, when the row turns out to
+    // be enclosed on both sides it settles and the next row up is filled,
+    // but never above `top`.
+    fn flow_scanline(&mut self, x: usize, top: usize) {
+        let mut y = top;
+        while y < self.max_y && self.get(x, y + 1) == Tile::Sand {
+            self.set(x, y, Tile::Flowing);
+            y += 1;
+        }
+        self.set(x, y, Tile::Flowing);
+        self.end_frame();
+
+        if y == self.max_y || self.get(x, y + 1) == Tile::Flowing {
+            return;
+        }
+
+        loop {
+            let left = self.spread(x, y, false);
+            let right = self.spread(x, y, true);
+
+            match (left, right) {
+                (Some(left), Some(right)) => {
+                    for x in left..=right {
+                        self.set(x, y, Tile::Settled);
+                    }
+                    self.end_frame();
+                }
+                _ => {
+                    self.end_frame();
+                    return;
+                }
+            }
+
+            if y == top {
+                return;
+            }
+            y -= 1;
+        }
+    }
+
+    // Spreads flowing water from `x` along row `y` until it either reaches
+    // clay, returning the last tile before it, or spills over an edge. Water
+    // that spills fills whatever is below first, which might turn out to
+    // support the row after all.
+    fn spread(&mut self, x: usize, y: usize, rightwards: bool) -> Option<usize> {
+        let mut x = x;
+
+        loop {
+            self.set(x, y, Tile::Flowing);
+
+            if !self.get(x, y + 1).supports() {
+                if self.get(x, y + 1) == Tile::Sand {
+                    self.flow_scanline(x, y + 1);
+                }
+
+                if !self.get(x, y + 1).supports() {
+                    return None;
+                }
+            }
+
+            let next = if rightwards { x + 1 } else { x - 1 };
+            if self.get(next, y) == Tile::Clay {
+                return Some(x);
+            }
+            x = next;
+        }
+    }
+
+    // Settles every enclosed and supported run of flowing water in row `y`
+    fn settle_row(&mut self, y: usize) -> bool {
+        let mut changed = false;
+        let mut x = self.min_x;
+
+        while x <= self.max_x() {
+            if self.get(x, y) != Tile::Flowing {
+                x += 1;
+                continue;
+            }
+
+            let start = x;
+            while x <= self.max_x() && self.get(x, y) == Tile::Flowing {
+                x += 1;
+            }
+
+            let enclosed = start > self.min_x
+                && self.get(start - 1, y) == Tile::Clay
+                && x <= self.max_x()
+                && self.get(x, y) == Tile::Clay;
+            let supported = (start..x).all(|x| self.get(x, y + 1).supports());
+
+            if enclosed && supported {
+                for x in start..x {
+                    self.set(x, y, Tile::Settled);
+                }
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    pub fn render_image(&self) -> Vec<u8> {
+        let height = self.tiles.len();
+        let width = self.tiles[0].len();
+        let pixels = self
+            .tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter().enumerate().map(move |(x, tile)| {
+                    if (x + self.min_x, y) == SPRING {
+                        (200, 30, 30)
+                    } else {
+                        tile.to_color()
+                    }
+                })
+            }).collect::<Vec<_>>();
+
+        viz::ppm(width, height, &pixels)
+    }
+
+    // Only the rows between the topmost and bottommost clay count
+    fn count<F>(&self, predicate: F) -> usize
+    where
+        F: Fn(Tile) -> bool,
+    {
+        self.tiles[self.min_y..=self.max_y]
+            .iter()
+            .map(|row| row.iter().filter(|&&tile| predicate(tile)).count())
+            .sum()
+    }
+}
+
+// Drawn like the puzzle description with the spring as `+`
+impl fmt::Display for Ground {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .tiles
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, tile)| {
+                        if (x + self.min_x, y) == SPRING {
+                            '+'
+                        } else {
+                            tile.to_char()
+                        }
+                    }).collect::<String>()
+            }).collect::<Vec<_>>();
+
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+// The ground as the water spreads, one frame for every time it falls and
+// every row it fills.
+pub struct Frames {
+    ground: Ground,
+    history: Vec<(usize, usize, Tile)>,
+    frame_ends: Vec<usize>,
+    frame: usize,
+}
+
+impl Iterator for Frames {
+    type Item = Ground;
+
+    fn next(&mut self) -> Option<Ground> {
+        let end = *self.frame_ends.get(self.frame)?;
+        let start = if self.frame == 0 {
+            0
+        } else {
+            self.frame_ends[self.frame - 1]
+        };
+
+        for &(x, y, tile) in &self.history[start..end] {
+            self.ground.set(x, y, tile);
+        }
+        self.frame += 1;
+
+        Some(self.ground.clone())
+    }
+}
+
+// The ground with all the water from the spring
+impl FromStr for Ground {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, ParseError> {
+        let mut ground = Ground::new(&parse(input)?);
+        ground.pour(SPRING.0, SPRING.1);
+
+        Ok(ground)
+    }
+}
+
+pub fn frames(input: &str) -> Frames {
+    let veins = parse(input).unwrap_or_else(|e| panic!("{}", e));
+    let dry = Ground::new(&veins);
+    let mut ground = dry.clone();
+    ground.history = Some(vec![]);
+    ground.pour(SPRING.0, SPRING.1);
+
+    Frames {
+        ground: dry,
+        history: ground.history.unwrap_or_default(),
+        frame_ends: ground.frame_ends,
+        frame: 0,
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<Vein>, ParseError> {
+    input
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse())
+        .collect()
+}
+
+fn flooded(input: &str, algorithm: Algorithm) -> Ground {
+    let veins = parse(input).unwrap_or_else(|e| panic!("{}", e));
+    let mut ground = Ground::new(&veins);
+
+    match algorithm {
+        Algorithm::Stepwise => ground.flow_stepwise(SPRING),
+        Algorithm::Scanline => ground.pour(SPRING.0, SPRING.1),
+    }
+
+    ground
+}
+
+// The number of tiles the water reaches and the number of tiles where it
+// settles.
+pub fn water(input: &str, algorithm: Algorithm) -> (usize, usize) {
+    let ground = flooded(input, algorithm);
+
+    (ground.reached(), ground.retained())
+}
+
+pub fn render(input: &str) -> String {
+    flooded(input, Algorithm::Scanline).to_string()
+}
+
+pub fn render_image(input: &str) -> Vec<u8> {
+    flooded(input, Algorithm::Scanline).render_image()
+}
+
+pub fn star_one(input: &str) -> usize {
+    water(input, Algorithm::Scanline).0
+}
+
+pub fn star_two(input: &str) -> usize {
+    water(input, Algorithm::Scanline).1
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{frames, render, star_one, star_two, water, Algorithm, Ground, ParseError, Vein};
+    use differential::Rng;
+    use snapshot::assert_snapshot;
+
+    static EXAMPLE: &str = "x=495, y=2..7
+y=7, x=495..501
+x=501, y=3..7
+x=498, y=2..4
+x=506, y=1..2
+x=498, y=10..13
+x=504, y=10..13
+y=13, x=498..504";
 
     #[test]
     fn test_star_one() {
-        assert_eq!(star_one(""), 1)
+        assert_eq!(star_one(EXAMPLE), 57)
     }
 
     #[test]
     fn test_star_two() {
-        assert_eq!(star_two(""), 1)
+        assert_eq!(star_two(EXAMPLE), 29)
+    }
+
+    // Lots of small basins and ledges around the spring
+    fn random_veins(count: usize) -> Vec<Vec<String>> {
+        let mut rng = Rng::new(2018);
+
+        (0..count)
+            .map(|_| {
+                let mut veins = vec![];
+
+                for _ in 0..15 {
+                    let (x, y) = (485 + rng.next(30), 2 + rng.next(50));
+                    let (width, depth) = (2 + rng.next(8), 1 + rng.next(5));
+
+                    veins.push(format!("y={}, x={}..{}", y + depth, x, x + width));
+                    if rng.next(4) > 0 {
+                        veins.push(format!("x={}, y={}..{}", x, y, y + depth));
+                    }
+                    if rng.next(4) > 0 {
+                        veins.push(format!("x={}, y={}..{}", x + width, y, y + depth));
+                    }
+                }
+
+                veins
+            }).collect()
+    }
+
+    #[test]
+    fn test_algorithms_agree() {
+        assert_eq!(water(EXAMPLE, Algorithm::Stepwise), (57, 29));
+        assert_eq!(water(EXAMPLE, Algorithm::Scanline), (57, 29));
+
+        for veins in random_veins(50) {
+            let input = veins.join("\n");
+
+            assert_eq!(
+                water(&input, Algorithm::Scanline),
+                water(&input, Algorithm::Stepwise),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_vein() {
+        // Adding the veins one at a time ends up where scanning them all does
+        for veins in random_veins(50) {
+            let mut ground = veins[0].parse::<Ground>().unwrap();
+            for vein in &veins[1..] {
+                ground.add_vein(vein).unwrap();
+            }
+            let expected = veins.join("\n").parse::<Ground>().unwrap();
+
+            assert_eq!(ground.to_string(), expected.to_string(), "{:?}", veins);
+        }
+
+        // Plugging the hole in a basin fills it all the way up
+        let mut ground = "x=498, y=2..6\nx=502, y=2..6\ny=6, x=498..499\ny=6, x=501..502"
+            .parse::<Ground>()
+            .unwrap();
+        assert!(!ground.is_settled(500, 5));
+        ground.add_vein("y=6, x=500").unwrap();
+        assert!(ground.is_settled(500, 2));
+        assert_eq!(ground.retained(), 3 * 4);
+        assert_eq!(
+            ground.add_vein("x=1"),
+            Err(ParseError::InvalidVein("x=1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_add_source() {
+        let mut ground = EXAMPLE.parse::<Ground>().unwrap();
+        assert!(!ground.is_wet(513, 3));
+
+        // A basin of its own far off to the side, and a source in the dry
+        // corner of the example
+        ground.add_vein("x=511, y=2..4").unwrap();
+        ground.add_vein("x=515, y=2..4").unwrap();
+        ground.add_vein("y=4, x=512..514").unwrap();
+        ground.add_source(513, 0);
+        ground.add_source(503, 8);
+
+        assert!(ground.is_wet(513, 3));
+        assert!(ground.is_settled(513, 2));
+        assert!(ground.is_wet(503, 8));
+        assert!(!ground.is_wet(600, 3));
+        assert!(ground.is_clay(511, 2));
+        assert_eq!(ground.retained(), 29 + 3 * 2);
+        // The new basin, the row above it and the two streams past it, and
+        // a single tile between the source and the flowing water below it
+        assert_eq!(ground.reached(), 57 + 3 * 2 + 7 + 2 * 12 + 1);
+    }
+
+    #[test]
+    fn test_render() {
+        assert_snapshot("day17_example", &render(EXAMPLE));
+    }
+
+    #[test]
+    fn test_frames() {
+        let frames = frames(EXAMPLE).collect::<Vec<_>>();
+
+        assert!(frames.len() > 10);
+        assert_eq!(frames.last().unwrap().to_string(), render(EXAMPLE));
+        assert_eq!(
+            frames[0].to_string().lines().take(4).collect::<Vec<_>>(),
+            vec![
+                "......+.......",
+                "......|.....#.",
+                ".#..#.|.....#.",
+                ".#..#.|#......"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_vein() {
+        assert_eq!(
+            "x=495, y=2..7".parse::<Vein>(),
+            Ok(Vein {
+                xs: (495, 495),
+                ys: (2, 7),
+            })
+        );
+        assert_eq!(
+            "y=7, x=495..501".parse::<Vein>(),
+            Ok(Vein {
+                xs: (495, 501),
+                ys: (7, 7),
+            })
+        );
+        assert_eq!(
+            "x=495, x=2..7".parse::<Vein>(),
+            Err(ParseError::InvalidVein(String::from("x=495, x=2..7")))
+        );
+        assert_eq!(
+            "y=7, x=501..495".parse::<Vein>(),
+            Err(ParseError::InvalidVein(String::from("y=7, x=501..495")))
+        );
+    }
+
+    #[test]
+    fn test_overflowing_basin() {
+        // The basin fills up and then overflows on both sides, the row above
+        // the topmost clay doesn't count
+        let input = "x=499, y=2..4\nx=503, y=2..4\ny=4, x=500..502";
+
+        assert_eq!(star_two(input), 6);
+        assert_eq!(star_one(input), 6 + 2 * 3);
     }
 }
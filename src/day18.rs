@@ -1,22 +1,616 @@
-pub fn star_one(input: &str) -> i64 {
-    0
+use std::fmt;
+use std::mem;
+
+use cycle;
+use viz::{self, Color};
+
+const OPEN: char = '.';
+const TREES: char = '|';
+const LUMBERYARD: char = '#';
+
+// Packed acres take two bits each, the low one is set for trees and the high
+// one for lumberyards.
+const ACRES_PER_WORD: usize = 32;
+const LOW_BITS: u64 = 0x5555_5555_5555_5555;
+// Counting happens in four bit lanes, one for every other acre
+const LANES: u64 = 0x1111_1111_1111_1111;
+const FIVES: u64 = 0x5555_5555_5555_5555;
+const SEVENS: u64 = 0x7777_7777_7777_7777;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    UnknownCharacter {
+        location: (usize, usize),
+        character: char,
+    },
+    RaggedRow {
+        row: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownCharacter {
+                location: (x, y),
+                character,
+            } => write!(f, "Unknown acre `{}` at ({}, {})", character, x, y),
+            ParseError::RaggedRow { row } => {
+                write!(f, "Row {} is not as wide as the first one", row)
+            }
+        }
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<Vec<char>>, ParseError> {
+    let grid = input
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .map(|(x, character)| match character {
+                    OPEN | TREES | LUMBERYARD => Ok(character),
+                    _ => Err(ParseError::UnknownCharacter {
+                        location: (x, y),
+                        character,
+                    }),
+                }).collect::<Result<Vec<_>, _>>()
+        }).collect::<Result<Vec<_>, _>>()?;
+
+    match grid.iter().position(|row| row.len() != grid[0].len()) {
+        Some(row) => Err(ParseError::RaggedRow { row }),
+        None => Ok(grid),
+    }
+}
+
+// The number of trees and lumberyards among the up to eight acres around
+// `(x, y)`
+fn neighbours(grid: &[Vec<char>], x: usize, y: usize) -> (usize, usize) {
+    let mut trees = 0;
+    let mut lumberyards = 0;
+
+    let (left, top) = (x.saturating_sub(1), y.saturating_sub(1));
+
+    for (ny, row) in grid[top..(y + 2).min(grid.len())].iter().enumerate() {
+        for (nx, &acre) in row[left..(x + 2).min(row.len())].iter().enumerate() {
+            if (left + nx, top + ny) == (x, y) {
+                continue;
+            }
+
+            match acre {
+                TREES => trees += 1,
+                LUMBERYARD => lumberyards += 1,
+                _ => {}
+            }
+        }
+    }
+
+    (trees, lumberyards)
+}
+
+fn minute(grid: &[Vec<char>]) -> Vec<Vec<char>> {
+    grid.iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(x, &acre)| {
+                    let (trees, lumberyards) = neighbours(grid, x, y);
+
+                    match acre {
+                        OPEN if trees >= 3 => TREES,
+                        TREES if lumberyards >= 3 => LUMBERYARD,
+                        LUMBERYARD if trees == 0 || lumberyards == 0 => OPEN,
+                        _ => acre,
+                    }
+                }).collect()
+        }).collect()
+}
+
+fn resource_value(grid: &[Vec<char>]) -> usize {
+    let count = |kind: char| grid.iter().flatten().filter(|&&acre| acre == kind).count();
+
+    count(TREES) * count(LUMBERYARD)
+}
+
+// Lane `k` holds the sum of acres `2k - 1`, `2k` and `2k + 1` in `even` and of
+// `2k`, `2k + 1` and `2k + 2` in `odd`, where `bits` has the low bit of every
+// acre of one kind set.
+fn row_sums(bits: &[u64]) -> Vec<(u64, u64)> {
+    let lanes = |idx: usize| {
+        bits.get(idx)
+            .map_or((0, 0), |word| (word & LANES, (word >> 2) & LANES))
+    };
+
+    (0..bits.len())
+        .map(|idx| {
+            let (even, odd) = lanes(idx);
+            let previous_odd = if idx == 0 { 0 } else { lanes(idx - 1).1 };
+            let next_even = lanes(idx + 1).0;
+
+            (
+                even + odd + ((odd << 4) | (previous_odd >> 60)),
+                even + odd + ((even >> 4) | (next_even << 60)),
+            )
+        }).collect()
+}
+
+// Whether every lane is at least three and whether it's non zero, lanes never
+// go above eight.
+fn at_least_three(lanes: u64) -> u64 {
+    ((lanes + FIVES) >> 3) & LANES
+}
+
+fn non_zero(lanes: u64) -> u64 {
+    ((lanes + SEVENS) >> 3) & LANES
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PackedForest {
+    width: usize,
+    rows: Vec<Vec<u64>>,
+}
+
+impl PackedForest {
+    fn new(grid: &[Vec<char>]) -> Self {
+        let width = grid.first().map_or(0, |row| row.len());
+        let rows = grid
+            .iter()
+            .map(|row| {
+                row.chunks(ACRES_PER_WORD)
+                    .map(|chunk| {
+                        chunk.iter().enumerate().fold(0, |word, (idx, &acre)| {
+                            let bits = match acre {
+                                TREES => 1,
+                                LUMBERYARD => 2,
+                                _ => 0,
+                            };
+
+                            word | bits << (2 * idx)
+                        })
+                    }).collect()
+            }).collect();
+
+        Self { width, rows }
+    }
+
+    // Every acre that's in the forest, padding at the end of each row is left
+    // open.
+    fn valid(&self, idx: usize) -> u64 {
+        let acres = self.width - idx * ACRES_PER_WORD;
+
+        if acres >= ACRES_PER_WORD {
+            !0
+        } else {
+            (1 << (2 * acres)) - 1
+        }
+    }
+
+    // Counts the neighbours of 32 acres at a time by summing three acres
+    // horizontally in each row and then the three rows, less the acre itself.
+    fn minute(&self) -> Self {
+        let trees = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|word| word & LOW_BITS).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let lumberyards = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|word| (word >> 1) & LOW_BITS)
+                    .collect::<Vec<_>>()
+            }).collect::<Vec<_>>();
+        let tree_sums = trees.iter().map(|row| row_sums(row)).collect::<Vec<_>>();
+        let lumberyard_sums = lumberyards
+            .iter()
+            .map(|row| row_sums(row))
+            .collect::<Vec<_>>();
+
+        let neighbours = |sums: &[Vec<(u64, u64)>], bits: &[Vec<u64>], y: usize, idx: usize| {
+            let (mut even, mut odd) = sums[y][idx];
+            for row in &[y.wrapping_sub(1), y + 1] {
+                if let Some(&(above_even, above_odd)) = sums.get(*row).map(|row| &row[idx]) {
+                    even += above_even;
+                    odd += above_odd;
+                }
+            }
+
+            let word = bits[y][idx];
+            (even - (word & LANES), odd - ((word >> 2) & LANES))
+        };
+
+        let rows = (0..self.rows.len())
+            .map(|y| {
+                (0..self.rows[y].len())
+                    .map(|idx| {
+                        let (trees_even, trees_odd) = neighbours(&tree_sums, &trees, y, idx);
+                        let (lumber_even, lumber_odd) =
+                            neighbours(&lumberyard_sums, &lumberyards, y, idx);
+
+                        let next = |shift: u64, trees_around: u64, lumber_around: u64| {
+                            let is_trees = (trees[y][idx] >> shift) & LANES;
+                            let is_lumberyard = (lumberyards[y][idx] >> shift) & LANES;
+                            let is_open = LANES & !(is_trees | is_lumberyard);
+
+                            let trees = (is_open & at_least_three(trees_around))
+                                | (is_trees & !at_least_three(lumber_around));
+                            let lumberyards = (is_trees & at_least_three(lumber_around))
+                                | (is_lumberyard
+                                    & non_zero(trees_around)
+                                    & non_zero(lumber_around));
+
+                            (trees | lumberyards << 1) << shift
+                        };
+
+                        (next(0, trees_even, lumber_even) | next(2, trees_odd, lumber_odd))
+                            & self.valid(idx)
+                    }).collect()
+            }).collect();
+
+        Self {
+            width: self.width,
+            rows,
+        }
+    }
+
+    fn count(&self, shift: u64) -> usize {
+        self.rows
+            .iter()
+            .flatten()
+            .map(|word| ((word >> shift) & LOW_BITS).count_ones() as usize)
+            .sum()
+    }
+
+    pub fn wooded(&self) -> usize {
+        self.count(0)
+    }
+
+    pub fn lumberyards(&self) -> usize {
+        self.count(1)
+    }
+
+    pub fn resource_value(&self) -> usize {
+        self.wooded() * self.lumberyards()
+    }
+
+    fn acres(&self) -> impl Iterator<Item = Vec<char>> + '_ {
+        self.rows.iter().map(move |row| {
+            (0..self.width)
+                .map(
+                    |x| match (row[x / ACRES_PER_WORD] >> (2 * (x % ACRES_PER_WORD))) & 3 {
+                        1 => TREES,
+                        2 => LUMBERYARD,
+                        _ => OPEN,
+                    },
+                )
+                .collect()
+        })
+    }
+
+    pub fn render_image(&self) -> Vec<u8> {
+        let pixels = self
+            .acres()
+            .flat_map(|row| row.into_iter().map(to_color))
+            .collect::<Vec<_>>();
+
+        viz::ppm(self.width, self.rows.len(), &pixels)
+    }
+}
+
+fn to_color(acre: char) -> Color {
+    match acre {
+        TREES => (30, 120, 40),
+        LUMBERYARD => (120, 70, 30),
+        _ => (200, 210, 150),
+    }
+}
+
+impl fmt::Display for PackedForest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .acres()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+// Every minute starting with the initial forest
+pub struct Minutes {
+    forest: PackedForest,
+}
+
+impl Iterator for Minutes {
+    type Item = PackedForest;
+
+    fn next(&mut self) -> Option<PackedForest> {
+        let next = self.forest.minute();
+
+        Some(mem::replace(&mut self.forest, next))
+    }
+}
+
+pub fn minutes(input: &str) -> Minutes {
+    Minutes {
+        forest: parse_packed(input),
+    }
+}
+
+// The number of wooded acres, the number of lumberyards and the resource
+// value for every minute.
+pub fn resources(input: &str) -> impl Iterator<Item = (usize, usize, usize)> {
+    minutes(input).map(|forest| {
+        (
+            forest.wooded(),
+            forest.lumberyards(),
+            forest.resource_value(),
+        )
+    })
+}
+
+// Runs `minutes` minutes calling `observer` with the number and forest of
+// every minute along the way, including the initial one.
+pub fn observe(
+    input: &str,
+    minutes: usize,
+    observer: &mut dyn FnMut(usize, &PackedForest),
+) -> PackedForest {
+    let mut last = None;
+
+    for (minute, forest) in self::minutes(input).enumerate().take(minutes + 1) {
+        observer(minute, &forest);
+        last = Some(forest);
+    }
+
+    last.unwrap()
+}
+
+// Every forest up to the point where it starts repeating, which is enough to
+// see the whole of the oscillation.
+pub fn until_repeat(input: &str) -> Vec<PackedForest> {
+    cycle::detect(minutes(input), |forest| forest.clone())
+        .expect("Minutes never run out")
+        .0
+}
+
+// The resources over time as CSV, ready for plotting
+pub fn resource_table(input: &str) -> String {
+    let mut table = String::from("minute,wooded,lumberyards,resource_value\n");
+
+    for (minute, forest) in until_repeat(input).iter().enumerate() {
+        table.push_str(&format!(
+            "{},{},{},{}\n",
+            minute,
+            forest.wooded(),
+            forest.lumberyards(),
+            forest.resource_value()
+        ));
+    }
+
+    table
+}
+
+fn parse_packed(input: &str) -> PackedForest {
+    PackedForest::new(&parse(input).unwrap_or_else(|e| panic!("{}", e)))
+}
+
+pub fn simulate(input: &str, minutes: usize) -> usize {
+    let mut forest = parse_packed(input);
+
+    for _ in 0..minutes {
+        forest = forest.minute();
+    }
+
+    forest.resource_value()
 }
 
-pub fn star_two(input: &str) -> i64 {
-    0
+pub fn simulate_naive(input: &str, minutes: usize) -> usize {
+    let mut grid = parse(input).unwrap_or_else(|e| panic!("{}", e));
+
+    for _ in 0..minutes {
+        grid = minute(&grid);
+    }
+
+    resource_value(&grid)
+}
+
+pub fn star_one(input: &str) -> usize {
+    simulate(input, 10)
+}
+
+pub fn star_one_naive(input: &str) -> usize {
+    simulate_naive(input, 10)
+}
+
+// The forest settles into a loop after a few hundred minutes at most, from
+// then on the state after any number of minutes is one that's been seen.
+pub fn resource_value_after(input: &str, minutes: usize) -> usize {
+    let (history, cycle) = cycle::detect(self::minutes(input), |forest| forest.clone())
+        .expect("Minutes never run out");
+
+    history[cycle.index(minutes)].resource_value()
+}
+
+pub fn star_two(input: &str) -> usize {
+    resource_value_after(input, 1_000_000_000)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        minute, minutes, observe, parse, resource_table, resource_value, resource_value_after,
+        resources, simulate, simulate_naive, star_one, star_one_naive, star_two, until_repeat,
+        PackedForest, ParseError,
+    };
+    use differential::Rng;
+    use snapshot::assert_snapshot;
+
+    static EXAMPLE: &str = "
+.#.#...|#.
+.....#|##|
+.|..|...#.
+..|#.....#
+#.#|||#|#|
+...#.||...
+.|....|...
+||...#|.#|
+|.||||..|.
+...#.|..|.";
 
     #[test]
     fn test_star_one() {
-        assert_eq!(star_one(""), 1)
+        assert_eq!(star_one(EXAMPLE), 1147);
+        assert_eq!(star_one_naive(EXAMPLE), 1147);
     }
 
     #[test]
     fn test_star_two() {
-        assert_eq!(star_two(""), 1)
+        // The example runs out of trees and stays that way
+        assert_eq!(star_two(EXAMPLE), 0)
+    }
+
+    #[test]
+    fn test_resource_value_after() {
+        assert_eq!(resource_value_after(EXAMPLE, 10), 1147);
+
+        // A random forest keeps oscillating long after the example has died out
+        let mut rng = Rng::new(2018);
+        let forest = (0..50)
+            .map(|_| {
+                (0..50)
+                    .map(|_| ['.', '.', '|', '#'][rng.next(4) as usize])
+                    .collect::<String>()
+            }).collect::<Vec<_>>()
+            .join("\n");
+
+        let mut grid = parse(&forest).unwrap();
+        for minutes in 0..=1000 {
+            if minutes % 100 == 0 {
+                assert_eq!(
+                    resource_value_after(&forest, minutes),
+                    resource_value(&grid)
+                );
+            }
+            grid = minute(&grid);
+        }
+        assert!(resource_value(&grid) > 0);
+    }
+
+    #[test]
+    fn test_minutes_snapshot() {
+        let forests = minutes(EXAMPLE)
+            .take(11)
+            .enumerate()
+            .map(|(minute, forest)| format!("After {} minutes:\n{}", minute, forest))
+            .collect::<Vec<_>>();
+
+        assert_snapshot("day18_example_minutes", &forests.join("\n\n"));
+    }
+
+    #[test]
+    fn test_minute() {
+        let expected = parse(
+            "
+.......##.
+......|###
+.|..|...#.
+..|#||...#
+..##||.|#|
+...#||||..
+||...|||..
+|||||.||.|
+||||||||||
+....||..|.",
+        )
+        .unwrap();
+
+        assert_eq!(minute(&parse(EXAMPLE).unwrap()), expected);
+    }
+
+    #[test]
+    fn test_packed_minute() {
+        // Wide enough to span three words per row, with trees and lumberyards
+        // next to the word boundaries.
+        let mut rng = Rng::new(18);
+        let forest = (0..20)
+            .map(|_| {
+                (0..70)
+                    .map(|_| ['.', '|', '|', '#'][rng.next(4) as usize])
+                    .collect::<String>()
+            }).collect::<Vec<_>>()
+            .join("\n");
+
+        let mut grid = parse(&forest).unwrap();
+        let mut packed = PackedForest::new(&grid);
+        for _ in 0..50 {
+            grid = minute(&grid);
+            packed = packed.minute();
+
+            assert_eq!(packed, PackedForest::new(&grid));
+        }
+
+        for minutes in 0..20 {
+            assert_eq!(simulate(EXAMPLE, minutes), simulate_naive(EXAMPLE, minutes));
+        }
+    }
+
+    #[test]
+    fn test_resources() {
+        let resources = resources(EXAMPLE).take(11).collect::<Vec<_>>();
+
+        assert_eq!(resources[0], (27, 17, 27 * 17));
+        assert_eq!(resources[10], (37, 31, 1147));
+        assert!(resources
+            .iter()
+            .enumerate()
+            .all(|(minute, &(_, _, value))| value == simulate_naive(EXAMPLE, minute)));
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut rendered = vec![];
+        let last = observe(EXAMPLE, 10, &mut |minute, forest| {
+            rendered.push((minute, forest.to_string()))
+        });
+
+        assert_eq!(rendered.len(), 11);
+        assert_eq!(rendered[0], (0, EXAMPLE.trim().to_owned()));
+        assert_eq!(rendered[10].1.lines().next(), Some(".||##....."));
+        assert_eq!(last.resource_value(), 1147);
+        assert_eq!(minutes(EXAMPLE).nth(10), Some(last));
+    }
+
+    #[test]
+    fn test_resource_table() {
+        let table = resource_table(EXAMPLE);
+        let mut lines = table.lines();
+
+        assert_eq!(
+            lines.next(),
+            Some("minute,wooded,lumberyards,resource_value")
+        );
+        assert_eq!(lines.next(), Some("0,27,17,459"));
+        assert_eq!(table.lines().count(), until_repeat(EXAMPLE).len() + 1);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse(".|#\n.x#"),
+            Err(ParseError::UnknownCharacter {
+                location: (1, 1),
+                character: 'x',
+            })
+        );
+        assert_eq!(
+            parse("..\n...").unwrap_err().to_string(),
+            "Row 1 is not as wide as the first one"
+        );
     }
 }
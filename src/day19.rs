@@ -1,22 +1,640 @@
-pub fn star_one(input: &str) -> i64 {
-    0
+use std::collections::HashSet;
+use std::fmt;
+
+use elfcode::{Instruction, Machine, Opcode, Program, Register};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Strategy {
+    // Executes every instruction, which takes forever for part two
+    Interpret,
+    // Finds the divisor sum loop at the heart of the program and computes
+    // what it would have, interpreting anything else.
+    Recognise,
+}
+
+// The loop from the puzzle input at the heart of the background process,
+// for every pair of `outer` and `inner` it does
+//
+//   if outer * inner == target { sum += outer }
+//
+// which sums the divisors of `target`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct DivisorSum {
+    start: usize,
+    outer: usize,
+    target: usize,
+    sum: usize,
+}
+
+fn parse(input: &str) -> Program {
+    input.parse().unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn operands(instruction: &Instruction, opcode: Opcode) -> Option<(usize, usize, usize)> {
+    if instruction.opcode == opcode {
+        Some((instruction.a, instruction.b, instruction.c))
+    } else {
+        None
+    }
+}
+
+// The other register of a commutative instruction that uses `register`
+fn one_of(pair: (usize, usize), register: usize) -> Option<usize> {
+    if pair.0 == register {
+        Some(pair.1)
+    } else if pair.1 == register {
+        Some(pair.0)
+    } else {
+        None
+    }
+}
+
+// Looks for the body of the inner loop
+//
+//   mulr outer inner product
+//   eqrr product target product
+//   addr product ip ip
+//   addi ip 1 ip
+//   addr outer sum sum
+fn recognise(program: &Program) -> Option<DivisorSum> {
+    let ip = program.ip_register;
+
+    program
+        .instructions
+        .windows(5)
+        .enumerate()
+        .find_map(|(start, window)| {
+            let (outer, inner, product) = operands(&window[0], Opcode::Mulr)?;
+            let (a, b, c) = operands(&window[1], Opcode::Eqrr)?;
+            let target = one_of((a, b), product).filter(|_| c == product)?;
+            let (a, b, c) = operands(&window[2], Opcode::Addr)?;
+            one_of((a, b), product).filter(|&other| other == ip && c == ip)?;
+            let (a, b, c) = operands(&window[3], Opcode::Addi)?;
+            if (a, b, c) != (ip, 1, ip) {
+                return None;
+            }
+            let (a, b, sum) = operands(&window[4], Opcode::Addr)?;
+            one_of((a, b), sum).filter(|&other| other == outer)?;
+
+            let registers = [outer, inner, product, target, sum];
+            if registers.contains(&ip) || outer == inner || target == product || sum == outer {
+                return None;
+            }
+
+            Some(DivisorSum {
+                start,
+                outer,
+                target,
+                sum,
+            })
+        })
+}
+
+fn sum_of_divisors(number: Register) -> Register {
+    (1..)
+        .take_while(|divisor| divisor * divisor <= number)
+        .filter(|divisor| number % divisor == 0)
+        .map(|divisor| {
+            let other = number / divisor;
+
+            if other == divisor {
+                divisor
+            } else {
+                divisor + other
+            }
+        }).sum()
+}
+
+// Runs the program with register 0 set to `first_register`. When recognising
+// the program is interpreted up to the first time it enters the divisor
+// loop, which only happens once the target has been computed. The program
+// halts as soon as the loop is done so that's where it stops.
+pub fn run(input: &str, first_register: Register, strategy: Strategy) -> Register {
+    let program = parse(input);
+    let mut machine = Machine::new(6);
+    machine[0] = first_register;
+
+    match (strategy, recognise(&program)) {
+        (Strategy::Recognise, Some(divisor_sum)) => {
+            let stopped = machine.run_bound_until(&program, &mut |ip, _| ip == divisor_sum.start);
+
+            if stopped.is_some() {
+                machine[divisor_sum.sum] += sum_of_divisors(machine[divisor_sum.target]);
+            }
+        }
+        _ => {
+            machine.run_bound(&program);
+        }
+    }
+
+    machine[0]
+}
+
+// An operand as it reads in the disassembly, the instruction pointer always
+// holds the index of the instruction being executed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Value {
+    Register(usize),
+    Constant(Register),
+}
+
+impl Value {
+    fn register(register: usize, ip_register: usize, idx: usize) -> Self {
+        if register == ip_register {
+            Value::Constant(idx as Register)
+        } else {
+            Value::Register(register)
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Register(register) => write!(f, "r{}", register),
+            Value::Constant(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// The operands the way the instruction reads them, `None` for an unused
+// second operand.
+fn values(instruction: &Instruction, ip_register: usize, idx: usize) -> (Value, Option<Value>) {
+    let register = |register| Value::register(register, ip_register, idx);
+    let immediate = |value| Value::Constant(value as Register);
+
+    match instruction.opcode {
+        Opcode::Addr | Opcode::Mulr | Opcode::Banr | Opcode::Borr | Opcode::Gtrr | Opcode::Eqrr => {
+            (register(instruction.a), Some(register(instruction.b)))
+        }
+        Opcode::Addi | Opcode::Muli | Opcode::Bani | Opcode::Bori | Opcode::Gtri | Opcode::Eqri => {
+            (register(instruction.a), Some(immediate(instruction.b)))
+        }
+        Opcode::Gtir | Opcode::Eqir => (immediate(instruction.a), Some(register(instruction.b))),
+        Opcode::Setr => (register(instruction.a), None),
+        Opcode::Seti => (immediate(instruction.a), None),
+    }
+}
+
+// What the instruction computes, folded into a constant when both operands
+// are known.
+fn expression(instruction: &Instruction, ip_register: usize, idx: usize) -> Value {
+    let (a, b) = values(instruction, ip_register, idx);
+    let folded = match (a, b) {
+        (Value::Constant(a), None) => Some(a),
+        (Value::Constant(a), Some(Value::Constant(b))) => {
+            // Both operands are known, so whatever they were they can be
+            // read from registers.
+            let opcode = match instruction.opcode {
+                Opcode::Addi => Opcode::Addr,
+                Opcode::Muli => Opcode::Mulr,
+                Opcode::Bani => Opcode::Banr,
+                Opcode::Bori => Opcode::Borr,
+                Opcode::Gtir | Opcode::Gtri => Opcode::Gtrr,
+                Opcode::Eqir | Opcode::Eqri => Opcode::Eqrr,
+                opcode => opcode,
+            };
+            let mut machine = Machine::new(3);
+            machine.set_register_state(&[a, b, 0]);
+            machine.execute(&Instruction::new(opcode, 0, 1, 2));
+
+            Some(machine[2])
+        }
+        _ => None,
+    };
+
+    folded.map_or(Value::Register(instruction.c), Value::Constant)
+}
+
+fn pseudocode(instruction: &Instruction, ip_register: usize, idx: usize) -> String {
+    let (a, b) = values(instruction, ip_register, idx);
+    let operator = match instruction.opcode {
+        Opcode::Addr | Opcode::Addi => "+",
+        Opcode::Mulr | Opcode::Muli => "*",
+        Opcode::Banr | Opcode::Bani => "&",
+        Opcode::Borr | Opcode::Bori => "|",
+        Opcode::Gtir | Opcode::Gtri | Opcode::Gtrr => ">",
+        Opcode::Eqir | Opcode::Eqri | Opcode::Eqrr => "==",
+        Opcode::Setr | Opcode::Seti => "",
+    };
+    let computed = match expression(instruction, ip_register, idx) {
+        Value::Constant(value) => value.to_string(),
+        Value::Register(_) => match b {
+            Some(b) => format!("{} {} {}", a, operator, b),
+            None => a.to_string(),
+        },
+    };
+
+    if instruction.c != ip_register {
+        return format!("r{} = {}", instruction.c, computed);
+    }
+
+    // Writing the instruction pointer is a jump to one past the value
+    let jump = expression(instruction, ip_register, idx);
+    match (jump, instruction.opcode, a, b) {
+        (Value::Constant(target), _, _, _) => format!("goto {}", target + 1),
+        (_, Opcode::Addr, Value::Constant(base), Some(Value::Register(offset)))
+        | (_, Opcode::Addr, Value::Register(offset), Some(Value::Constant(base))) => {
+            format!("goto {} + r{}", base + 1, offset)
+        }
+        _ => format!("goto {} + 1", computed),
+    }
+}
+
+// Where control can go after the instruction, other than the next one
+fn jump_targets(program: &Program, idx: usize) -> Vec<usize> {
+    let instruction = &program.instructions[idx];
+    if instruction.c != program.ip_register {
+        return vec![];
+    }
+
+    match expression(instruction, program.ip_register, idx) {
+        Value::Constant(target) if target >= 0 => vec![target as usize + 1],
+        // Skipping ahead by the result of a comparison, either 0 or 1
+        Value::Register(_) if instruction.opcode == Opcode::Addr => {
+            let compared = idx > 0 && {
+                let previous = &program.instructions[idx - 1];
+                let comparisons = [
+                    Opcode::Gtir,
+                    Opcode::Gtri,
+                    Opcode::Gtrr,
+                    Opcode::Eqir,
+                    Opcode::Eqri,
+                    Opcode::Eqrr,
+                ];
+
+                comparisons.contains(&previous.opcode)
+                    && (previous.c == instruction.a || previous.c == instruction.b)
+            };
+
+            if compared {
+                vec![idx + 1, idx + 2]
+            } else {
+                vec![]
+            }
+        }
+        _ => vec![],
+    }
 }
 
-pub fn star_two(input: &str) -> i64 {
-    0
+// Where control can go after the instruction, as far as can be told
+fn successors(program: &Program, idx: usize) -> Vec<usize> {
+    let successors = if program.instructions[idx].c == program.ip_register {
+        jump_targets(program, idx)
+    } else {
+        vec![idx + 1]
+    };
+
+    successors
+        .into_iter()
+        .filter(|&next| next < program.instructions.len())
+        .collect()
+}
+
+fn reachable(program: &Program, from: usize, to: usize) -> bool {
+    let mut seen = HashSet::new();
+    let mut pending = vec![from];
+
+    while let Some(idx) = pending.pop() {
+        if idx == to {
+            return true;
+        }
+        if seen.insert(idx) {
+            pending.extend(successors(program, idx));
+        }
+    }
+
+    false
+}
+
+// The first and last instruction of every loop, formed by a jump backwards
+// to a place that leads back to the jump. Jumps back that are never reached
+// from where they go to, like the return from the setup code at the end of
+// the program, aren't loops.
+fn loops(program: &Program) -> Vec<(usize, usize)> {
+    let mut loops = (0..program.instructions.len())
+        .flat_map(|idx| {
+            jump_targets(program, idx)
+                .into_iter()
+                .filter(move |&target| target <= idx)
+                .map(move |target| (target, idx))
+        }).filter(|&(start, end)| reachable(program, start, end))
+        .collect::<Vec<_>>();
+    loops.sort_by_key(|&(start, end)| (start, !end));
+    loops.dedup();
+
+    loops
+}
+
+// Whether loop `inner` is nested in loop `outer`
+fn contains(outer: (usize, usize), inner: (usize, usize)) -> bool {
+    outer != inner && outer.0 <= inner.0 && inner.1 <= outer.1
+}
+
+// Describes a loop by whether it's nested and the registers it counts with,
+// which are the ones incremented by one outside of any nested loop.
+fn describe_loop(
+    program: &Program,
+    loops: &[(usize, usize)],
+    (start, end): (usize, usize),
+) -> String {
+    let kind = if loops.iter().any(|&other| contains((start, end), other)) {
+        "outer loop"
+    } else if loops.iter().any(|&other| contains(other, (start, end))) {
+        "inner loop"
+    } else {
+        "loop"
+    };
+    let counters = (start..=end)
+        .filter(|&idx| {
+            !loops
+                .iter()
+                .any(|&other| contains((start, end), other) && other.0 <= idx && idx <= other.1)
+        }).map(|idx| &program.instructions[idx])
+        .filter(|body| {
+            body.opcode == Opcode::Addi
+                && body.a == body.c
+                && body.b == 1
+                && body.c != program.ip_register
+        }).map(|body| format!("r{}", body.c))
+        .collect::<Vec<_>>();
+
+    if counters.is_empty() {
+        format!("{} ({}..={})", kind, start, end)
+    } else {
+        format!(
+            "{} over {} ({}..={})",
+            kind,
+            counters.join(", "),
+            start,
+            end
+        )
+    }
+}
+
+// The program with the instruction pointer's reads replaced by the index of
+// the instruction, jumps written as `goto` and labelled at their targets,
+// and every loop described by whether it's nested and what it counts.
+pub fn disassemble(input: &str) -> String {
+    let program = parse(input);
+    let loops = loops(&program);
+    let targets = (0..program.instructions.len())
+        .flat_map(|idx| jump_targets(&program, idx))
+        .collect::<HashSet<_>>();
+
+    let mut lines = vec![format!(
+        "#ip {} is bound to the instruction pointer",
+        program.ip_register
+    )];
+    for (idx, instruction) in program.instructions.iter().enumerate() {
+        let depth = loops
+            .iter()
+            .filter(|&&(start, end)| start <= idx && idx <= end)
+            .count();
+
+        for &l in loops.iter().filter(|&&(start, _)| start == idx) {
+            lines.push(format!(
+                "{:8}{}# {}",
+                "",
+                "  ".repeat(depth - 1),
+                describe_loop(&program, &loops, l)
+            ));
+        }
+
+        let label = if targets.contains(&idx) {
+            format!("L{}:", idx)
+        } else {
+            String::new()
+        };
+        let code = format!(
+            "{}{}",
+            "  ".repeat(depth),
+            pseudocode(instruction, program.ip_register, idx)
+        );
+        let halts = jump_targets(&program, idx)
+            .iter()
+            .any(|&target| target >= program.instructions.len());
+
+        lines.push(format!(
+            "{:<5}{:>3} {:<32}; {}{}",
+            label,
+            idx,
+            code,
+            instruction,
+            if halts { ", halts" } else { "" }
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MachineState {
+    pub registers: Vec<Register>,
+    pub instructions_executed: usize,
+}
+
+// Interprets the program from the given registers until it halts, there are
+// as many registers as there are initial values.
+pub fn run_program(input: &str, initial_registers: &[Register]) -> MachineState {
+    let program = parse(input);
+    assert!(
+        program.ip_register < initial_registers.len(),
+        "The instruction pointer is bound to register {} but there are only {} registers",
+        program.ip_register,
+        initial_registers.len()
+    );
+    assert!(
+        program
+            .instructions
+            .iter()
+            .all(|instruction| instruction.fits(initial_registers.len())),
+        "The program uses more than {} registers",
+        initial_registers.len()
+    );
+
+    let mut machine = Machine::new(initial_registers.len());
+    machine.set_register_state(initial_registers);
+    let instructions_executed = machine.run_bound(&program);
+
+    MachineState {
+        registers: machine.registers().to_vec(),
+        instructions_executed,
+    }
+}
+
+pub fn star_one(input: &str) -> Register {
+    run(input, 0, Strategy::Recognise)
+}
+
+pub fn star_two(input: &str) -> Register {
+    run(input, 1, Strategy::Recognise)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        disassemble, parse, recognise, run, run_program, star_one, star_two, sum_of_divisors,
+        DivisorSum, MachineState, Strategy,
+    };
+
+    static EXAMPLE: &str = "
+#ip 0
+seti 5 0 1
+seti 6 0 2
+addi 0 1 0
+addr 1 2 3
+setr 1 0 0
+seti 8 0 4
+seti 9 0 5";
+
+    // The shape every puzzle input has, computes 976 in register 4 for part
+    // one and 10551376 for part two.
+    static BACKGROUND: &str = "
+#ip 3
+addi 3 16 3
+seti 1 8 1
+seti 1 3 2
+mulr 1 2 5
+eqrr 5 4 5
+addr 5 3 3
+addi 3 1 3
+addr 1 0 0
+addi 2 1 2
+gtrr 2 4 5
+addr 3 5 3
+seti 2 6 3
+addi 1 1 1
+gtrr 1 4 5
+addr 5 3 3
+seti 1 1 3
+mulr 3 3 3
+addi 4 2 4
+mulr 4 4 4
+mulr 3 4 4
+muli 4 11 4
+addi 5 6 5
+mulr 5 3 5
+addi 5 8 5
+addr 4 5 4
+addr 3 0 3
+seti 0 3 3
+setr 3 4 5
+mulr 5 3 5
+addr 3 5 5
+mulr 3 5 5
+muli 5 14 5
+mulr 5 3 5
+addr 4 5 4
+seti 0 3 0
+seti 0 0 3";
 
     #[test]
     fn test_star_one() {
-        assert_eq!(star_one(""), 1)
+        assert_eq!(star_one(EXAMPLE), 6);
+        assert_eq!(
+            star_one(BACKGROUND),
+            1 + 2 + 4 + 8 + 16 + 61 + 122 + 244 + 488 + 976
+        );
+        assert_eq!(
+            run(BACKGROUND, 0, Strategy::Interpret),
+            run(BACKGROUND, 0, Strategy::Recognise)
+        );
     }
 
     #[test]
     fn test_star_two() {
-        assert_eq!(star_two(""), 1)
+        let brute_force = (1..=10551376).filter(|d| 10551376 % d == 0).sum::<i64>();
+
+        assert_eq!(star_two(BACKGROUND), brute_force);
+    }
+
+    #[test]
+    fn test_recognise() {
+        assert_eq!(
+            recognise(&parse(BACKGROUND)),
+            Some(DivisorSum {
+                start: 3,
+                outer: 1,
+                target: 4,
+                sum: 0,
+            })
+        );
+        assert_eq!(recognise(&parse(EXAMPLE)), None);
+        assert_eq!(
+            run(EXAMPLE, 0, Strategy::Recognise),
+            run(EXAMPLE, 0, Strategy::Interpret)
+        );
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let disassembly = disassemble(BACKGROUND);
+        let lines = disassembly.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "#ip 3 is bound to the instruction pointer");
+        assert_eq!(
+            &lines[1..10],
+            &[
+                "       0 goto 17                         ; addi 3 16 3",
+                "L1:    1 r1 = 1                          ; seti 1 8 1",
+                "        # outer loop over r1 (2..=15)",
+                "L2:    2   r2 = 1                        ; seti 1 3 2",
+                "          # inner loop over r2 (3..=11)",
+                "L3:    3     r5 = r1 * r2                ; mulr 1 2 5",
+                "       4     r5 = r5 == r4               ; eqrr 5 4 5",
+                "       5     goto 6 + r5                 ; addr 5 3 3",
+                "L6:    6     goto 8                      ; addi 3 1 3",
+            ]
+        );
+        assert_eq!(
+            lines[19],
+            "L16:  16 goto 257                        ; mulr 3 3 3, halts"
+        );
+        assert_eq!(
+            lines[22],
+            "      19 r4 = 19 * r4                    ; mulr 3 4 4"
+        );
+        // The jump back from the setup code doesn't make a loop
+        assert_eq!(lines.iter().filter(|line| line.contains('#')).count(), 3);
+
+        assert_eq!(
+            disassemble(EXAMPLE).lines().nth(5),
+            Some("L4:    4 goto r1 + 1                     ; setr 1 0 0")
+        );
+    }
+
+    #[test]
+    fn test_run_program() {
+        assert_eq!(
+            run_program(EXAMPLE, &[0; 6]),
+            MachineState {
+                registers: vec![6, 5, 6, 0, 0, 9],
+                instructions_executed: 5,
+            }
+        );
+
+        // The divisor loop runs once for every pair of numbers up to the
+        // target
+        let state = run_program(BACKGROUND, &[0; 6]);
+        assert_eq!(state.registers[0], star_one(BACKGROUND));
+        assert_eq!(state.registers[4], 976);
+        assert!(state.instructions_executed > 976 * 976);
+    }
+
+    #[test]
+    #[should_panic(expected = "The program uses more than 4 registers")]
+    fn test_run_program_too_few_registers() {
+        run_program(BACKGROUND, &[0; 4]);
+    }
+
+    #[test]
+    fn test_sum_of_divisors() {
+        for number in 1..500 {
+            assert_eq!(
+                sum_of_divisors(number),
+                (1..=number).filter(|d| number % d == 0).sum::<i64>()
+            );
+        }
     }
 }
@@ -1,22 +1,375 @@
-pub fn star_one(input: &str) -> i64 {
-    0
+use std::collections::HashSet;
+
+use elfcode::{Instruction, Machine, Opcode, Program, Register};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Strategy {
+    // Executes every instruction, collecting every value takes minutes
+    Interpret,
+    // Computes the result of the division loop instead of running it
+    Accelerate,
+}
+
+// The loop the programs divide with, one subtraction at a time
+//
+//   head: test = quotient + 1
+//         test *= divisor
+//         test = test > dividend
+//         goto head + 4 + test
+//         goto head + 6
+//         exit - 1
+//         quotient += 1
+//         goto head
+//   exit:
+//
+// it leaves the smallest quotient at or above the current one where
+// `(quotient + 1) * divisor > dividend`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct DivisionLoop {
+    head: usize,
+    exit: usize,
+    quotient: usize,
+    test: usize,
+    divisor: Register,
+    dividend: usize,
+}
+
+fn parse(input: &str) -> Program {
+    input.parse().unwrap_or_else(|e| panic!("{}", e))
+}
+
+// Register 0 is only ever read by a single `eqrr` that halts the program
+// when it's equal to whatever is in the other register. Returns where that
+// comparison is and which register it compares against.
+fn halting_check(program: &Program) -> (usize, usize) {
+    program
+        .instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, instruction)| instruction.opcode == Opcode::Eqrr)
+        .find_map(|(idx, instruction)| match (instruction.a, instruction.b) {
+            (0, register) | (register, 0) if register != 0 => Some((idx, register)),
+            _ => None,
+        }).expect("Expected the program to compare a register with register 0")
+}
+
+fn is(instruction: &Instruction, opcode: Opcode, a: usize, b: usize, c: usize) -> bool {
+    *instruction == Instruction::new(opcode, a, b, c)
+}
+
+fn division_loop(program: &Program) -> Option<DivisionLoop> {
+    let ip = program.ip_register;
+
+    program
+        .instructions
+        .windows(8)
+        .enumerate()
+        .find_map(|(head, window)| {
+            let (quotient, test) = (window[0].a, window[0].c);
+            let divisor = window[1].b;
+            let dividend = window[2].b;
+            let exit = window[5].a + 1;
+
+            let matches = is(&window[0], Opcode::Addi, quotient, 1, test)
+                && is(&window[1], Opcode::Muli, test, divisor, test)
+                && is(&window[2], Opcode::Gtrr, test, dividend, test)
+                && (is(&window[3], Opcode::Addr, test, ip, ip)
+                    || is(&window[3], Opcode::Addr, ip, test, ip))
+                && is(&window[4], Opcode::Addi, ip, 1, ip)
+                && window[5].opcode == Opcode::Seti
+                && window[5].c == ip
+                && is(&window[6], Opcode::Addi, quotient, 1, quotient)
+                && window[7].opcode == Opcode::Seti
+                && window[7].c == ip
+                && window[7].a + 1 == head
+                && divisor > 0;
+            let distinct = [quotient, test, dividend, ip]
+                .iter()
+                .collect::<HashSet<_>>()
+                .len()
+                == 4;
+
+            if matches && distinct {
+                Some(DivisionLoop {
+                    head,
+                    exit,
+                    quotient,
+                    test,
+                    divisor: divisor as Register,
+                    dividend,
+                })
+            } else {
+                None
+            }
+        })
+}
+
+// Every value register 0 is compared with, in order, for as long as the
+// program runs.
+struct Comparisons {
+    program: Program,
+    check: usize,
+    register: usize,
+    division: Option<DivisionLoop>,
+    machine: Machine,
+    ip: Option<usize>,
+}
+
+impl Iterator for Comparisons {
+    type Item = Register;
+
+    fn next(&mut self) -> Option<Register> {
+        while let Some(current) = self.ip {
+            self.ip = match self.division {
+                Some(division) if current == division.head => {
+                    let quotient = self.machine[division.dividend] / division.divisor;
+                    self.machine[division.quotient] = self.machine[division.quotient].max(quotient);
+                    self.machine[division.test] = 1;
+
+                    Some(division.exit)
+                }
+                _ => self.machine.step_bound(&self.program, current),
+            };
+
+            if current == self.check {
+                return Some(self.machine[self.register]);
+            }
+        }
+
+        None
+    }
+}
+
+fn comparisons(program: Program, strategy: Strategy) -> Comparisons {
+    let (check, register) = halting_check(&program);
+    let division = match strategy {
+        Strategy::Interpret => None,
+        Strategy::Accelerate => division_loop(&program),
+    };
+
+    Comparisons {
+        program,
+        check,
+        register,
+        division,
+        machine: Machine::new(6),
+        ip: Some(0),
+    }
+}
+
+// The values that would halt the program, in the order the program gets to
+// them, up to the first one it has already tried.
+pub fn halting_values(input: &str) -> impl Iterator<Item = Register> {
+    let mut seen = HashSet::new();
+
+    comparisons(parse(input), Strategy::Accelerate).take_while(move |&value| seen.insert(value))
+}
+
+// The value that halts the program after the fewest instructions is the
+// first one it's compared with.
+pub fn star_one(input: &str) -> Register {
+    halting_values(input)
+        .next()
+        .expect("The program halted without comparing anything with register 0")
 }
 
-pub fn star_two(input: &str) -> i64 {
-    0
+// The values compared with eventually repeat, after that nothing new can
+// halt the program so the last new value takes the most instructions.
+pub fn star_two(input: &str) -> Register {
+    halting_values(input)
+        .last()
+        .expect("The program halted without comparing anything with register 0")
+}
+
+// The halting values as CSV, ready for plotting
+pub fn value_table(input: &str) -> String {
+    let mut table = String::from("index,value\n");
+
+    for (idx, value) in halting_values(input).enumerate() {
+        table.push_str(&format!("{},{}\n", idx, value));
+    }
+
+    table
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        comparisons, division_loop, halting_values, parse, star_one, star_two, value_table,
+        DivisionLoop, Strategy,
+    };
+
+    // A 24 bit hash of the previous value, shifted a byte at a time by
+    // repeated subtraction.
+    static PROGRAM: &str = "
+#ip 4
+seti 123 0 3
+bani 3 456 3
+eqri 3 72 3
+addr 3 4 4
+seti 0 0 4
+seti 0 3 3
+bori 3 65536 2
+seti 4843319 1 3
+bani 2 255 5
+addr 3 5 3
+bani 3 16777215 3
+muli 3 65899 3
+bani 3 16777215 3
+gtir 256 2 5
+addr 5 4 4
+addi 4 1 4
+seti 27 4 4
+seti 0 2 5
+addi 5 1 1
+muli 1 256 1
+gtrr 1 2 1
+addr 1 4 4
+addi 4 1 4
+seti 25 1 4
+addi 5 1 5
+seti 17 8 4
+setr 5 2 2
+seti 7 4 4
+eqrr 3 0 5
+addr 5 4 4
+seti 5 5 4";
+
+    // The same program hashing into a single byte, which cycles quickly
+    static SMALL: &str = "
+#ip 4
+seti 123 0 3
+bani 3 456 3
+eqri 3 72 3
+addr 3 4 4
+seti 0 0 4
+seti 0 3 3
+bori 3 256 2
+seti 43 1 3
+bani 2 255 5
+addr 3 5 3
+bani 3 255 3
+muli 3 37 3
+bani 3 255 3
+gtir 256 2 5
+addr 5 4 4
+addi 4 1 4
+seti 27 4 4
+seti 0 2 5
+addi 5 1 1
+muli 1 256 1
+gtrr 1 2 1
+addr 1 4 4
+addi 4 1 4
+seti 25 1 4
+addi 5 1 5
+seti 17 8 4
+setr 5 2 2
+seti 7 4 4
+eqrr 0 3 5
+addr 5 4 4
+seti 5 5 4";
+
+    // What the programs compute, written out
+    fn hash(previous: i64, seed: i64, multiplier: i64, mask: i64, high: i64) -> i64 {
+        let mut bytes = previous | high;
+        let mut value = seed;
+
+        loop {
+            value = (((value + (bytes & 255)) & mask) * multiplier) & mask;
+            if bytes < 256 {
+                return value;
+            }
+            bytes /= 256;
+        }
+    }
 
     #[test]
     fn test_star_one() {
-        assert_eq!(star_one(""), 1)
+        assert_eq!(star_one(PROGRAM), hash(0, 4843319, 65899, 16777215, 65536));
+        assert_eq!(star_one(SMALL), hash(0, 43, 37, 255, 256));
     }
 
     #[test]
     fn test_star_two() {
-        assert_eq!(star_two(""), 1)
+        let mut seen = vec![];
+        let mut value = 0;
+        loop {
+            value = hash(value, 43, 37, 255, 256);
+            if seen.contains(&value) {
+                break;
+            }
+            seen.push(value);
+        }
+
+        assert!(seen.len() > 10);
+        assert_eq!(star_two(SMALL), *seen.last().unwrap());
+
+        let mut seen = vec![];
+        let mut value = 0;
+        loop {
+            value = hash(value, 4843319, 65899, 16777215, 65536);
+            if seen.contains(&value) {
+                break;
+            }
+            seen.push(value);
+        }
+
+        assert_eq!(star_two(PROGRAM), *seen.last().unwrap());
+    }
+
+    #[test]
+    fn test_division_loop() {
+        assert_eq!(
+            division_loop(&parse(PROGRAM)),
+            Some(DivisionLoop {
+                head: 18,
+                exit: 26,
+                quotient: 5,
+                test: 1,
+                divisor: 256,
+                dividend: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_accelerated_matches_interpreted() {
+        let values = |input: &str, strategy: Strategy, count: usize| {
+            comparisons(parse(input), strategy)
+                .take(count)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            values(PROGRAM, Strategy::Accelerate, 20),
+            values(PROGRAM, Strategy::Interpret, 20)
+        );
+        assert_eq!(
+            values(SMALL, Strategy::Accelerate, 300),
+            values(SMALL, Strategy::Interpret, 300)
+        );
+    }
+
+    #[test]
+    fn test_halting_values() {
+        let values = halting_values(SMALL).collect::<Vec<_>>();
+
+        assert_eq!(values[0], star_one(SMALL));
+        assert_eq!(values.last(), Some(&star_two(SMALL)));
+        assert_eq!(
+            comparisons(parse(SMALL), Strategy::Interpret)
+                .take(values.len())
+                .collect::<Vec<_>>(),
+            values
+        );
+
+        let table = value_table(SMALL);
+        assert_eq!(table.lines().next(), Some("index,value"));
+        assert_eq!(
+            table.lines().nth(1),
+            Some(format!("0,{}", values[0]).as_str())
+        );
+        assert_eq!(table.lines().count(), values.len() + 1);
     }
 }
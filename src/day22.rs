@@ -1,22 +1,597 @@
-pub fn star_one(input: &str) -> i64 {
-    0
+use std::fmt;
+use std::str::FromStr;
+
+use search;
+use viz::{self, Color};
+
+const MOUTH: (usize, usize) = (0, 0);
+const EROSION_MODULO: usize = 20183;
+const MOVE_TIME: usize = 1;
+const SWITCH_TIME: usize = 7;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Tool {
+    Torch,
+    ClimbingGear,
+    Neither,
+}
+
+impl Tool {
+    fn to_color(self) -> Color {
+        match self {
+            Tool::Torch => (250, 170, 30),
+            Tool::ClimbingGear => (220, 40, 40),
+            Tool::Neither => (250, 250, 250),
+        }
+    }
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tool::Torch => write!(f, "the torch"),
+            Tool::ClimbingGear => write!(f, "the climbing gear"),
+            Tool::Neither => write!(f, "neither"),
+        }
+    }
+}
+
+// Where the rescue is, what's equipped
+type State = (usize, usize, Tool);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Region {
+    Rocky,
+    Wet,
+    Narrow,
+}
+
+impl Region {
+    fn from_erosion(erosion: usize) -> Self {
+        match erosion % 3 {
+            0 => Region::Rocky,
+            1 => Region::Wet,
+            _ => Region::Narrow,
+        }
+    }
+
+    fn risk_level(self) -> usize {
+        match self {
+            Region::Rocky => 0,
+            Region::Wet => 1,
+            Region::Narrow => 2,
+        }
+    }
+
+    fn tools(self) -> [Tool; 2] {
+        match self {
+            Region::Rocky => [Tool::ClimbingGear, Tool::Torch],
+            Region::Wet => [Tool::ClimbingGear, Tool::Neither],
+            Region::Narrow => [Tool::Torch, Tool::Neither],
+        }
+    }
+
+    fn allows(self, tool: Tool) -> bool {
+        self.tools().contains(&tool)
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Region::Rocky => '.',
+            Region::Wet => '=',
+            Region::Narrow => '|',
+        }
+    }
+
+    fn to_color(self) -> Color {
+        match self {
+            Region::Rocky => (120, 110, 100),
+            Region::Wet => (40, 80, 160),
+            Region::Narrow => (60, 50, 40),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    InvalidLine(String),
+    MissingDepth,
+    MissingTarget,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidLine(line) => write!(f, "Invalid scan line `{}`", line),
+            ParseError::MissingDepth => write!(f, "The scan has no depth"),
+            ParseError::MissingTarget => write!(f, "The scan has no target"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct Scan {
+    depth: usize,
+    target: (usize, usize),
+}
+
+impl FromStr for Scan {
+    type Err = ParseError;
+
+    // `depth: 510` followed by `target: 10,10`
+    fn from_str(input: &str) -> Result<Self, ParseError> {
+        let mut depth = None;
+        let mut target = None;
+
+        for line in input
+            .lines()
+            .map(|line| line.trim())
+            .filter(|l| !l.is_empty())
+        {
+            let invalid = || ParseError::InvalidLine(line.to_owned());
+            let mut parts = line.splitn(2, ':').map(|part| part.trim());
+
+            match (parts.next(), parts.next()) {
+                (Some("depth"), Some(value)) => {
+                    depth = Some(value.parse().map_err(|_| invalid())?);
+                }
+                (Some("target"), Some(value)) => {
+                    let coordinates = value
+                        .split(',')
+                        .map(|coordinate| coordinate.trim().parse::<usize>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| invalid())?;
+
+                    match coordinates[..] {
+                        [x, y] => target = Some((x, y)),
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Self {
+            depth: depth.ok_or(ParseError::MissingDepth)?,
+            target: target.ok_or(ParseError::MissingTarget)?,
+        })
+    }
+}
+
+struct Cave {
+    depth: usize,
+    target: (usize, usize),
+    // Indexed by `y` and then `x`, always a rectangle
+    erosion: Vec<Vec<usize>>,
+}
+
+impl Cave {
+    // Starts out with the rectangle from the mouth to the target mapped
+    fn new(scan: &Scan) -> Self {
+        let mut cave = Self {
+            depth: scan.depth,
+            target: scan.target,
+            erosion: vec![],
+        };
+        cave.grow(scan.target.0 + 1, scan.target.1 + 1);
+
+        cave
+    }
+
+    fn width(&self) -> usize {
+        self.erosion.first().map_or(0, |row| row.len())
+    }
+
+    fn height(&self) -> usize {
+        self.erosion.len()
+    }
+
+    // Maps at least `width` by `height` regions
+    fn grow(&mut self, width: usize, height: usize) {
+        let width = width.max(self.width());
+        let height = height.max(self.height());
+
+        for y in 0..height {
+            if y == self.erosion.len() {
+                self.erosion.push(Vec::with_capacity(width));
+            }
+
+            for x in self.erosion[y].len()..width {
+                let geologic_index = if (x, y) == MOUTH || (x, y) == self.target {
+                    0
+                } else if y == 0 {
+                    x * 16807
+                } else if x == 0 {
+                    y * 48271
+                } else {
+                    self.erosion[y][x - 1] * self.erosion[y - 1][x]
+                };
+
+                self.erosion[y].push((geologic_index + self.depth) % EROSION_MODULO);
+            }
+        }
+    }
+
+    fn region(&self, x: usize, y: usize) -> Region {
+        Region::from_erosion(self.erosion[y][x])
+    }
+
+    // Like `region` but maps more of the cave when `(x, y)` is outside of it,
+    // doubling in size to avoid growing a region at a time.
+    fn explore(&mut self, x: usize, y: usize) -> Region {
+        if x >= self.width() || y >= self.height() {
+            let width = if x >= self.width() {
+                (x + 1).max(self.width() * 2)
+            } else {
+                0
+            };
+            let height = if y >= self.height() {
+                (y + 1).max(self.height() * 2)
+            } else {
+                0
+            };
+
+            self.grow(width, height);
+        }
+
+        self.region(x, y)
+    }
+
+    // Switching to the other tool the region allows or moving to a
+    // neighbouring region that allows the current one.
+    fn moves(&mut self, &(x, y, tool): &State) -> Vec<(State, usize)> {
+        let region = self.explore(x, y);
+        let mut moves = region
+            .tools()
+            .iter()
+            .filter(|&&other| other != tool)
+            .map(|&other| ((x, y, other), SWITCH_TIME))
+            .collect::<Vec<_>>();
+
+        let neighbours = [
+            (x.checked_sub(1), Some(y)),
+            (Some(x + 1), Some(y)),
+            (Some(x), y.checked_sub(1)),
+            (Some(x), Some(y + 1)),
+        ];
+
+        for &neighbour in &neighbours {
+            if let (Some(x), Some(y)) = neighbour {
+                if self.explore(x, y).allows(tool) {
+                    moves.push(((x, y, tool), MOVE_TIME));
+                }
+            }
+        }
+
+        moves
+    }
+
+    fn risk_level(&self) -> usize {
+        (0..=self.target.1)
+            .flat_map(|y| (0..=self.target.0).map(move |x| (x, y)))
+            .map(|(x, y)| self.region(x, y).risk_level())
+            .sum()
+    }
+}
+
+fn manhattan_distance(a: (usize, usize), b: (usize, usize)) -> usize {
+    ((a.0 as isize - b.0 as isize).abs() + (a.1 as isize - b.1 as isize).abs()) as usize
 }
 
-pub fn star_two(input: &str) -> i64 {
-    0
+// A lower bound on the time left, every step takes at least a minute and the
+// torch has to be equipped at the end.
+fn estimate(target: (usize, usize), &(x, y, tool): &State) -> usize {
+    let distance = manhattan_distance((x, y), target);
+
+    if tool == Tool::Torch {
+        distance * MOVE_TIME
+    } else {
+        distance * MOVE_TIME + SWITCH_TIME
+    }
+}
+
+// Where the rescue is at `time` minutes in, a tool switch is two waypoints in
+// the same place.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Waypoint {
+    pub position: (usize, usize),
+    pub tool: Tool,
+    pub time: usize,
+}
+
+// The quickest rescue starts and ends with the torch equipped. Returns the
+// cave as far as it had to be mapped along with the way through it.
+fn rescue(scan: &Scan) -> (Cave, Vec<Waypoint>) {
+    let mut cave = Cave::new(scan);
+    assert!(
+        cave.region(MOUTH.0, MOUTH.1).allows(Tool::Torch),
+        "The mouth and the target are wet, the torch can't be used there"
+    );
+    let start = (MOUTH.0, MOUTH.1, Tool::Torch);
+    let goal = (scan.target.0, scan.target.1, Tool::Torch);
+
+    let path = search::a_star(
+        start,
+        |state| cave.moves(state),
+        |state| estimate(scan.target, state),
+        |&state| state == goal,
+    )
+    .expect("The target is always reachable");
+
+    let waypoints = path
+        .into_iter()
+        .map(|((x, y, tool), time)| Waypoint {
+            position: (x, y),
+            tool,
+            time,
+        }).collect();
+
+    (cave, waypoints)
+}
+
+pub fn rescue_path(input: &str) -> Vec<Waypoint> {
+    rescue(&parse(input)).1
+}
+
+// Every move and tool switch on the way to the target with the time it
+// finishes at.
+pub fn replay(input: &str) -> String {
+    let path = rescue_path(input);
+    let mut lines = vec![format!("{:>5} start at 0,0 with {}", 0, path[0].tool)];
+
+    for pair in path.windows(2) {
+        let ((x, y), tool, time) = (pair[1].position, pair[1].tool, pair[1].time);
+
+        if pair[0].position == pair[1].position {
+            lines.push(format!("{:>5} switch to {} at {},{}", time, tool, x, y));
+        } else {
+            lines.push(format!("{:>5} move to {},{}", time, x, y));
+        }
+    }
+
+    lines.join("\n")
+}
+
+// Everything up to the target and the path, whichever reaches further
+fn bounds(target: (usize, usize), path: &[Waypoint]) -> (usize, usize) {
+    path.iter()
+        .fold((target.0 + 1, target.1 + 1), |(width, height), waypoint| {
+            (
+                width.max(waypoint.position.0 + 1),
+                height.max(waypoint.position.1 + 1),
+            )
+        })
+}
+
+// The cave with the way through it, `M` is the mouth, `T` the target, `*` a
+// move and `+` a tool switch.
+pub fn render(input: &str) -> String {
+    let scan = parse(input);
+    let (cave, path) = rescue(&scan);
+    let (width, height) = bounds(scan.target, &path);
+
+    let mut rows = (0..height)
+        .map(|y| (0..width).map(|x| cave.region(x, y).to_char()).collect())
+        .collect::<Vec<Vec<_>>>();
+
+    for pair in path.windows(2) {
+        let (x, y) = pair[1].position;
+        rows[y][x] = if pair[0].position == pair[1].position {
+            '+'
+        } else {
+            '*'
+        };
+    }
+    rows[MOUTH.1][MOUTH.0] = 'M';
+    rows[scan.target.1][scan.target.0] = 'T';
+
+    rows.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The path is drawn in the colour of the tool equipped when leaving each
+// region.
+pub fn render_image(input: &str) -> Vec<u8> {
+    let scan = parse(input);
+    let (cave, path) = rescue(&scan);
+    let (width, height) = bounds(scan.target, &path);
+
+    let mut pixels = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| cave.region(x, y).to_color())
+        .collect::<Vec<_>>();
+
+    for waypoint in &path {
+        let (x, y) = waypoint.position;
+        pixels[y * width + x] = waypoint.tool.to_color();
+    }
+
+    viz::ppm(width, height, &pixels)
+}
+
+fn parse(input: &str) -> Scan {
+    input.parse().unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn star_one(input: &str) -> usize {
+    Cave::new(&parse(input)).risk_level()
+}
+
+pub fn star_two(input: &str) -> usize {
+    rescue_path(input)
+        .last()
+        .expect("The rescue always has a start")
+        .time
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        estimate, render, render_image, replay, rescue_path, star_one, star_two, Cave, ParseError,
+        Region, Scan, Tool, Waypoint, MOUTH, MOVE_TIME, SWITCH_TIME,
+    };
+    use differential::Rng;
+    use search;
+    use std::collections::HashSet;
+
+    static EXAMPLE: &str = "
+depth: 510
+target: 10,10";
 
     #[test]
     fn test_star_one() {
-        assert_eq!(star_one(""), 1)
+        assert_eq!(star_one(EXAMPLE), 114)
     }
 
     #[test]
     fn test_star_two() {
-        assert_eq!(star_two(""), 1)
+        assert_eq!(star_two(EXAMPLE), 45)
+    }
+
+    #[test]
+    fn test_grow() {
+        let scan = EXAMPLE.parse().unwrap();
+        let mut cave = Cave::new(&scan);
+        cave.grow(16, 3);
+        cave.grow(4, 16);
+
+        assert_eq!((cave.width(), cave.height()), (16, 16));
+        assert!(cave.erosion.iter().all(|row| row.len() == 16));
+        assert_eq!(cave.erosion[10][10], 510);
+
+        let mut explored = Cave::new(&scan);
+        assert_eq!(explored.explore(15, 15), cave.region(15, 15));
+        assert_eq!((explored.width(), explored.height()), (22, 22));
+        cave.grow(22, 22);
+        assert_eq!(explored.erosion, cave.erosion);
+    }
+
+    #[test]
+    fn test_rescue_heuristic() {
+        // Searching with the estimate has to be exactly as fast as searching
+        // without it. Wet mouths are skipped since the torch can't be used
+        // there.
+        let mut rng = Rng::new(510);
+        for _ in 0..20 {
+            let scan = Scan {
+                depth: rng.range(100, 10_099),
+                target: (rng.range(0, 14), rng.range(0, 14)),
+            };
+            if scan.depth % 3 == 1 {
+                continue;
+            }
+            let start = (MOUTH.0, MOUTH.1, Tool::Torch);
+            let goal = (scan.target.0, scan.target.1, Tool::Torch);
+
+            let mut cave = Cave::new(&scan);
+            let expected =
+                search::dijkstra(start, |state| cave.moves(state), |&state| state == goal).unwrap();
+            let mut cave = Cave::new(&scan);
+            let actual = search::a_star(
+                start,
+                |state| cave.moves(state),
+                |state| estimate(scan.target, state),
+                |&state| state == goal,
+            )
+            .unwrap();
+
+            assert_eq!(actual[actual.len() - 1].1, expected[expected.len() - 1].1);
+        }
+    }
+
+    #[test]
+    fn test_cave() {
+        let cave = Cave::new(&EXAMPLE.parse().unwrap());
+
+        assert_eq!(cave.erosion[0][0], 510);
+        assert_eq!(cave.erosion[0][1], 17317);
+        assert_eq!(cave.erosion[1][0], 8415);
+        assert_eq!(cave.erosion[1][1], 1805);
+        assert_eq!(cave.erosion[10][10], 510);
+        assert_eq!(cave.region(0, 0), Region::Rocky);
+        assert_eq!(cave.region(1, 0), Region::Wet);
+        assert_eq!(cave.region(0, 1), Region::Rocky);
+        assert_eq!(cave.region(1, 1), Region::Narrow);
+        assert_eq!(cave.region(10, 10), Region::Rocky);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "depth: 11109\ntarget: 9,731".parse::<Scan>(),
+            Ok(Scan {
+                depth: 11109,
+                target: (9, 731),
+            })
+        );
+        assert_eq!("depth: 510".parse::<Scan>(), Err(ParseError::MissingTarget));
+        assert_eq!(
+            "depth: 510\ntarget: 10"
+                .parse::<Scan>()
+                .unwrap_err()
+                .to_string(),
+            "Invalid scan line `target: 10`"
+        );
+    }
+
+    #[test]
+    fn test_rescue_path() {
+        let path = rescue_path(EXAMPLE);
+
+        assert_eq!(
+            path[0],
+            Waypoint {
+                position: (0, 0),
+                tool: Tool::Torch,
+                time: 0,
+            }
+        );
+        assert_eq!(path.last().map(|waypoint| waypoint.time), Some(45));
+        assert!(path.windows(2).all(|pair| {
+            let (from, to) = (pair[0], pair[1]);
+
+            if from.position == to.position {
+                from.tool != to.tool && to.time == from.time + SWITCH_TIME
+            } else {
+                from.tool == to.tool && to.time == from.time + MOVE_TIME
+            }
+        }));
+    }
+
+    #[test]
+    fn test_replay() {
+        let replay = replay(EXAMPLE);
+        let mut lines = replay.lines();
+
+        assert_eq!(lines.next(), Some("    0 start at 0,0 with the torch"));
+        assert_eq!(lines.next(), Some("    1 move to 0,1"));
+        assert_eq!(lines.last(), Some("   45 switch to the torch at 10,10"));
+        assert_eq!(replay.lines().count(), rescue_path(EXAMPLE).len());
+    }
+
+    #[test]
+    fn test_render() {
+        let rendered = render(EXAMPLE);
+        let rows = rendered.lines().collect::<Vec<_>>();
+
+        assert!(rows[0].starts_with("M=.|=.|.|=."));
+        assert_eq!(rows[10].chars().nth(10), Some('T'));
+
+        // Everywhere on the way except the mouth and the target is marked
+        let visited = rescue_path(EXAMPLE)
+            .iter()
+            .map(|waypoint| waypoint.position)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            rendered.matches('*').count() + rendered.matches('+').count(),
+            visited.len() - 2
+        );
+
+        let image = render_image(EXAMPLE);
+        assert!(
+            image.starts_with(format!("P6\n{} {}\n255\n", rows[0].len(), rows.len()).as_bytes())
+        );
     }
 }
@@ -1,22 +1,777 @@
-pub fn star_one(input: &str) -> i64 {
-    0
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+lazy_static! {
+    static ref PATTERN: Regex =
+        Regex::new(r"^pos=<\s*(-?\d+),\s*(-?\d+),\s*(-?\d+)>,\s*r=(\d+)$").unwrap();
+}
+
+type Point = (i64, i64, i64);
+
+const ORIGIN: Point = (0, 0, 0);
+
+// Ranges along each of the directions in `diagonals`
+type Slabs = [(i64, i64); 4];
+
+fn manhattan_distance(a: Point, b: Point) -> i64 {
+    (a.0 - b.0).abs() + (a.1 - b.1).abs() + (a.2 - b.2).abs()
+}
+
+// The Manhattan distance between two points is the largest difference between
+// them along these four directions.
+fn diagonals((x, y, z): Point) -> [i64; 4] {
+    [x + y + z, x + y - z, x - y + z, -x + y + z]
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Algorithm {
+    // Branch and bound over ever smaller cubes
+    Octree,
+    // Sweeps the distances from the origin each nanobot's range covers and
+    // then checks which of the nanobots covering a distance share a point
+    Intervals,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    InvalidNanobot(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidNanobot(line) => write!(f, "Invalid nanobot `{}`", line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct Nanobot {
+    position: Point,
+    radius: i64,
+}
+
+impl Nanobot {
+    fn in_range(&self, point: Point) -> bool {
+        manhattan_distance(self.position, point) <= self.radius
+    }
+
+    // Whether some point is in range of both
+    fn overlaps(&self, other: &Nanobot) -> bool {
+        manhattan_distance(self.position, other.position) <= self.radius + other.radius
+    }
+
+    // The range is an octahedron, the same as where all four slabs overlap
+    fn slabs(&self) -> Slabs {
+        let centre = diagonals(self.position);
+        let mut slabs = [(0, 0); 4];
+        for (slab, &value) in slabs.iter_mut().zip(centre.iter()) {
+            *slab = (value - self.radius, value + self.radius);
+        }
+
+        slabs
+    }
+}
+
+// All points from `min` up to but not including `min + size` along every axis
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+struct Cube {
+    min: Point,
+    size: i64,
+}
+
+impl Cube {
+    // The smallest cube with a power of two size that contains every point
+    // in range of any of the nanobots as well as the origin.
+    fn enclosing(nanobots: &[Nanobot]) -> Self {
+        let (mut min, mut max) = (ORIGIN, ORIGIN);
+        for nanobot in nanobots {
+            let (x, y, z) = nanobot.position;
+            let r = nanobot.radius;
+
+            min = (min.0.min(x - r), min.1.min(y - r), min.2.min(z - r));
+            max = (max.0.max(x + r), max.1.max(y + r), max.2.max(z + r));
+        }
+
+        let extent = (max.0 - min.0).max(max.1 - min.1).max(max.2 - min.2) + 1;
+        let mut size = 1;
+        while size < extent {
+            size *= 2;
+        }
+
+        Self { min, size }
+    }
+
+    // The distance from `point` to the closest point in the cube
+    fn distance_to(&self, point: Point) -> i64 {
+        let axis = |value: i64, min: i64| {
+            let max = min + self.size - 1;
+
+            if value < min {
+                min - value
+            } else if value > max {
+                value - max
+            } else {
+                0
+            }
+        };
+
+        axis(point.0, self.min.0) + axis(point.1, self.min.1) + axis(point.2, self.min.2)
+    }
+
+    // The eight cubes of half the size that make up this one
+    fn split(&self) -> Vec<Cube> {
+        let size = self.size / 2;
+        let (x, y, z) = self.min;
+
+        (0..8)
+            .map(|corner| Cube {
+                min: (
+                    x + size * (corner & 1),
+                    y + size * ((corner >> 1) & 1),
+                    z + size * ((corner >> 2) & 1),
+                ),
+                size,
+            }).collect()
+    }
+
+    // An upper bound on the number of nanobots in range of any point in the
+    // cube, exact once the cube is a single point.
+    fn in_range(&self, nanobots: &[Nanobot]) -> usize {
+        nanobots
+            .iter()
+            .filter(|nanobot| self.distance_to(nanobot.position) <= nanobot.radius)
+            .count()
+    }
+}
+
+impl FromStr for Nanobot {
+    type Err = ParseError;
+
+    // `pos=<0,0,0>, r=4`
+    fn from_str(line: &str) -> Result<Self, ParseError> {
+        let invalid = || ParseError::InvalidNanobot(line.to_owned());
+        let groups = PATTERN.captures(line).ok_or_else(invalid)?;
+        let number = |idx: usize| groups[idx].parse::<i64>().map_err(|_| invalid());
+
+        Ok(Self {
+            position: (number(1)?, number(2)?, number(3)?),
+            radius: number(4)?,
+        })
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<Nanobot>, ParseError> {
+    input
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse())
+        .collect()
+}
+
+fn parse_nanobots(input: &str) -> Vec<Nanobot> {
+    parse(input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// The number of nanobots, the strongest one included, in range of the
+// strongest one.
+pub fn star_one(input: &str) -> usize {
+    let nanobots = parse_nanobots(input);
+    let strongest = nanobots
+        .iter()
+        .max_by_key(|nanobot| nanobot.radius)
+        .expect("Expected at least one nanobot");
+
+    nanobots
+        .iter()
+        .filter(|nanobot| strongest.in_range(nanobot.position))
+        .count()
+}
+
+// Splits space into ever smaller cubes, always looking at the cube that might
+// be in range of the most nanobots first. Ties go to the cube closest to the
+// origin and then to the smallest one, so the first single point cube is in
+// range of the most nanobots and closest to the origin among those. Returns
+// that point and how many nanobots it's in range of.
+fn octree_search(nanobots: &[Nanobot]) -> (Point, usize) {
+    let mut to_visit = BinaryHeap::new();
+    let start = Cube::enclosing(nanobots);
+    to_visit.push((
+        start.in_range(nanobots),
+        Reverse(start.distance_to(ORIGIN)),
+        Reverse(start.size),
+        Reverse(start),
+    ));
+
+    while let Some((count, _, _, Reverse(cube))) = to_visit.pop() {
+        if cube.size == 1 {
+            return (cube.min, count);
+        }
+
+        for child in cube.split() {
+            to_visit.push((
+                child.in_range(nanobots),
+                Reverse(child.distance_to(ORIGIN)),
+                Reverse(child.size),
+                Reverse(child),
+            ));
+        }
+    }
+
+    unreachable!("Every cube is eventually split into single points")
+}
+
+fn intersect(nanobots: &[&Nanobot]) -> Slabs {
+    nanobots
+        .iter()
+        .fold([(i64::MIN, i64::MAX); 4], |mut slabs: Slabs, nanobot| {
+            for (slab, other) in slabs.iter_mut().zip(nanobot.slabs().iter()) {
+                *slab = (slab.0.max(other.0), slab.1.min(other.1));
+            }
+
+            slabs
+        })
+}
+
+// Whether there's a point within `distance` of the origin in all the slabs. A
+// point's diagonals share a parity and the first is the sum of the other three,
+// any three values of the same parity make a point.
+fn reachable(slabs: &Slabs, distance: i64) -> bool {
+    (0..2).any(|parity| {
+        let mut bounds = [(0, 0); 4];
+        for (bound, &(lo, hi)) in bounds.iter_mut().zip(slabs.iter()) {
+            let (lo, hi) = (lo.max(-distance), hi.min(distance));
+
+            // Rounded inwards to the parity
+            *bound = (
+                lo + (lo - parity).rem_euclid(2),
+                hi - (hi - parity).rem_euclid(2),
+            );
+        }
+
+        if bounds.iter().any(|&(lo, hi)| lo > hi) {
+            return false;
+        }
+
+        let sum_lo = bounds[1].0 + bounds[2].0 + bounds[3].0;
+        let sum_hi = bounds[1].1 + bounds[2].1 + bounds[3].1;
+
+        sum_lo.max(bounds[0].0) <= sum_hi.min(bounds[0].1)
+    })
+}
+
+// How close to the origin a point in all the slabs can be, if there is one
+fn closest_distance(slabs: &Slabs) -> Option<i64> {
+    if slabs.iter().any(|&(lo, hi)| lo > hi) {
+        return None;
+    }
+
+    let mut high = slabs
+        .iter()
+        .map(|&(lo, hi)| lo.abs().max(hi.abs()))
+        .max()
+        .unwrap_or(0);
+    if !reachable(slabs, high) {
+        return None;
+    }
+
+    let mut low = 0;
+    while low < high {
+        let middle = low + (high - low) / 2;
+
+        if reachable(slabs, middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+
+    Some(low)
+}
+
+fn overlap_matrix(nanobots: &[Nanobot]) -> Vec<Vec<bool>> {
+    nanobots
+        .iter()
+        .map(|nanobot| {
+            nanobots
+                .iter()
+                .map(|other| nanobot.overlaps(other))
+                .collect()
+        }).collect()
+}
+
+// Bron-Kerbosch with pivoting, `clique` is extended with nanobots from
+// `candidates` and none from `excluded` since those cliques are already known.
+fn extend_clique(
+    overlaps: &[Vec<bool>],
+    clique: &mut Vec<usize>,
+    candidates: Vec<usize>,
+    mut excluded: Vec<usize>,
+    largest: &mut Vec<usize>,
+) {
+    if candidates.is_empty() {
+        if excluded.is_empty() && clique.len() > largest.len() {
+            *largest = clique.clone();
+        }
+        return;
+    }
+    if clique.len() + candidates.len() <= largest.len() {
+        return;
+    }
+
+    // Any maximal clique has either the pivot or something it doesn't overlap
+    let pivot = candidates
+        .iter()
+        .chain(excluded.iter())
+        .cloned()
+        .max_by_key(|&pivot| {
+            candidates
+                .iter()
+                .filter(|&&idx| overlaps[pivot][idx])
+                .count()
+        }).unwrap();
+    let branches = candidates
+        .iter()
+        .cloned()
+        .filter(|&idx| idx == pivot || !overlaps[pivot][idx])
+        .collect::<Vec<_>>();
+    let mut candidates = candidates;
+
+    for idx in branches {
+        let neighbours = |set: &[usize]| {
+            set.iter()
+                .cloned()
+                .filter(|&other| other != idx && overlaps[idx][other])
+                .collect()
+        };
+
+        clique.push(idx);
+        extend_clique(
+            overlaps,
+            clique,
+            neighbours(&candidates),
+            neighbours(&excluded),
+            largest,
+        );
+        clique.pop();
+
+        candidates.retain(|&other| other != idx);
+        excluded.push(idx);
+    }
+}
+
+// Every point's nanobots overlap in pairs, the largest clique is at least as
+// large as the number of nanobots in range of the best point. It's often
+// exactly that in practice, but ranges can overlap in pairs without sharing a
+// point.
+fn largest_clique(nanobots: &[Nanobot]) -> Vec<usize> {
+    let overlaps = overlap_matrix(nanobots);
+    let mut largest = vec![];
+
+    extend_clique(
+        &overlaps,
+        &mut vec![],
+        (0..nanobots.len()).collect(),
+        vec![],
+        &mut largest,
+    );
+    largest.sort();
+
+    largest
+}
+
+// For every nanobot the others with a point in range of both
+pub fn overlap_graph(input: &str) -> Vec<Vec<usize>> {
+    let nanobots = parse_nanobots(input);
+
+    overlap_matrix(&nanobots)
+        .iter()
+        .enumerate()
+        .map(|(idx, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|&(other, &overlaps)| overlaps && other != idx)
+                .map(|(other, _)| other)
+                .collect()
+        }).collect()
+}
+
+// The indices of the largest group of nanobots whose ranges all overlap with
+// each other, in input order.
+pub fn largest_overlapping_group(input: &str) -> Vec<usize> {
+    largest_clique(&parse_nanobots(input))
+}
+
+// Every point in range of a nanobot is between `distance - radius` and
+// `distance + radius` from the origin. Sweeping those intervals gives the
+// nanobots covering every distance, any point at that distance is only in
+// range of some of them.
+fn covering(nanobots: &[Nanobot]) -> Vec<Vec<usize>> {
+    let mut events = nanobots
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, nanobot)| {
+            let distance = manhattan_distance(nanobot.position, ORIGIN);
+
+            vec![
+                ((distance - nanobot.radius).max(0), idx, true),
+                (distance + nanobot.radius + 1, idx, false),
+            ]
+        }).collect::<Vec<_>>();
+    events.sort();
+
+    let mut active = vec![false; nanobots.len()];
+    let mut covering = vec![];
+
+    for (idx, &(distance, nanobot, starts)) in events.iter().enumerate() {
+        active[nanobot] = starts;
+
+        // Only once every event at this distance is done
+        let last_at_distance = events
+            .get(idx + 1)
+            .is_none_or(|&(next, _, _)| next != distance);
+        if last_at_distance && active.contains(&true) {
+            covering.push((0..nanobots.len()).filter(|&idx| active[idx]).collect());
+        }
+    }
+
+    covering
+}
+
+fn closest_point_in_range(nanobots: &[Nanobot], members: &[usize]) -> Option<i64> {
+    let members = members
+        .iter()
+        .map(|&idx| &nanobots[idx])
+        .collect::<Vec<_>>();
+
+    closest_distance(&intersect(&members))
+}
+
+// Verifies the sets from the sweep, largest first. When the nanobots of a set
+// don't share a point some of them have to go, either one that doesn't
+// overlap with others or, if they all overlap in pairs, one from a smallest
+// group without a shared point. Every point's nanobots are part of one of the
+// sets left over, so the first sets to share a point are in range of the most
+// nanobots. Returns the distance of the closest such point and the count.
+fn interval_search(nanobots: &[Nanobot]) -> (i64, usize) {
+    let overlaps = overlap_matrix(nanobots);
+
+    let mut to_visit = covering(nanobots)
+        .into_iter()
+        .map(|members| (members.len(), members))
+        .collect::<BinaryHeap<_>>();
+    let mut seen = HashSet::new();
+    let mut best: Option<(i64, usize)> = None;
+
+    while let Some((count, members)) = to_visit.pop() {
+        if best.is_some_and(|(_, most)| count < most) {
+            break;
+        }
+        if !seen.insert(members.clone()) {
+            continue;
+        }
+
+        let without = |excluded: &dyn Fn(usize) -> bool| {
+            let rest = members
+                .iter()
+                .cloned()
+                .filter(|&idx| !excluded(idx))
+                .collect::<Vec<_>>();
+
+            (rest.len(), rest)
+        };
+
+        let conflicts = |idx: usize| {
+            members
+                .iter()
+                .filter(|&&other| !overlaps[idx][other])
+                .count()
+        };
+        let worst = members.iter().cloned().max_by_key(|&idx| conflicts(idx));
+        if let Some(worst) = worst.filter(|&idx| conflicts(idx) > 0) {
+            // Either it goes or everything it doesn't overlap with does
+            to_visit.push(without(&|idx| idx == worst));
+            to_visit.push(without(&|idx| !overlaps[worst][idx]));
+            continue;
+        }
+
+        match closest_point_in_range(nanobots, &members) {
+            Some(distance) => {
+                if best.is_none_or(|(closest, _)| distance < closest) {
+                    best = Some((distance, count));
+                }
+            }
+            None => {
+                let mut core = members.clone();
+                for &idx in &members {
+                    let smaller = core
+                        .iter()
+                        .cloned()
+                        .filter(|&other| other != idx)
+                        .collect::<Vec<_>>();
+
+                    if closest_point_in_range(nanobots, &smaller).is_none() {
+                        core = smaller;
+                    }
+                }
+
+                for &idx in &core {
+                    to_visit.push(without(&|other| other == idx));
+                }
+            }
+        }
+    }
+
+    best.expect("Expected at least one nanobot")
+}
+
+// How far from the origin the closest point in range of the most nanobots is
+pub fn best_distance(input: &str, algorithm: Algorithm) -> i64 {
+    let nanobots = parse_nanobots(input);
+
+    match algorithm {
+        Algorithm::Octree => manhattan_distance(octree_search(&nanobots).0, ORIGIN),
+        Algorithm::Intervals => interval_search(&nanobots).0,
+    }
 }
 
 pub fn star_two(input: &str) -> i64 {
-    0
+    best_distance(input, Algorithm::Octree)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{
+        best_distance, closest_distance, diagonals, intersect, interval_search, largest_clique,
+        largest_overlapping_group, manhattan_distance, octree_search, overlap_graph, parse,
+        star_one, star_two, Algorithm, Cube, Nanobot, ParseError, ORIGIN,
+    };
+    use differential::Rng;
+
+    static EXAMPLE: &str = "
+pos=<0,0,0>, r=4
+pos=<1,0,0>, r=1
+pos=<4,0,0>, r=3
+pos=<0,2,0>, r=1
+pos=<0,5,0>, r=3
+pos=<0,0,3>, r=1
+pos=<1,1,1>, r=1
+pos=<1,1,2>, r=1
+pos=<1,3,1>, r=1";
 
     #[test]
     fn test_star_one() {
-        assert_eq!(star_one(""), 1)
+        assert_eq!(star_one(EXAMPLE), 7)
     }
 
+    static TELEPORT_EXAMPLE: &str = "
+pos=<10,12,12>, r=2
+pos=<12,14,12>, r=2
+pos=<16,12,12>, r=4
+pos=<14,14,14>, r=6
+pos=<50,50,50>, r=200
+pos=<10,10,10>, r=5";
+
     #[test]
     fn test_star_two() {
-        assert_eq!(star_two(""), 1)
+        assert_eq!(star_two(TELEPORT_EXAMPLE), 36);
+        assert_eq!(best_distance(TELEPORT_EXAMPLE, Algorithm::Intervals), 36);
+    }
+
+    fn random_nanobots(rng: &mut Rng, count: usize, spread: i64) -> Vec<Nanobot> {
+        let mut random = |n: i64| rng.next(n as u64) as i64;
+
+        (0..count)
+            .map(|_| Nanobot {
+                position: (
+                    random(2 * spread) - spread,
+                    random(2 * spread) - spread,
+                    random(2 * spread) - spread,
+                ),
+                radius: 1 + random(spread),
+            }).collect()
+    }
+
+    #[test]
+    fn test_octree_search() {
+        // Small enough to check every point
+        let mut rng = Rng::new(23);
+        for _ in 0..30 {
+            let nanobots = random_nanobots(&mut rng, 8, 6);
+            let limit = 12;
+            let mut best = (0, 0);
+
+            for x in -limit..=limit {
+                for y in -limit..=limit {
+                    for z in -limit..=limit {
+                        let point = (x, y, z);
+                        let count = nanobots
+                            .iter()
+                            .filter(|nanobot| nanobot.in_range(point))
+                            .count();
+                        let distance = manhattan_distance(point, ORIGIN);
+
+                        if count > best.0 || (count == best.0 && distance < best.1) {
+                            best = (count, distance);
+                        }
+                    }
+                }
+            }
+
+            let (point, count) = octree_search(&nanobots);
+            assert_eq!((count, manhattan_distance(point, ORIGIN)), best);
+        }
+    }
+
+    #[test]
+    fn test_interval_search() {
+        // Spread out like the puzzle, where most of the nanobots share a
+        // point, and packed together.
+        let mut rng = Rng::new(30);
+        for round in 0..200 {
+            let mut nanobots = random_nanobots(&mut rng, 12, 40);
+            if round % 2 == 0 {
+                let shared = nanobots[0].position;
+                for nanobot in &mut nanobots[..9] {
+                    nanobot.radius =
+                        manhattan_distance(nanobot.position, shared) + nanobot.radius / 4;
+                }
+            }
+            let (point, count) = octree_search(&nanobots);
+
+            assert_eq!(
+                interval_search(&nanobots),
+                (manhattan_distance(point, ORIGIN), count)
+            );
+        }
+
+        // Overlapping in pairs and threes but without a point in range of all
+        // four
+        let nanobots = parse(
+            "
+pos=<4,2,-1>, r=4
+pos=<1,3,-2>, r=4
+pos=<1,1,1>, r=2
+pos=<3,1,0>, r=2",
+        )
+        .unwrap();
+        let (point, count) = octree_search(&nanobots);
+        assert_eq!(count, 3);
+        assert_eq!(
+            interval_search(&nanobots),
+            (manhattan_distance(point, ORIGIN), count)
+        );
+    }
+
+    #[test]
+    fn test_closest_distance() {
+        let nanobots = parse(TELEPORT_EXAMPLE).unwrap();
+        let all = nanobots.iter().collect::<Vec<_>>();
+
+        assert_eq!(closest_distance(&intersect(&all[..1])), Some(32));
+        assert_eq!(closest_distance(&intersect(&all[..2])), Some(36));
+        assert_eq!(closest_distance(&intersect(&all)), None);
+        assert_eq!(diagonals((1, -2, 3)), [2, -4, 6, 0]);
+
+        // Every point in range of a nanobot is in all of its slabs and the
+        // other way around.
+        let nanobot = nanobots[0];
+        for x in 5..15 {
+            for y in 7..17 {
+                for z in 7..17 {
+                    let inside = diagonals((x, y, z))
+                        .iter()
+                        .zip(nanobot.slabs().iter())
+                        .all(|(&value, &(lo, hi))| lo <= value && value <= hi);
+
+                    assert_eq!(inside, nanobot.in_range((x, y, z)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cube() {
+        let cube = Cube {
+            min: (0, 0, 0),
+            size: 4,
+        };
+
+        assert_eq!(cube.distance_to((1, 2, 3)), 0);
+        assert_eq!(cube.distance_to((-1, 5, 2)), 3);
+        assert_eq!(cube.split().len(), 8);
+        assert!(cube.split().contains(&Cube {
+            min: (2, 0, 2),
+            size: 2,
+        }));
+
+        let nanobots = parse(TELEPORT_EXAMPLE).unwrap();
+        let enclosing = Cube::enclosing(&nanobots);
+        assert!((enclosing.size as u64).is_power_of_two());
+        assert_eq!(enclosing.distance_to(ORIGIN), 0);
+        assert_eq!(enclosing.in_range(&nanobots), nanobots.len());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "pos=<-12,0,7>, r=42".parse::<Nanobot>(),
+            Ok(Nanobot {
+                position: (-12, 0, 7),
+                radius: 42,
+            })
+        );
+        assert_eq!(parse(EXAMPLE).map(|nanobots| nanobots.len()), Ok(9));
+        assert_eq!(
+            parse("pos=<0,0,0>, r=4\npos=<0,0>, r=1"),
+            Err(ParseError::InvalidNanobot(String::from("pos=<0,0>, r=1")))
+        );
+    }
+
+    #[test]
+    fn test_overlap_graph() {
+        let graph = overlap_graph(TELEPORT_EXAMPLE);
+
+        assert_eq!(graph[4], vec![0, 1, 2, 3, 5]);
+        assert_eq!(graph[5], vec![0, 4]);
+        assert!(graph
+            .iter()
+            .enumerate()
+            .all(|(idx, others)| others.iter().all(|&other| graph[other].contains(&idx))));
+        assert_eq!(
+            largest_overlapping_group(TELEPORT_EXAMPLE),
+            vec![0, 1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_largest_clique() {
+        // Small enough to try every group
+        let mut rng = Rng::new(31);
+        for _ in 0..50 {
+            let nanobots = random_nanobots(&mut rng, 10, 30);
+            let overlapping = |group: usize| {
+                (0..10).all(|a| {
+                    (0..10).all(|b| {
+                        group & (1 << a) == 0
+                            || group & (1 << b) == 0
+                            || nanobots[a].overlaps(&nanobots[b])
+                    })
+                })
+            };
+            let expected = (0..1_usize << 10)
+                .filter(|&group| overlapping(group))
+                .map(|group| group.count_ones() as usize)
+                .max()
+                .unwrap();
+
+            let clique = largest_clique(&nanobots);
+            assert_eq!(clique.len(), expected);
+            assert!(clique.len() >= octree_search(&nanobots).1);
+        }
     }
 }
@@ -1,22 +1,480 @@
-pub fn star_one(input: &str) -> i64 {
-    0
+use regex::Regex;
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+
+lazy_static! {
+    static ref GROUP: Regex = Regex::new(
+        r"^(\d+) units each with (\d+) hit points (?:\(([^)]*)\) )?with an attack that does (\d+) (\w+) damage at initiative (\d+)$"
+    ).unwrap();
+}
+
+static IMMUNE_SYSTEM: &str = "Immune System";
+
+// Armies are referred to by their index in `Battle::armies`
+type Army = usize;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Outcome {
+    // The army left standing and how many units it has
+    Victory(Army, usize),
+    // None of the armies left can kill a single unit of another, with
+    // immunities and units with more hit points than an attack can deal it's
+    // entirely possible.
+    Stalemate,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    MissingArmyName(String),
+    InvalidGroup(String),
+    GroupWithoutArmy(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingArmyName(line) => write!(f, "Army without a name `{}`", line),
+            ParseError::InvalidGroup(line) => write!(f, "Invalid group `{}`", line),
+            ParseError::GroupWithoutArmy(line) => {
+                write!(f, "Group `{}` comes before any army", line)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Group {
+    army: Army,
+    units: usize,
+    hit_points: usize,
+    attack_damage: usize,
+    attack_type: String,
+    initiative: usize,
+    weaknesses: Vec<String>,
+    immunities: Vec<String>,
+}
+
+impl Group {
+    fn effective_power(&self) -> usize {
+        self.units * self.attack_damage
+    }
+
+    // What this group would deal to `other` if it attacked right now
+    fn damage_to(&self, other: &Group) -> usize {
+        if other.immunities.contains(&self.attack_type) {
+            0
+        } else if other.weaknesses.contains(&self.attack_type) {
+            self.effective_power() * 2
+        } else {
+            self.effective_power()
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        self.units > 0
+    }
+}
+
+// Any number of armies, each one fighting all of the others
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Battle {
+    armies: Vec<String>,
+    groups: Vec<Group>,
+}
+
+impl Battle {
+    fn army(&self, name: &str) -> Option<Army> {
+        self.armies.iter().position(|army| army == name)
+    }
+
+    // The same battle with `army` dealing `boost` more damage per unit
+    fn boosted(&self, army: Army, boost: usize) -> Battle {
+        let mut battle = self.clone();
+        for group in battle.groups.iter_mut().filter(|group| group.army == army) {
+            group.attack_damage += boost;
+        }
+
+        battle
+    }
 }
 
-pub fn star_two(input: &str) -> i64 {
-    0
+// Without the army, that's given by the heading the group is listed under
+struct GroupDescription {
+    units: usize,
+    hit_points: usize,
+    attack_damage: usize,
+    attack_type: String,
+    initiative: usize,
+    weaknesses: Vec<String>,
+    immunities: Vec<String>,
+}
+
+impl FromStr for GroupDescription {
+    type Err = ParseError;
+
+    // `18 units each with 729 hit points (weak to fire; immune to cold, slashing)
+    // with an attack that does 8 radiation damage at initiative 10`, the part
+    // in parentheses is optional and so is either half of it.
+    fn from_str(line: &str) -> Result<Self, ParseError> {
+        let invalid = || ParseError::InvalidGroup(line.to_owned());
+        let groups = GROUP.captures(line).ok_or_else(invalid)?;
+        let number = |idx: usize| groups[idx].parse::<usize>().map_err(|_| invalid());
+
+        let mut weaknesses = vec![];
+        let mut immunities = vec![];
+        if let Some(modifiers) = groups.get(3) {
+            for modifier in modifiers.as_str().split(';').map(|part| part.trim()) {
+                let (list, types) = if let Some(types) = modifier.strip_prefix("weak to ") {
+                    (&mut weaknesses, types)
+                } else if let Some(types) = modifier.strip_prefix("immune to ") {
+                    (&mut immunities, types)
+                } else {
+                    return Err(invalid());
+                };
+
+                list.extend(types.split(',').map(|kind| kind.trim().to_owned()));
+            }
+        }
+
+        Ok(Self {
+            units: number(1)?,
+            hit_points: number(2)?,
+            attack_damage: number(4)?,
+            attack_type: groups[5].to_owned(),
+            initiative: number(6)?,
+            weaknesses,
+            immunities,
+        })
+    }
+}
+
+// Every heading starts an army, or continues one listed earlier with the same
+// name, the groups below it belong to that army.
+fn parse(input: &str) -> Result<Battle, ParseError> {
+    let mut armies: Vec<String> = vec![];
+    let mut army = None;
+    let mut groups = vec![];
+
+    for line in input
+        .lines()
+        .map(|line| line.trim())
+        .filter(|l| !l.is_empty())
+    {
+        if let Some(name) = line.strip_suffix(':') {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(ParseError::MissingArmyName(line.to_owned()));
+            }
+
+            army = Some(
+                armies
+                    .iter()
+                    .position(|army| army == name)
+                    .unwrap_or_else(|| {
+                        armies.push(name.to_owned());
+                        armies.len() - 1
+                    }),
+            );
+            continue;
+        }
+
+        let army = army.ok_or_else(|| ParseError::GroupWithoutArmy(line.to_owned()))?;
+        let description = line.parse::<GroupDescription>()?;
+
+        groups.push(Group {
+            army,
+            units: description.units,
+            hit_points: description.hit_points,
+            attack_damage: description.attack_damage,
+            attack_type: description.attack_type,
+            initiative: description.initiative,
+            weaknesses: description.weaknesses,
+            immunities: description.immunities,
+        });
+    }
+
+    Ok(Battle { armies, groups })
+}
+
+// Every group picks the enemy group it would deal the most damage to, in
+// order of decreasing effective power. Returns the index of the target for
+// every group.
+fn select_targets(groups: &[Group]) -> Vec<Option<usize>> {
+    let mut order = (0..groups.len())
+        .filter(|&idx| groups[idx].is_alive())
+        .collect::<Vec<_>>();
+    order.sort_by_key(|&idx| {
+        (
+            Reverse(groups[idx].effective_power()),
+            Reverse(groups[idx].initiative),
+        )
+    });
+
+    let mut targets = vec![None; groups.len()];
+    let mut targeted = vec![false; groups.len()];
+
+    for attacker in order {
+        let target = (0..groups.len())
+            .filter(|&idx| {
+                groups[idx].is_alive()
+                    && groups[idx].army != groups[attacker].army
+                    && !targeted[idx]
+            }).filter(|&idx| groups[attacker].damage_to(&groups[idx]) > 0)
+            .max_by_key(|&idx| {
+                (
+                    groups[attacker].damage_to(&groups[idx]),
+                    groups[idx].effective_power(),
+                    groups[idx].initiative,
+                )
+            });
+
+        if let Some(target) = target {
+            targeted[target] = true;
+            targets[attacker] = Some(target);
+        }
+    }
+
+    targets
+}
+
+// Groups attack in order of decreasing initiative, one that has lost all of
+// its units before its turn doesn't attack. Returns the number of units killed.
+fn fight_round(groups: &mut [Group]) -> usize {
+    let targets = select_targets(groups);
+    let mut order = (0..groups.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&idx| Reverse(groups[idx].initiative));
+
+    let mut total_killed = 0;
+
+    for attacker in order {
+        if !groups[attacker].is_alive() {
+            continue;
+        }
+
+        if let Some(target) = targets[attacker] {
+            let damage = groups[attacker].damage_to(&groups[target]);
+            let killed = (damage / groups[target].hit_points).min(groups[target].units);
+
+            groups[target].units -= killed;
+            total_killed += killed;
+        }
+    }
+
+    total_killed
+}
+
+// Fights until only one army is left or a round passes without losses, after
+// which every round plays out the same.
+fn fight(battle: Battle) -> Outcome {
+    let mut groups = battle.groups;
+
+    loop {
+        let alive = groups
+            .iter()
+            .filter(|group| group.is_alive())
+            .collect::<Vec<_>>();
+
+        if alive.iter().all(|group| group.army == alive[0].army) {
+            let units = alive.iter().map(|group| group.units).sum();
+
+            return Outcome::Victory(alive[0].army, units);
+        }
+
+        if fight_round(&mut groups) == 0 {
+            return Outcome::Stalemate;
+        }
+    }
+}
+
+fn parse_battle(input: &str) -> Battle {
+    parse(input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn star_one(input: &str) -> usize {
+    match fight(parse_battle(input)) {
+        Outcome::Victory(_, units) => units,
+        Outcome::Stalemate => panic!("The fight ended in a stalemate"),
+    }
+}
+
+// The units the immune system has left with the smallest boost that lets it
+// win. Stalemates don't count as a win, and since a bigger boost can turn a
+// win back into a stalemate the boosts are tried one at a time.
+pub fn star_two(input: &str) -> usize {
+    let battle = parse_battle(input);
+    let immune_system = battle
+        .army(IMMUNE_SYSTEM)
+        .unwrap_or_else(|| panic!("Expected an army called `{}`", IMMUNE_SYSTEM));
+
+    (1..)
+        .find_map(|boost| match fight(battle.boosted(immune_system, boost)) {
+            Outcome::Victory(army, units) if army == immune_system => Some(units),
+            _ => None,
+        }).unwrap()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{star_one, star_two};
+    use super::{fight, fight_round, parse, star_one, star_two, Outcome, ParseError};
+
+    static EXAMPLE: &str = "
+Immune System:
+17 units each with 5390 hit points (weak to radiation, bludgeoning) with an attack that does 4507 fire damage at initiative 2
+989 units each with 1274 hit points (immune to fire; weak to bludgeoning, slashing) with an attack that does 25 slashing damage at initiative 3
+
+Infection:
+801 units each with 4706 hit points (weak to radiation) with an attack that does 116 bludgeoning damage at initiative 1
+4485 units each with 2961 hit points (immune to radiation; weak to fire, cold) with an attack that does 12 slashing damage at initiative 4";
 
     #[test]
     fn test_star_one() {
-        assert_eq!(star_one(""), 1)
+        assert_eq!(star_one(EXAMPLE), 5216)
     }
 
     #[test]
     fn test_star_two() {
-        assert_eq!(star_two(""), 1)
+        assert_eq!(star_two(EXAMPLE), 51)
+    }
+
+    #[test]
+    fn test_star_two_stalemate_after_win() {
+        let input = "
+Immune System:
+6 units each with 11 hit points (immune to fire) with an attack that does 3 cold damage at initiative 4
+4 units each with 16 hit points (immune to slashing) with an attack that does 7 slashing damage at initiative 7
+
+Infection:
+4 units each with 30 hit points with an attack that does 4 fire damage at initiative 6
+10 units each with 9 hit points (immune to slashing) with an attack that does 5 cold damage at initiative 9";
+        let battle = parse(input).unwrap();
+
+        // Boosts 6 through 11 stall, so a search that doubles the boost lands
+        // on a stalemate above the winning boost 5 and skips past it.
+        assert_eq!(fight(battle.boosted(0, 4)), Outcome::Victory(1, 7));
+        assert_eq!(fight(battle.boosted(0, 5)), Outcome::Victory(0, 4));
+        for boost in 6..12 {
+            assert_eq!(fight(battle.boosted(0, boost)), Outcome::Stalemate);
+        }
+        assert_eq!(fight(battle.boosted(0, 12)), Outcome::Victory(0, 3));
+        assert_eq!(star_two(input), 4);
+    }
+
+    #[test]
+    fn test_fight() {
+        let battle = parse(EXAMPLE).unwrap();
+
+        assert_eq!(fight(battle.clone()), Outcome::Victory(1, 5216));
+        assert_eq!(fight(battle.boosted(0, 1570)), Outcome::Victory(0, 51));
+
+        // Both sides are immune to each other
+        let stuck = parse(
+            "
+Immune System:
+10 units each with 10 hit points (immune to cold) with an attack that does 5 fire damage at initiative 2
+
+Infection:
+10 units each with 10 hit points (immune to fire) with an attack that does 5 cold damage at initiative 1",
+        ).unwrap();
+        assert_eq!(fight(stuck.clone()), Outcome::Stalemate);
+
+        // Neither side deals enough damage to kill a whole unit
+        let mut weak = stuck;
+        for group in &mut weak.groups {
+            group.immunities.clear();
+            group.units = 1;
+            group.hit_points = 100;
+        }
+        assert_eq!(fight(weak), Outcome::Stalemate);
+    }
+
+    #[test]
+    fn test_three_armies() {
+        // Nobody can hurt the reinforcements, they finish off the infection in
+        // the same round the infection wipes out the immune system.
+        let battle = parse(
+            "
+Immune System:
+10 units each with 10 hit points with an attack that does 10 fire damage at initiative 3
+
+Infection:
+10 units each with 10 hit points (immune to fire; weak to slashing) with an attack that does 10 cold damage at initiative 2
+
+Reinforcements:
+5 units each with 10 hit points (immune to cold, fire) with an attack that does 40 slashing damage at initiative 1",
+        ).unwrap();
+
+        assert_eq!(
+            battle.armies,
+            vec!["Immune System", "Infection", "Reinforcements"]
+        );
+        assert_eq!(battle.army("Reinforcements"), Some(2));
+        assert_eq!(battle.army("Elves"), None);
+
+        let mut without_reinforcements = battle.clone();
+        without_reinforcements.groups.pop();
+        assert_eq!(fight(without_reinforcements), Outcome::Victory(1, 10));
+        assert_eq!(fight(battle), Outcome::Victory(2, 5));
+    }
+
+    #[test]
+    fn test_parse() {
+        let battle = parse(EXAMPLE).unwrap();
+        let groups = &battle.groups;
+
+        assert_eq!(battle.armies, vec!["Immune System", "Infection"]);
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[1].army, 0);
+        assert_eq!(groups[1].immunities, vec!["fire"]);
+        assert_eq!(groups[1].weaknesses, vec!["bludgeoning", "slashing"]);
+        assert_eq!(groups[2].army, 1);
+        assert_eq!(
+            (groups[2].units, groups[2].hit_points, groups[2].initiative),
+            (801, 4706, 1)
+        );
+        assert_eq!(groups[2].attack_type, "bludgeoning");
+
+        let plain = parse(
+            "Infection:\n3 units each with 4 hit points with an attack that does 5 cold damage at initiative 6",
+        ).unwrap();
+        assert!(plain.groups[0].weaknesses.is_empty() && plain.groups[0].immunities.is_empty());
+
+        // Groups listed under a repeated heading join the earlier army
+        let split = parse(&format!(
+            "{}\nImmune System:\n{}",
+            EXAMPLE,
+            "3 units each with 4 hit points with an attack that does 5 cold damage at initiative 6"
+        ))
+        .unwrap();
+        assert_eq!(split.armies.len(), 2);
+        assert_eq!(split.groups[4].army, 0);
+
+        assert_eq!(
+            parse(" :"),
+            Err(ParseError::MissingArmyName(String::from(":")))
+        );
+        assert_eq!(
+            parse("3 units").unwrap_err().to_string(),
+            "Group `3 units` comes before any army"
+        );
+        assert_eq!(
+            parse("Infection:\n3 units").unwrap_err().to_string(),
+            "Invalid group `3 units`"
+        );
+    }
+
+    #[test]
+    fn test_fight_round() {
+        let mut groups = parse(EXAMPLE).unwrap().groups;
+
+        assert_eq!(groups[2].damage_to(&groups[0]), 185832);
+        assert_eq!(groups[3].damage_to(&groups[0]), 53820);
+        assert_eq!(groups[3].damage_to(&groups[1]), 107640);
+        assert_eq!(groups[0].damage_to(&groups[3]), 153238);
+        assert_eq!(groups[1].damage_to(&groups[2]), 24725);
+
+        fight_round(&mut groups);
+        let units = groups.iter().map(|group| group.units).collect::<Vec<_>>();
+        assert_eq!(units, vec![0, 905, 797, 4434]);
     }
 }
@@ -0,0 +1,210 @@
+use std::fmt;
+
+type Point = [i64; 4];
+
+// Points this close, or closer, are in the same constellation
+const MAX_DISTANCE: i64 = 3;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    InvalidPoint(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidPoint(line) => write!(f, "Invalid point `{}`", line),
+        }
+    }
+}
+
+fn manhattan_distance(a: &Point, b: &Point) -> i64 {
+    a.iter().zip(b.iter()).map(|(a, b)| (a - b).abs()).sum()
+}
+
+// Union-find over the indices `0..size`, with path halving and union by size
+struct DisjointSets {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+    count: usize,
+}
+
+impl DisjointSets {
+    fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+            sizes: vec![1; size],
+            count: size,
+        }
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.parents[idx] != idx {
+            self.parents[idx] = self.parents[self.parents[idx]];
+            idx = self.parents[idx];
+        }
+
+        idx
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+
+        let (larger, smaller) = if self.sizes[a] >= self.sizes[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parents[smaller] = larger;
+        self.sizes[larger] += self.sizes[smaller];
+        self.count -= 1;
+    }
+}
+
+// `-1,2,2,0`
+fn parse_point(line: &str) -> Result<Point, ParseError> {
+    let invalid = || ParseError::InvalidPoint(line.to_owned());
+    let coordinates = line
+        .split(',')
+        .map(|part| part.trim().parse::<i64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match coordinates[..] {
+        [x, y, z, t] => Ok([x, y, z, t]),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<Point>, ParseError> {
+    input
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_point)
+        .collect()
+}
+
+fn parse_points(input: &str) -> Vec<Point> {
+    parse(input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn constellations(points: &[Point]) -> usize {
+    let mut sets = DisjointSets::new(points.len());
+
+    for (i, a) in points.iter().enumerate() {
+        for (j, b) in points.iter().enumerate().skip(i + 1) {
+            if manhattan_distance(a, b) <= MAX_DISTANCE {
+                sets.union(i, j);
+            }
+        }
+    }
+
+    sets.count
+}
+
+pub fn star_one(input: &str) -> usize {
+    constellations(&parse_points(input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constellations, parse, star_one, DisjointSets, ParseError};
+
+    static EXAMPLE_ONE: &str = "
+ 0,0,0,0
+ 3,0,0,0
+ 0,3,0,0
+ 0,0,3,0
+ 0,0,0,3
+ 0,0,0,6
+ 9,0,0,0
+12,0,0,0";
+
+    static EXAMPLE_TWO: &str = "
+-1,2,2,0
+0,0,2,-2
+0,0,0,-2
+-1,2,0,0
+-2,-2,-2,2
+3,0,2,-1
+-1,3,2,2
+-1,0,-1,0
+0,2,1,-2
+3,0,0,0";
+
+    static EXAMPLE_THREE: &str = "
+1,-1,0,1
+2,0,-1,0
+3,2,-1,0
+0,0,3,1
+0,0,-1,-1
+2,3,-2,0
+-2,2,0,0
+2,-2,0,-1
+1,-1,0,-1
+3,2,0,2";
+
+    static EXAMPLE_FOUR: &str = "
+1,-1,-1,-2
+-2,-2,0,1
+0,2,1,3
+-2,3,-2,1
+0,2,3,-2
+-1,-1,1,-2
+0,-2,-1,0
+-2,2,3,-1
+1,2,2,0
+-1,-2,0,-2";
+
+    #[test]
+    fn test_star_one() {
+        assert_eq!(star_one(EXAMPLE_ONE), 2);
+        assert_eq!(star_one(EXAMPLE_TWO), 4);
+        assert_eq!(star_one(EXAMPLE_THREE), 3);
+        assert_eq!(star_one(EXAMPLE_FOUR), 8);
+    }
+
+    #[test]
+    fn test_constellations() {
+        assert_eq!(constellations(&[]), 0);
+
+        // 6,0,0,0 joins the two halves of the first example
+        let mut points = parse(EXAMPLE_ONE).unwrap();
+        points.push([6, 0, 0, 0]);
+        assert_eq!(constellations(&points), 1);
+    }
+
+    #[test]
+    fn test_disjoint_sets() {
+        let mut sets = DisjointSets::new(5);
+
+        sets.union(0, 1);
+        sets.union(3, 4);
+        sets.union(1, 0);
+        assert_eq!(sets.count, 3);
+        assert_eq!(sets.find(0), sets.find(1));
+        assert_ne!(sets.find(1), sets.find(3));
+
+        sets.union(4, 1);
+        assert_eq!(sets.count, 2);
+        assert_eq!(sets.find(0), sets.find(3));
+        let root = sets.find(0);
+        assert_eq!(sets.sizes[root], 4);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(" 0,-3, 0,12\n").unwrap(), vec![[0, -3, 0, 12]]);
+        assert_eq!(
+            parse("1,2,3"),
+            Err(ParseError::InvalidPoint(String::from("1,2,3")))
+        );
+        assert_eq!(
+            parse("1,2,3,x").unwrap_err().to_string(),
+            "Invalid point `1,2,3,x`"
+        );
+    }
+}
@@ -0,0 +1,515 @@
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+use day05;
+use day09;
+use day09::Rules;
+use day11;
+
+// Fixed so that `check` finds the same failures every time it's run
+const SEED: u64 = 2018;
+
+// Deterministic LCG, a failure can be reproduced from the seed it reports
+pub struct Rng {
+    seed: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.seed = self.seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+
+        self.seed
+    }
+
+    // Uniform enough in `0..max` for generating inputs, the low bits of an LCG
+    // aren't very random so they're dropped.
+    pub fn next(&mut self, max: u64) -> u64 {
+        (self.next_u64() >> 33) % max
+    }
+
+    pub fn range(&mut self, low: usize, high: usize) -> usize {
+        low + self.next((high - low + 1) as u64) as usize
+    }
+}
+
+// Smaller versions of an input to try when it makes two implementations
+// disagree, the ones most likely to be much smaller first.
+pub trait Shrink: Sized {
+    fn shrink(&self) -> Vec<Self>;
+}
+
+impl Shrink for usize {
+    fn shrink(&self) -> Vec<Self> {
+        let mut candidates = vec![];
+        let mut delta = *self;
+
+        while delta > 0 {
+            candidates.push(self - delta);
+            delta /= 2;
+        }
+
+        candidates
+    }
+}
+
+// Removes runs of halving length from everywhere in the string
+impl Shrink for String {
+    fn shrink(&self) -> Vec<Self> {
+        let chars = self.chars().collect::<Vec<_>>();
+        let mut candidates = vec![];
+        let mut length = chars.len();
+
+        while length > 0 {
+            for start in (0..=chars.len() - length).step_by(length) {
+                candidates.push(
+                    chars[..start]
+                        .iter()
+                        .chain(&chars[start + length..])
+                        .collect(),
+                );
+            }
+            length /= 2;
+        }
+
+        candidates
+    }
+}
+
+impl<A: Shrink + Clone, B: Shrink + Clone> Shrink for (A, B) {
+    fn shrink(&self) -> Vec<Self> {
+        let (a, b) = self;
+
+        a.shrink()
+            .into_iter()
+            .map(|a| (a, b.clone()))
+            .chain(b.shrink().into_iter().map(|b| (a.clone(), b)))
+            .collect()
+    }
+}
+
+impl<A: Shrink + Clone, B: Shrink + Clone, C: Shrink + Clone> Shrink for (A, B, C) {
+    fn shrink(&self) -> Vec<Self> {
+        let (a, b, c) = self;
+
+        ((a.clone(), b.clone()), c.clone())
+            .shrink()
+            .into_iter()
+            .map(|((a, b), c)| (a, b, c))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Limit {
+    Iterations(usize),
+    // At least one input is checked no matter how small the budget is
+    Budget(Duration),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Report {
+    pub iterations: usize,
+}
+
+// The generated input the two implementations disagreed on, and the smallest
+// input it shrank to along with what each of them answered for that one.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Failure {
+    pub seed: u64,
+    pub iteration: usize,
+    pub input: String,
+    pub shrunk: String,
+    pub fast: String,
+    pub slow: String,
+}
+
+// Repeatedly replaces the failing input with the first of its shrinks that
+// still fails until none of them do.
+fn shrink<T, R, F, S>(mut input: T, fast: &F, slow: &S) -> T
+where
+    T: Shrink,
+    R: PartialEq,
+    F: Fn(&T) -> R,
+    S: Fn(&T) -> R,
+{
+    while let Some(smaller) = input
+        .shrink()
+        .into_iter()
+        .find(|candidate| fast(candidate) != slow(candidate))
+    {
+        input = smaller;
+    }
+
+    input
+}
+
+pub fn run<T, R, F, S, G>(
+    fast: F,
+    slow: S,
+    mut generator: G,
+    limit: Limit,
+    seed: u64,
+) -> Result<Report, Failure>
+where
+    T: Debug + Shrink,
+    R: Debug + PartialEq,
+    F: Fn(&T) -> R,
+    S: Fn(&T) -> R,
+    G: FnMut(&mut Rng) -> T,
+{
+    let start = Instant::now();
+    let mut rng = Rng::new(seed);
+    let mut iterations = 0;
+
+    while match limit {
+        Limit::Iterations(limit) => iterations < limit,
+        Limit::Budget(budget) => iterations == 0 || start.elapsed() < budget,
+    } {
+        let input = generator(&mut rng);
+
+        if fast(&input) != slow(&input) {
+            let original = format!("{:?}", input);
+            let shrunk = shrink(input, &fast, &slow);
+
+            return Err(Failure {
+                seed,
+                iteration: iterations,
+                input: original,
+                shrunk: format!("{:?}", shrunk),
+                fast: format!("{:?}", fast(&shrunk)),
+                slow: format!("{:?}", slow(&shrunk)),
+            });
+        }
+
+        iterations += 1;
+    }
+
+    Ok(Report { iterations })
+}
+
+pub fn check<T, R, F, S, G>(
+    fast: F,
+    slow: S,
+    generator: G,
+    iterations: usize,
+) -> Result<Report, Failure>
+where
+    T: Debug + Shrink,
+    R: Debug + PartialEq,
+    F: Fn(&T) -> R,
+    S: Fn(&T) -> R,
+    G: FnMut(&mut Rng) -> T,
+{
+    run(fast, slow, generator, Limit::Iterations(iterations), SEED)
+}
+
+// Short polymers over a few units so that most of them react
+fn polymer(rng: &mut Rng) -> String {
+    (0..rng.range(0, 40))
+        .map(|_| {
+            let unit = (b'a' + rng.next(3) as u8) as char;
+
+            if rng.next(2) == 0 {
+                unit.to_ascii_uppercase()
+            } else {
+                unit
+            }
+        }).collect()
+}
+
+fn day05(limit: Limit, seed: u64) -> Result<Report, Failure> {
+    run(
+        |polymer: &String| {
+            (
+                day05::reduction(polymer).polymer,
+                day05::best_removal(polymer).length,
+            )
+        },
+        |polymer| {
+            let shortest = (b'a'..=b'z')
+                .map(|unit| day05::reduce_naive(polymer, Some(unit as char)).len())
+                .min();
+
+            (day05::reduce_naive(polymer, None), shortest.unwrap())
+        },
+        polymer,
+        limit,
+        seed,
+    )
+}
+
+#[derive(Debug, Clone)]
+struct Game {
+    players: usize,
+    last_marble: usize,
+    rules: Rules,
+}
+
+impl Shrink for Game {
+    fn shrink(&self) -> Vec<Self> {
+        let rules = (self.rules.divisor, self.rules.counter_clockwise);
+
+        ((self.players, self.last_marble), rules)
+            .shrink()
+            .into_iter()
+            .filter(|&((players, last_marble), (divisor, _))| {
+                players > 0 && last_marble > 0 && divisor > 0
+            }).map(|((players, last_marble), (divisor, counter_clockwise))| {
+                let rules = Rules {
+                    divisor,
+                    counter_clockwise,
+                };
+
+                Game {
+                    players,
+                    last_marble,
+                    rules,
+                }
+            }).collect()
+    }
+}
+
+// Mostly the puzzle's rules, but also ones that empty the circle
+fn rules(rng: &mut Rng) -> Rules {
+    if rng.next(2) == 0 {
+        Rules::default()
+    } else {
+        Rules {
+            divisor: rng.range(1, 30),
+            counter_clockwise: rng.range(0, 30),
+        }
+    }
+}
+
+fn day09(limit: Limit, seed: u64) -> Result<Report, Failure> {
+    run(
+        |game: &Game| {
+            (
+                day09::play_linked(game.players, game.last_marble, game.rules),
+                day09::play(game.players, game.last_marble, game.rules),
+            )
+        },
+        |game| {
+            let result = day09::play_naive(game.players, game.last_marble, game.rules);
+
+            (result.clone(), result)
+        },
+        |rng| Game {
+            players: rng.range(1, 12),
+            last_marble: rng.range(1, 500),
+            rules: rules(rng),
+        },
+        limit,
+        seed,
+    )
+}
+
+#[derive(Debug, Clone)]
+struct Square {
+    serial: usize,
+    size: usize,
+    window: usize,
+}
+
+impl Shrink for Square {
+    fn shrink(&self) -> Vec<Self> {
+        (self.serial, self.size, self.window)
+            .shrink()
+            .into_iter()
+            .filter(|&(_, size, window)| window > 0 && window <= size)
+            .map(|(serial, size, window)| Square {
+                serial,
+                size,
+                window,
+            }).collect()
+    }
+}
+
+// Every square checked one cell at a time, later squares win ties just like
+// they do with `max_by`.
+fn best_squares_naive(serial: usize, size: usize, window: usize) -> (i64, usize, usize, usize) {
+    let grid = day11::build_grid(serial, size);
+    let windows = if window == 0 {
+        1..=size
+    } else {
+        window..=window
+    };
+    let mut best = None;
+
+    for window in windows {
+        for x in 0..=size - window {
+            for y in 0..=size - window {
+                let power = day11::power(&grid, &(x, y), window);
+
+                if best.is_none_or(|(best_power, _, _, _)| power >= best_power) {
+                    best = Some((power, x + 1, y + 1, window));
+                }
+            }
+        }
+    }
+
+    best.unwrap()
+}
+
+fn day11(limit: Limit, seed: u64) -> Result<Report, Failure> {
+    run(
+        |square: &Square| {
+            let (power, x, y) = day11::best_fixed_window(square.serial, square.size, square.window);
+
+            (
+                (power, x, y, square.window),
+                day11::best_any_window(square.serial, square.size),
+            )
+        },
+        |square| {
+            (
+                best_squares_naive(square.serial, square.size, square.window),
+                best_squares_naive(square.serial, square.size, 0),
+            )
+        },
+        |rng| {
+            let size = rng.range(1, 12);
+
+            Square {
+                serial: rng.range(0, 10_000),
+                size,
+                window: rng.range(1, size),
+            }
+        },
+        limit,
+        seed,
+    )
+}
+
+// Days with both a fast implementation and a slower, simpler one to check it
+// against.
+pub static DAYS: &[&str] = &["day05", "day09", "day11"];
+
+pub fn day(day: &str, limit: Limit, seed: u64) -> Option<Result<Report, Failure>> {
+    match day {
+        "day05" => Some(day05(limit, seed)),
+        "day09" => Some(day09(limit, seed)),
+        "day11" => Some(day11(limit, seed)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, day, run, Failure, Limit, Report, Rng, Shrink, DAYS, SEED};
+    use std::time::Duration;
+
+    #[test]
+    fn test_days() {
+        for name in DAYS {
+            let report = day(name, Limit::Iterations(200), SEED)
+                .unwrap()
+                .unwrap_or_else(|failure| panic!("{} disagrees: {:?}", name, failure));
+
+            assert_eq!(report.iterations, 200);
+        }
+
+        assert_eq!(day("day01", Limit::Iterations(200), SEED), None);
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            check(|&n: &usize| n, |&n| n, |rng| rng.range(0, 100), 50),
+            Ok(Report { iterations: 50 })
+        );
+
+        // Wrong for every number from 37 on
+        let failure = check(
+            |&n: &usize| if n >= 37 { n + 1 } else { n },
+            |&n| n,
+            |rng| rng.range(0, 1000),
+            100,
+        )
+        .unwrap_err();
+        assert_eq!(failure.shrunk, "37");
+        assert_eq!((failure.fast.as_str(), failure.slow.as_str()), ("38", "37"));
+
+        // Wrong whenever an `a` is followed by a `b`
+        let failure = check(
+            |s: &String| s.contains("ab"),
+            |_| false,
+            |rng| {
+                (0..20)
+                    .map(|_| (b'a' + rng.next(3) as u8) as char)
+                    .collect()
+            },
+            100,
+        )
+        .unwrap_err();
+        assert_eq!(failure.shrunk, "\"ab\"");
+        assert_ne!(failure.input, failure.shrunk);
+    }
+
+    #[test]
+    fn test_run() {
+        let failure = run(
+            |&(a, b): &(usize, usize)| a.saturating_sub(b),
+            |&(a, b)| a - b.min(a) + usize::from(b > 50),
+            |rng| (rng.range(0, 100), rng.range(0, 100)),
+            Limit::Budget(Duration::from_secs(10)),
+            7,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            failure,
+            Failure {
+                seed: 7,
+                iteration: failure.iteration,
+                input: failure.input.clone(),
+                shrunk: String::from("(0, 51)"),
+                fast: String::from("0"),
+                slow: String::from("1"),
+            }
+        );
+
+        // Even a zero budget checks something
+        let report = run(
+            |&n: &usize| n,
+            |&n| n,
+            |rng| rng.range(0, 100),
+            Limit::Budget(Duration::from_secs(0)),
+            7,
+        );
+        assert_eq!(report, Ok(Report { iterations: 1 }));
+    }
+
+    #[test]
+    fn test_shrink() {
+        assert_eq!(10.shrink(), vec![0, 5, 8, 9]);
+        assert_eq!(0.shrink(), vec![]);
+        assert_eq!(String::from("abc").shrink(), vec!["", "bc", "ac", "ab"]);
+        assert_eq!((1, 2).shrink(), vec![(0, 2), (1, 0), (1, 1)]);
+        assert_eq!((1, 0, 1).shrink(), vec![(0, 0, 1), (1, 0, 0)]);
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut rng = Rng::new(1);
+        let values = (0..1000).map(|_| rng.range(3, 5)).collect::<Vec<_>>();
+
+        assert!(values.iter().all(|value| (3..=5).contains(value)));
+        assert!((3..=5).all(|value| values.contains(&value)));
+
+        // Seeded tests elsewhere depend on the sequence staying the same
+        let mut rng = Rng::new(2018);
+        assert_eq!(
+            (0..3).map(|_| rng.next_u64()).collect::<Vec<_>>(),
+            vec![
+                3893024420980359355,
+                1865371219141748192,
+                11058844453129291873
+            ]
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+// The device from days 16, 19 and 21. The opcode semantics live here and
+// nowhere else.
+pub type Register = i64;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Opcode {
+    Addr,
+    Addi,
+
+    Mulr,
+    Muli,
+
+    Banr,
+    Bani,
+
+    Borr,
+    Bori,
+
+    Setr,
+    Seti,
+
+    Gtir,
+    Gtri,
+    Gtrr,
+
+    Eqir,
+    Eqri,
+    Eqrr,
+}
+
+pub const OPCODES: [Opcode; 16] = [
+    Opcode::Addr,
+    Opcode::Addi,
+    Opcode::Mulr,
+    Opcode::Muli,
+    Opcode::Banr,
+    Opcode::Bani,
+    Opcode::Borr,
+    Opcode::Bori,
+    Opcode::Setr,
+    Opcode::Seti,
+    Opcode::Gtir,
+    Opcode::Gtri,
+    Opcode::Gtrr,
+    Opcode::Eqir,
+    Opcode::Eqri,
+    Opcode::Eqrr,
+];
+
+// The mnemonics from the puzzle, e.g. `addr`
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    UnknownOpcode(String),
+    InvalidInstruction(String),
+    MissingIpBinding,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownOpcode(mnemonic) => write!(f, "Unknown opcode `{}`", mnemonic),
+            ParseError::InvalidInstruction(line) => write!(f, "Invalid instruction `{}`", line),
+            ParseError::MissingIpBinding => {
+                write!(f, "Expected the program to start with `#ip <register>`")
+            }
+        }
+    }
+}
+
+impl FromStr for Opcode {
+    type Err = ParseError;
+
+    fn from_str(mnemonic: &str) -> Result<Self, ParseError> {
+        OPCODES
+            .iter()
+            .cloned()
+            .find(|opcode| opcode.to_string() == mnemonic)
+            .ok_or_else(|| ParseError::UnknownOpcode(mnemonic.to_owned()))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Operand {
+    Register,
+    Immediate,
+    Ignored,
+}
+
+impl Opcode {
+    // How the first and second operands are interpreted
+    fn operands(self) -> (Operand, Operand) {
+        match self {
+            Opcode::Addr | Opcode::Mulr | Opcode::Banr | Opcode::Borr => {
+                (Operand::Register, Operand::Register)
+            }
+            Opcode::Addi | Opcode::Muli | Opcode::Bani | Opcode::Bori => {
+                (Operand::Register, Operand::Immediate)
+            }
+            Opcode::Setr => (Operand::Register, Operand::Ignored),
+            Opcode::Seti => (Operand::Immediate, Operand::Ignored),
+            Opcode::Gtir | Opcode::Eqir => (Operand::Immediate, Operand::Register),
+            Opcode::Gtri | Opcode::Eqri => (Operand::Register, Operand::Immediate),
+            Opcode::Gtrr | Opcode::Eqrr => (Operand::Register, Operand::Register),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+}
+
+impl Instruction {
+    pub fn new(opcode: Opcode, a: usize, b: usize, c: usize) -> Self {
+        Self { opcode, a, b, c }
+    }
+
+    // Whether every register the instruction touches exists on a machine
+    // with `num_registers` registers.
+    pub fn fits(&self, num_registers: usize) -> bool {
+        let (first, second) = self.opcode.operands();
+
+        (first != Operand::Register || self.a < num_registers)
+            && (second != Operand::Register || self.b < num_registers)
+            && self.c < num_registers
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.opcode, self.a, self.b, self.c)
+    }
+}
+
+// The same format as `Display`, e.g. `addi 0 5 0`
+impl FromStr for Instruction {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, ParseError> {
+        let invalid = || ParseError::InvalidInstruction(line.to_owned());
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+
+        match parts.as_slice() {
+            [mnemonic, a, b, c] => Ok(Instruction::new(
+                mnemonic.parse()?,
+                a.parse().map_err(|_| invalid())?,
+                b.parse().map_err(|_| invalid())?,
+                c.parse().map_err(|_| invalid())?,
+            )),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// A program from day 19 or 21, where the instruction pointer is bound to one
+// of the registers.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Program {
+    pub ip_register: usize,
+    pub instructions: Vec<Instruction>,
+}
+
+impl FromStr for Program {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, ParseError> {
+        let mut lines = input
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty());
+        let ip_register = lines
+            .next()
+            .and_then(|line| line.trim_start_matches("#ip ").parse().ok())
+            .ok_or(ParseError::MissingIpBinding)?;
+        let instructions = lines.map(|line| line.parse()).collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            ip_register,
+            instructions,
+        })
+    }
+}
+
+// Day 16 uses four registers and days 19 and 21 use six
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Machine {
+    registers: Vec<Register>,
+}
+
+impl Machine {
+    pub fn new(num_registers: usize) -> Self {
+        Self {
+            registers: vec![0; num_registers],
+        }
+    }
+
+    pub fn num_registers(&self) -> usize {
+        self.registers.len()
+    }
+
+    pub fn set_register_state(&mut self, values: &[Register]) {
+        assert!(
+            values.len() == self.registers.len(),
+            "Cannot set registers unless length matches"
+        );
+
+        self.registers.copy_from_slice(values);
+    }
+
+    pub fn registers(&self) -> &[Register] {
+        &self.registers
+    }
+
+    fn value(&self, operand: Operand, value: usize) -> Register {
+        match operand {
+            Operand::Register => self.registers[value],
+            Operand::Immediate => value as Register,
+            Operand::Ignored => 0,
+        }
+    }
+
+    pub fn execute(&mut self, instruction: &Instruction) {
+        let (first, second) = instruction.opcode.operands();
+        let a = self.value(first, instruction.a);
+        let b = self.value(second, instruction.b);
+
+        self.registers[instruction.c] = match instruction.opcode {
+            Opcode::Addr | Opcode::Addi => a + b,
+
+            Opcode::Mulr | Opcode::Muli => a * b,
+
+            Opcode::Banr | Opcode::Bani => a & b,
+
+            Opcode::Borr | Opcode::Bori => a | b,
+
+            Opcode::Setr | Opcode::Seti => a,
+
+            Opcode::Gtir | Opcode::Gtri | Opcode::Gtrr => (a > b) as Register,
+
+            Opcode::Eqir | Opcode::Eqri | Opcode::Eqrr => (a == b) as Register,
+        }
+    }
+
+    // Executes every instruction once, in order
+    pub fn run(&mut self, program: &[Instruction]) {
+        self.run_observed(program, &mut |_, _, _| {});
+    }
+
+    // Like `run`, calling `observe` with the index of each instruction and
+    // the machine after executing it.
+    pub fn run_observed(
+        &mut self,
+        program: &[Instruction],
+        observe: &mut dyn FnMut(usize, &Instruction, &Machine),
+    ) {
+        for (idx, instruction) in program.iter().enumerate() {
+            self.execute(instruction);
+            observe(idx, instruction, self);
+        }
+    }
+
+    // Runs a program with a bound instruction pointer until the pointer
+    // leaves the program, returning the number of instructions executed.
+    pub fn run_bound(&mut self, program: &Program) -> usize {
+        let mut executed = 0;
+        self.run_bound_until(program, &mut |_, _| {
+            executed += 1;
+            false
+        });
+
+        executed
+    }
+
+    // Like `run_bound` but `until` is asked before every instruction whether
+    // to stop there. Returns the instruction pointer it stopped at, or `None`
+    // when the program ran to the end.
+    pub fn run_bound_until(
+        &mut self,
+        program: &Program,
+        until: &mut dyn FnMut(usize, &Machine) -> bool,
+    ) -> Option<usize> {
+        let mut ip = Some(0);
+
+        while let Some(current) = ip {
+            self.registers[program.ip_register] = current as Register;
+            if until(current, self) {
+                return Some(current);
+            }
+            ip = self.step_bound(program, current);
+        }
+
+        None
+    }
+
+    // Executes the instruction at `ip` with the instruction pointer bound,
+    // returning the next one or `None` when the program is done.
+    pub fn step_bound(&mut self, program: &Program, ip: usize) -> Option<usize> {
+        let instruction = program.instructions.get(ip)?;
+        self.registers[program.ip_register] = ip as Register;
+        self.execute(instruction);
+
+        let next = self.registers[program.ip_register] + 1;
+        if next < 0 || next as usize >= program.instructions.len() {
+            None
+        } else {
+            Some(next as usize)
+        }
+    }
+}
+
+impl Index<usize> for Machine {
+    type Output = Register;
+
+    fn index(&self, register: usize) -> &Register {
+        &self.registers[register]
+    }
+}
+
+impl IndexMut<usize> for Machine {
+    fn index_mut(&mut self, register: usize) -> &mut Register {
+        &mut self.registers[register]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Instruction, Machine, Opcode, ParseError, Program, OPCODES};
+
+    #[test]
+    fn test_execute() {
+        // The example from day 16, behaves like addi, mulr and seti
+        let matching = OPCODES
+            .iter()
+            .cloned()
+            .filter(|&opcode| {
+                let mut machine = Machine::new(4);
+                machine.set_register_state(&[3, 2, 1, 1]);
+                machine.execute(&Instruction::new(opcode, 2, 1, 2));
+
+                machine.registers() == [3, 2, 2, 1]
+            }).collect::<Vec<_>>();
+
+        assert_eq!(matching, vec![Opcode::Addi, Opcode::Mulr, Opcode::Seti]);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let mut machine = Machine::new(4);
+        machine.set_register_state(&[5, 7, 0, 0]);
+
+        machine.run(&[
+            Instruction::new(Opcode::Gtrr, 1, 0, 2),
+            Instruction::new(Opcode::Eqir, 5, 0, 3),
+        ]);
+        assert_eq!(machine.registers(), &[5, 7, 1, 1]);
+
+        machine.execute(&Instruction::new(Opcode::Gtri, 0, 5, 2));
+        assert_eq!(machine[2], 0);
+    }
+
+    #[test]
+    fn test_run_observed() {
+        let program = [
+            Instruction::new(Opcode::Seti, 7, 0, 1),
+            Instruction::new(Opcode::Addr, 1, 1, 0),
+        ];
+        let mut steps = vec![];
+
+        Machine::new(4).run_observed(&program, &mut |idx, instruction, machine| {
+            steps.push(format!("{} {} {:?}", idx, instruction, machine.registers()))
+        });
+
+        assert_eq!(
+            steps,
+            vec!["0 seti 7 0 1 [0, 7, 0, 0]", "1 addr 1 1 0 [14, 7, 0, 0]"]
+        );
+    }
+
+    #[test]
+    fn test_six_registers() {
+        let mut machine = Machine::new(6);
+        machine.set_register_state(&[0, 1, 2, 3, 4, 5]);
+        machine.execute(&Instruction::new(Opcode::Mulr, 4, 5, 5));
+
+        assert_eq!(machine.num_registers(), 6);
+        assert_eq!(machine.registers(), &[0, 1, 2, 3, 4, 20]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot set registers unless length matches")]
+    fn test_register_count_mismatch() {
+        Machine::new(6).set_register_state(&[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "addi 0 5 0".parse::<Instruction>(),
+            Ok(Instruction::new(Opcode::Addi, 0, 5, 0))
+        );
+        assert_eq!(
+            "addx 0 5 0".parse::<Instruction>(),
+            Err(ParseError::UnknownOpcode(String::from("addx")))
+        );
+        assert_eq!(
+            "addi 0 5".parse::<Instruction>(),
+            Err(ParseError::InvalidInstruction(String::from("addi 0 5")))
+        );
+        assert_eq!(
+            "seti 5 0 1".parse::<Program>(),
+            Err(ParseError::MissingIpBinding)
+        );
+        assert!(OPCODES
+            .iter()
+            .all(|&opcode| opcode.to_string().parse() == Ok(opcode)));
+    }
+
+    #[test]
+    fn test_run_bound() {
+        let program = "#ip 0
+seti 5 0 1
+seti 6 0 2
+addi 0 1 0
+addr 1 2 3
+setr 1 0 0
+seti 8 0 4
+seti 9 0 5"
+            .parse::<Program>()
+            .unwrap();
+        let mut machine = Machine::new(6);
+
+        assert_eq!(program.ip_register, 0);
+        assert_eq!(machine.run_bound(&program), 5);
+        assert_eq!(machine.registers(), &[6, 5, 6, 0, 0, 9]);
+    }
+
+    #[test]
+    fn test_fits() {
+        assert!(Instruction::new(Opcode::Seti, 9, 9, 3).fits(4));
+        assert!(Instruction::new(Opcode::Addi, 3, 9, 0).fits(4));
+        assert!(!Instruction::new(Opcode::Addr, 3, 4, 0).fits(4));
+        assert!(!Instruction::new(Opcode::Setr, 0, 0, 4).fits(4));
+    }
+}
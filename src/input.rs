@@ -0,0 +1,146 @@
+use std::fmt;
+use std::ops::Deref;
+
+const BOM: char = '\u{feff}';
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum InputError {
+    UnexpectedCharacter {
+        day: String,
+        line: usize,
+        column: usize,
+        character: char,
+    },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::UnexpectedCharacter {
+                day,
+                line,
+                column,
+                character,
+            } => write!(
+                f,
+                "Unexpected character {:?} at line {}, column {} of the input for {}",
+                character, line, column, day
+            ),
+        }
+    }
+}
+
+// Puzzle input as the solvers expect it, with `\n` line endings and without a
+// byte order mark. Whitespace is otherwise left alone, the leading spaces on
+// the lines of day 13's tracks are part of the track.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Input(String);
+
+impl Input {
+    pub fn new(raw: &str) -> Self {
+        let raw = raw.strip_prefix(BOM).unwrap_or(raw);
+
+        Input(raw.replace("\r\n", "\n").replace('\r', "\n"))
+    }
+
+    // Also checks that the input only uses the characters the puzzle for
+    // `day` does, which catches the wrong file being passed early.
+    pub fn for_day(day: &str, raw: &str) -> Result<Self, InputError> {
+        let input = Self::new(raw);
+
+        for (idx, line) in input.0.lines().enumerate() {
+            if let Some((column, character)) = line
+                .chars()
+                .enumerate()
+                .find(|&(_, character)| !is_allowed(day, character))
+            {
+                return Err(InputError::UnexpectedCharacter {
+                    day: day.to_owned(),
+                    line: idx + 1,
+                    column: column + 1,
+                    character,
+                });
+            }
+        }
+
+        Ok(input)
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for Input {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Days that aren't listed take their input as parameters rather than a file
+fn is_allowed(day: &str, c: char) -> bool {
+    let any_of = |allowed: &str| allowed.contains(c);
+
+    match day {
+        "day01" => c.is_ascii_digit() || any_of("+-,\t "),
+        "day02" => c.is_ascii_lowercase(),
+        "day03" => c.is_ascii_digit() || any_of("#@,:x "),
+        "day04" => c.is_ascii_alphanumeric() || any_of("[]-:# "),
+        "day05" => c.is_ascii_alphabetic(),
+        "day06" | "day08" | "day25" => c.is_ascii_digit() || any_of("-, "),
+        "day07" => c.is_ascii_alphabetic() || any_of(". "),
+        "day10" => c.is_ascii_alphanumeric() || any_of("<>=,- "),
+        "day12" => c.is_ascii_alphabetic() || any_of("#.=>: "),
+        "day13" => any_of("/\\|-+<>^v "),
+        "day15" => any_of("#.GE"),
+        "day16" => c.is_ascii_alphanumeric() || any_of("[],: "),
+        "day17" | "day22" | "day23" => c.is_ascii_alphanumeric() || any_of("=<>.,:- "),
+        "day18" => any_of(".|#"),
+        "day19" | "day21" => c.is_ascii_alphanumeric() || any_of("# "),
+        "day20" => any_of("^$()|NESW"),
+        "day24" => c.is_ascii_alphanumeric() || any_of("();,: "),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Input, InputError};
+
+    #[test]
+    fn test_new() {
+        assert_eq!(&*Input::new("\u{feff}+1\r\n-2\r\n"), "+1\n-2\n");
+        assert_eq!(&*Input::new("+1\r-2"), "+1\n-2");
+        // Only a leading byte order mark is removed
+        assert_eq!(&*Input::new("a\u{feff}"), "a\u{feff}");
+        assert_eq!(
+            Input::new("  /-\\\r\n  \\-/").into_string(),
+            "  /-\\\n  \\-/"
+        );
+    }
+
+    #[test]
+    fn test_for_day() {
+        assert_eq!(
+            Input::for_day("day13", "\u{feff}   /->-\\\r\n   \\---/\r\n"),
+            Ok(Input::new("   /->-\\\n   \\---/\n"))
+        );
+        assert_eq!(
+            Input::for_day("day01", "+1\n-2\n+x"),
+            Err(InputError::UnexpectedCharacter {
+                day: String::from("day01"),
+                line: 3,
+                column: 2,
+                character: 'x',
+            })
+        );
+        assert_eq!(
+            Input::for_day("day18", ".|#\n.T#").unwrap_err().to_string(),
+            "Unexpected character 'T' at line 2, column 2 of the input for day18"
+        );
+        assert!(Input::for_day("day01", "+1 -2, +3\n").is_ok());
+        assert!(Input::for_day("day09", "anything goes").is_ok());
+    }
+}
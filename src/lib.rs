@@ -2,35 +2,51 @@
 
 use std::time::{SystemTime, UNIX_EPOCH};
 
+extern crate memchr;
+extern crate rayon;
 extern crate regex;
+extern crate unicode_segmentation;
 
 #[macro_use]
 extern crate lazy_static;
 
 mod day01;
-mod day02;
-mod day03;
-mod day04;
-mod day05;
-mod day06;
-mod day07;
-mod day08;
-mod day09;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
+pub mod day02;
+pub mod day03;
+pub mod day04;
+pub mod day05;
+pub mod day06;
+pub mod day07;
+pub mod day08;
+pub mod day09;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
 mod day20;
-mod day21;
-mod day22;
-mod day23;
+pub mod day21;
+pub mod day22;
+pub mod day23;
 mod day24;
+mod day25;
+pub mod answers;
+mod cycle;
+pub mod differential;
+pub mod elfcode;
+pub mod input;
+mod parallel;
+pub mod provider;
+mod search;
+#[cfg(test)]
+mod snapshot;
+pub mod verify;
+pub mod viz;
 
 fn time<F>(label: &str, closure: F)
 where
@@ -51,16 +67,55 @@ where
 #[cfg(test)]
 mod tests {
     use super::time;
+    use answers::{self, Answers};
+    use differential::Rng;
+    use input::Input;
+    use provider::{self, Directory, InputProvider, ProviderError};
+    use verify::{self, Entry, Verdict};
+
+    use std::path::Path;
+
+    lazy_static! {
+        static ref INPUTS: Box<dyn InputProvider + Sync> =
+            provider::from_env(Directory::crate_root());
+        static ref ANSWERS: Answers =
+            answers::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("answers.toml"))
+                .unwrap_or_else(|e| panic!("{}", e));
+        static ref ENTRIES: Vec<Entry> =
+            verify::parse(verify::HASHES).unwrap_or_else(|e| panic!("{}", e));
+    }
 
-    use std::fs::File;
-    use std::io::Read;
+    fn load_file(name: &str) -> String {
+        INPUTS
+            .load(name)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .into_string()
+    }
 
-    fn load_file(path: &str) -> String {
-        let mut input = String::new();
-        let mut f = File::open(path).expect("Unable to open file");
-        f.read_to_string(&mut input).expect("Unable to read string");
+    // For the days without an input in the repository
+    fn try_load_file(name: &str) -> Option<String> {
+        match INPUTS.load(name) {
+            Ok(input) => Some(input.into_string()),
+            Err(ProviderError::NotFound(_)) => None,
+            Err(e) => panic!("{}", e),
+        }
+    }
 
-        input
+    // Compares with the answer in answers.toml, or the salted hash of it for
+    // stars that aren't there. Stars without either aren't checked.
+    fn check<T: ToString>(day: &str, star: usize, actual: T) {
+        let actual = actual.to_string();
+
+        match ANSWERS.get(day, star) {
+            Some(expected) => assert_eq!(actual, expected, "{} star {}", day, star),
+            None => assert!(
+                verify::check(&ENTRIES, day, star, &actual) != Verdict::Incorrect,
+                "{} star {}: {} is incorrect",
+                day,
+                star,
+                actual
+            ),
+        }
     }
 
     #[test]
@@ -69,35 +124,66 @@ mod tests {
 
         let input = load_file("day1.txt");
 
-        assert_eq!(star_one(&input), 510);
-        assert_eq!(star_two(&input), 69074);
+        check("day01", 1, star_one(&input));
+        check("day01", 2, star_two(&input));
     }
     #[test]
     fn solve_day02() {
-        use day02::{star_one, star_two};
+        use day02::{star_one, star_two, star_two_naive, star_two_parallel};
 
         let input = load_file("day2.txt");
 
-        assert_eq!(star_one(&input), 5166);
-        assert_eq!(star_two(&input), "cypueihajytordkgzxfqplbwn");
+        check("day02", 1, star_one(&input));
+        check("day02", 2, star_two(&input));
+        check("day02", 2, star_two_naive(&input));
+        check("day02", 2, star_two_parallel(&input));
     }
     #[test]
     fn solve_day03() {
-        use day03::{star_one, star_two};
+        use day03::{star_one, star_one_hashed, star_two};
 
         let input = load_file("day3.txt");
 
-        assert_eq!(star_one(&input), 110891);
-        assert_eq!(star_two(&input), 297);
+        check("day03", 1, star_one(&input));
+        check("day03", 1, star_one_hashed(&input));
+        check("day03", 2, star_two(&input));
     }
+    #[test]
+    fn bench_day03_claim_parser() {
+        use day03::{parse_claims, star_one};
+
+        // Seeded so the synthetic claims are the same on every run
+        let mut rng = Rng::new(2018);
+        let input = (1..=100_000)
+            .map(|id| {
+                format!(
+                    "#{} @ {},{}: {}x{}",
+                    id,
+                    rng.next(970),
+                    rng.next(970),
+                    rng.next(29) + 1,
+                    rng.next(29) + 1
+                )
+            }).collect::<Vec<_>>()
+            .join("\n");
+
+        time("Day 03 parse 100k claims", || {
+            assert_eq!(parse_claims(&input).map(|c| c.len()), Ok(100_000));
+        });
+
+        time("Day 03 star one 100k claims", || {
+            assert!(star_one(&input) > 0);
+        });
+    }
+
     #[test]
     fn solve_day04() {
         use day04::{star_one, star_two};
 
         let input = load_file("day4.txt");
 
-        assert_eq!(star_one(&input), 19874);
-        assert_eq!(star_two(&input), 22687);
+        check("day04", 1, star_one(&input));
+        check("day04", 2, star_two(&input));
     }
     #[test]
     fn solve_day05() {
@@ -105,8 +191,40 @@ mod tests {
 
         let input = load_file("day5.txt");
 
-        assert_eq!(star_one(&input), 10250);
-        assert_eq!(star_two(&input), 6188);
+        check("day05", 1, star_one(&input));
+        check("day05", 2, star_two(&input));
+    }
+    #[test]
+    fn bench_day05_reducer() {
+        use day05::{reduce_with_rules, reduction, star_two, OppositeCase};
+
+        // A few letters so reactions are frequent
+        let mut rng = Rng::new(2018);
+        let input = (0..1_000_000)
+            .map(|_| {
+                let unit = b'a' + rng.next(4) as u8;
+
+                if rng.next(2) == 0 {
+                    unit.to_ascii_uppercase() as char
+                } else {
+                    unit as char
+                }
+            }).collect::<String>();
+        let expected = reduce_with_rules(&input, &OppositeCase);
+
+        // The char based reducer collects into a `Vec<char>` and then a new
+        // `String`, the byte based one into a single buffer.
+        time("Day 05 reduce 1M units as chars", || {
+            assert_eq!(reduce_with_rules(&input, &OppositeCase), expected);
+        });
+
+        time("Day 05 reduce 1M units as bytes", || {
+            assert_eq!(reduction(&input), expected);
+        });
+
+        time("Day 05 star two 1M units", || {
+            assert!(star_two(&input) <= expected.length);
+        });
     }
     #[test]
     fn solve_day06() {
@@ -114,49 +232,126 @@ mod tests {
 
         let input = load_file("day6.txt");
 
-        assert_eq!(star_one(&input), 3687);
-        assert_eq!(star_two(&input, 10000), 40134);
+        check("day06", 1, star_one(&input));
+        check("day06", 2, star_two(&input, 10000));
     }
     #[test]
     fn solve_day07() {
-        use day07::{star_one, star_two};
+        use day07::{critical_path, star_one, star_two};
 
         let input = load_file("day7.txt");
 
-        assert_eq!(star_one(&input), "EFHLMTKQBWAPGIVXSZJRDUYONC");
-        assert_eq!(star_two(&input, 5, 60), 1056);
+        check("day07", 1, star_one(&input));
+        check("day07", 2, star_two(&input, 5, 60));
+        // With enough workers it all comes down to the critical path, which
+        // for this input is no shorter than with five.
+        check("day07", 2, star_two(&input, 26, 60));
+        check("day07", 2, critical_path(&input, 60).unwrap().duration);
     }
     #[test]
     fn solve_day08() {
-        use day08::{star_one, star_two};
+        use day08::{star_one, star_two, sum_and_value};
 
         let input = load_file("day8.txt");
 
-        assert_eq!(star_one(&input), 40977);
-        assert_eq!(star_two(&input), 27490);
+        check("day08", 1, star_one(&input));
+        check("day08", 2, star_two(&input));
+
+        let (sum, value) = sum_and_value(&input).unwrap();
+        check("day08", 1, sum);
+        check("day08", 2, value);
+    }
+    #[test]
+    fn bench_day08_single_pass() {
+        use day08::{star_one, star_two, sum_and_value};
+
+        // Nodes stop having children once there are enough
+        let mut rng = Rng::new(2018);
+        let mut numbers = vec![3, 3];
+        let mut pending = vec![(3, 3)];
+        let mut node_count = 1;
+        while let Some((remaining_children, metadata_count)) = pending.pop() {
+            if remaining_children > 0 {
+                let child_count = if node_count < 200_000 { rng.range(0, 3) } else { 0 };
+                let child_metadata_count = rng.range(1, 3);
+                numbers.extend(&[child_count, child_metadata_count]);
+                node_count += 1;
+
+                pending.push((remaining_children - 1, metadata_count));
+                pending.push((child_count, child_metadata_count));
+            } else {
+                numbers.extend((0..metadata_count).map(|_| rng.range(1, 5)));
+            }
+        }
+        let input = numbers
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let expected = (star_one(&input), star_two(&input));
+
+        time("Day 08 building the tree", || {
+            assert_eq!((star_one(&input), star_two(&input)), expected);
+        });
+
+        time("Day 08 single pass", || {
+            assert_eq!(sum_and_value(&input), Ok(expected));
+        });
     }
     #[test]
     fn solve_day09() {
-        use day09::solve_efficient;
+        use day09::{solve, solve_efficient};
 
-        assert_eq!(solve_efficient(424, 71144), 405143);
-        assert_eq!(solve_efficient(424, 71144 * 100), 3411514667);
+        check("day09", 1, solve(424, 71144));
+        check("day09", 2, solve(424, 71144 * 100));
+        check("day09", 1, solve_efficient(424, 71144));
+        check("day09", 2, solve_efficient(424, 71144 * 100));
+    }
+    #[test]
+    fn bench_day09_implementations() {
+        use day09::{solve_deque, solve_efficient, solve_naive};
+
+        time("Day 09 naive Vec", || {
+            check("day09", 1, solve_naive(424, 71144));
+        });
+
+        time("Day 09 index linked list", || {
+            check("day09", 1, solve_efficient(424, 71144));
+        });
+
+        time("Day 09 VecDeque", || {
+            check("day09", 1, solve_deque(424, 71144));
+        });
+
+        time("Day 09 index linked list x100", || {
+            check("day09", 2, solve_efficient(424, 71144 * 100));
+        });
+
+        time("Day 09 VecDeque x100", || {
+            check("day09", 2, solve_deque(424, 71144 * 100));
+        });
     }
     #[test]
     fn solve_day10() {
-        use day10::star_one;
+        use day10::{message, render_after, star_one, star_two};
 
         let input = load_file("day10.txt");
         let expected = load_file("day10_expected.txt");
 
-        assert_eq!(star_one(&input, 10081), expected.trim());
+        check("day10", 1, star_one(&input));
+        check("day10", 2, star_two(&input));
+        assert_eq!(message(&input), expected.trim());
+        assert_eq!(render_after(&input, star_two(&input)), expected.trim());
     }
     #[test]
     fn solve_day11() {
         use day11::{star_one, star_two};
 
-        assert_eq!(star_one(2568, 300, 3), (21, 68));
-        assert_eq!(star_two(2568, 300), (90, 201, 15));
+        let (x, y) = star_one(2568, 300, 3);
+        check("day11", 1, format!("{},{}", x, y));
+
+        let (x, y, size) = star_two(2568, 300);
+        check("day11", 2, format!("{},{},{}", x, y, size));
     }
     #[test]
     fn solve_day12() {
@@ -165,11 +360,24 @@ mod tests {
         let initial_state = load_file("day12_initial_state.txt");
         let rules = load_file("day12_rules.txt");
 
-        assert_eq!(star_one(&initial_state, &rules, 3, 20), 2281);
-        assert_eq!(
-            star_two(&initial_state, &rules, 3, 50_000_000_000),
-            2250000000120
-        );
+        check("day12", 1, star_one(&initial_state, &rules, 20));
+        check("day12", 2, star_two(&initial_state, &rules, 50_000_000_000));
+    }
+
+    #[test]
+    fn bench_day12_generations() {
+        use day12::{star_one, star_one_naive};
+
+        let initial_state = load_file("day12_initial_state.txt");
+        let rules = load_file("day12_rules.txt");
+
+        time("Day 12 Vec<bool> 5000 generations", || {
+            assert_eq!(star_one_naive(&initial_state, &rules, 5000), 225120);
+        });
+
+        time("Day 12 packed 5000 generations", || {
+            assert_eq!(star_one(&initial_state, &rules, 5000), 225120);
+        });
     }
     #[test]
     fn solve_day13() {
@@ -177,115 +385,258 @@ mod tests {
 
         let input = load_file("day13.txt");
 
-        assert_eq!(star_one(&input), (83, 121));
-        assert_eq!(star_two(&input), (102, 144));
+        let location = |(x, y): (usize, usize)| format!("{},{}", x, y);
+
+        check("day13", 1, location(star_one(&input)));
+        check("day13", 2, location(star_two(&input)));
     }
     #[test]
     fn solve_day14() {
         use day14::{star_one, star_two};
 
         time("Day 14 part 1", || {
-            assert_eq!(star_one(635041), String::from("1150511382"));
+            check("day14", 1, star_one(635041));
         });
 
         time("Day 14 part 2", || {
-            assert_eq!(star_two(&[6, 3, 5, 0, 4, 1]), 20173656);
+            check("day14", 2, star_two("635041"));
+        });
+    }
+
+    #[test]
+    fn bench_day14_sequence_search() {
+        use day14::{recipes, recipes_before, recipes_before_streaming};
+
+        // A long pattern taken from far into the sequence
+        let sequence = recipes().skip(5_000_000).take(1000).collect::<Vec<_>>();
+
+        time("Day 14 streaming KMP 1000 digit pattern", || {
+            assert_eq!(recipes_before_streaming(&[3, 7], 2, &sequence), 5_000_000);
+        });
+
+        time("Day 14 block memmem 1000 digit pattern", || {
+            assert_eq!(recipes_before(&[3, 7], 2, &sequence), 5_000_000);
+        });
+
+        time("Day 14 streaming KMP puzzle input", || {
+            check(
+                "day14",
+                2,
+                recipes_before_streaming(&[3, 7], 2, &[6, 3, 5, 0, 4, 1]),
+            );
+        });
+
+        time("Day 14 block memmem puzzle input", || {
+            check("day14", 2, recipes_before(&[3, 7], 2, &[6, 3, 5, 0, 4, 1]));
         });
     }
     #[test]
     fn solve_day15() {
-        use day15::{star_one, star_two};
+        use day15::{star_one, star_two, star_two_parallel};
 
         let input = load_file("day15.txt");
 
         time("Day 15 Star 1", || {
-            assert_eq!(star_one(&input), 248235);
+            check("day15", 1, star_one(&input));
         });
 
         time("Day 15 Star 2", || {
-            assert_eq!(star_two(&input), 46784);
+            check("day15", 2, star_two(&input));
+        });
+
+        time("Day 15 Star 2 parallel", || {
+            check("day15", 2, star_two_parallel(&input));
         });
     }
     #[test]
     fn solve_day16() {
-        use day16::{star_one, star_two};
+        use day16::{deduce_opcode_mapping, star_one, star_two, star_two_combined};
 
         let observations = load_file("day16_observations.txt");
         let program = load_file("day16_example_program.txt");
 
-        assert_eq!(star_one(&observations), 596);
-        assert_eq!(star_two(&observations, &program), 554);
+        check("day16", 1, star_one(&observations));
+        assert_eq!(deduce_opcode_mapping(&observations).unwrap().len(), 16);
+        check("day16", 2, star_two(&observations, &program));
+
+        // The puzzle input as it comes, both parts in one file
+        let combined = format!("{}\n\n\n{}", observations.trim_end(), program);
+        check("day16", 1, star_one(&combined));
+        check("day16", 2, star_two_combined(&combined));
     }
     #[test]
     fn solve_day17() {
         use day17::{star_one, star_two};
 
-        let input = load_file("day17.txt");
+        if let Some(input) = try_load_file("day17.txt") {
+            check("day17", 1, star_one(&input));
+            check("day17", 2, star_two(&input));
+        }
+    }
+    #[test]
+    fn bench_day17_deep_input() {
+        use day17::{water, Algorithm};
+
+        // Basins of all sizes scattered below the spring
+        let mut rng = Rng::new(2018);
+        let mut basins = |count: usize, depth: u64| {
+            (0..count)
+                .map(|_| {
+                    let (x, y) = (400 + rng.next(200), 1 + rng.next(depth));
+                    let (width, height) = (2 + rng.next(20), 1 + rng.next(10));
+
+                    let (right, bottom) = (x + width, y + height);
+
+                    format!(
+                        "x={}, y={}..{}\nx={}, y={}..{}\ny={}, x={}..{}",
+                        x, y, bottom, right, y, bottom, bottom, x, right
+                    )
+                }).collect::<Vec<_>>()
+                .join("\n")
+        };
+        let shallow = basins(200, 300);
+        let deep = basins(3000, 5000);
+
+        let expected = water(&shallow, Algorithm::Scanline);
+        time("Day 17 stepwise shallow input", || {
+            assert_eq!(water(&shallow, Algorithm::Stepwise), expected);
+        });
 
-        assert_eq!(star_one(&input), 1);
-        assert_eq!(star_two(&input), 1);
+        time("Day 17 scanline shallow input", || {
+            assert_eq!(water(&shallow, Algorithm::Scanline), expected);
+        });
+
+        time("Day 17 scanline deep input", || {
+            let (reached, retained) = water(&deep, Algorithm::Scanline);
+            assert!(retained > 0 && retained < reached);
+        });
     }
     #[test]
     fn solve_day18() {
         use day18::{star_one, star_two};
 
-        let input = load_file("day18.txt");
+        if let Some(input) = try_load_file("day18.txt") {
+            check("day18", 1, star_one(&input));
+            check("day18", 2, star_two(&input));
+        }
+    }
+    #[test]
+    fn bench_day18_minutes() {
+        use day18::{simulate, simulate_naive};
+
+        // A 50x50 forest like the puzzle input
+        let mut rng = Rng::new(2018);
+        let forest = (0..50)
+            .map(|_| {
+                (0..50)
+                    .map(|_| ['.', '.', '|', '#'][rng.next(4) as usize])
+                    .collect::<String>()
+            }).collect::<Vec<_>>()
+            .join("\n");
+        let expected = simulate_naive(&forest, 1000);
+
+        time("Day 18 Vec<Vec<char>> 1000 minutes", || {
+            assert_eq!(simulate_naive(&forest, 1000), expected);
+        });
 
-        assert_eq!(star_one(&input), 1);
-        assert_eq!(star_two(&input), 1);
+        time("Day 18 packed 1000 minutes", || {
+            assert_eq!(simulate(&forest, 1000), expected);
+        });
     }
     #[test]
     fn solve_day19() {
         use day19::{star_one, star_two};
 
-        let input = load_file("day19.txt");
-
-        assert_eq!(star_one(&input), 1);
-        assert_eq!(star_two(&input), 1);
+        if let Some(input) = try_load_file("day19.txt") {
+            check("day19", 1, star_one(&input));
+            check("day19", 2, star_two(&input));
+        }
     }
     #[test]
     fn solve_day20() {
         use day20::{star_one, star_two};
 
-        let input = load_file("day20.txt");
-
-        assert_eq!(star_one(&input), 1);
-        assert_eq!(star_two(&input), 1);
+        if let Some(input) = try_load_file("day20.txt") {
+            check("day20", 1, star_one(&input));
+            check("day20", 2, star_two(&input));
+        }
     }
     #[test]
     fn solve_day21() {
         use day21::{star_one, star_two};
 
-        let input = load_file("day21.txt");
-
-        assert_eq!(star_one(&input), 1);
-        assert_eq!(star_two(&input), 1);
+        if let Some(input) = try_load_file("day21.txt") {
+            check("day21", 1, star_one(&input));
+            check("day21", 2, star_two(&input));
+        }
     }
     #[test]
     fn solve_day22() {
         use day22::{star_one, star_two};
 
-        let input = load_file("day22.txt");
-
-        assert_eq!(star_one(&input), 1);
-        assert_eq!(star_two(&input), 1);
+        if let Some(input) = try_load_file("day22.txt") {
+            check("day22", 1, star_one(&input));
+            check("day22", 2, star_two(&input));
+        }
     }
     #[test]
     fn solve_day23() {
         use day23::{star_one, star_two};
 
-        let input = load_file("day23.txt");
-
-        assert_eq!(star_one(&input), 1);
-        assert_eq!(star_two(&input), 1);
+        if let Some(input) = try_load_file("day23.txt") {
+            check("day23", 1, star_one(&input));
+            check("day23", 2, star_two(&input));
+        }
     }
     #[test]
     fn solve_day24() {
         use day24::{star_one, star_two};
 
-        let input = load_file("day24.txt");
+        if let Some(input) = try_load_file("day24.txt") {
+            check("day24", 1, star_one(&input));
+            check("day24", 2, star_two(&input));
+        }
+    }
+    #[test]
+    fn validate_inputs() {
+        for day in 1..=25 {
+            if let Some(input) = try_load_file(&format!("day{}.txt", day)) {
+                if let Err(e) = Input::for_day(&format!("day{:02}", day), &input) {
+                    panic!("{}", e);
+                }
+            }
+        }
+    }
+    #[test]
+    fn verify_answers() {
+        use verify::{parse, verify, Verdict, HASHES};
+
+        let entries = parse(HASHES).unwrap();
+
+        for entry in entries.iter().filter(|entry| entry.star == 1) {
+            // `day01` reads `day1.txt`
+            let path = format!("day{}.txt", entry.day[3..].trim_start_matches('0'));
+            let input = match try_load_file(&path) {
+                Some(input) => input,
+                None => continue,
+            };
+
+            for (answer, verdict) in verify(&entries, &entry.day, &input).unwrap() {
+                assert!(
+                    verdict != Verdict::Incorrect,
+                    "{} for {} is incorrect",
+                    answer,
+                    entry.day
+                );
+            }
+        }
+    }
+    #[test]
+    fn solve_day25() {
+        use day25::star_one;
 
-        assert_eq!(star_one(&input), 1);
-        assert_eq!(star_two(&input), 1);
+        if let Some(input) = try_load_file("day25.txt") {
+            check("day25", 1, star_one(&input));
+        }
     }
 }
@@ -0,0 +1,184 @@
+extern crate advent_of_rust_2018;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use advent_of_rust_2018::answers;
+use advent_of_rust_2018::differential::{self, Limit};
+use advent_of_rust_2018::input::Input;
+use advent_of_rust_2018::provider::{self, Directory};
+use advent_of_rust_2018::{verify, viz};
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: advent-of-rust-2018 viz <day> <input> \
+         [--image <output.ppm> | --animate | --frames <directory>]\n       \
+         advent-of-rust-2018 verify <day> <input>\n       \
+         advent-of-rust-2018 hash <day> <input>\n       \
+         advent-of-rust-2018 stress <day> [<seconds> [<seed>]]"
+    );
+    process::exit(1);
+}
+
+fn exit_with<E: ToString, T>(error: E) -> T {
+    eprintln!("{}", error.to_string());
+    process::exit(1);
+}
+
+// Paths are relative to the working directory, inputs that aren't there are
+// downloaded when AOC_SESSION is set.
+fn load_file(day: &str, path: &str) -> Input {
+    let input = provider::from_env(Directory::new("."))
+        .load(path)
+        .unwrap_or_else(exit_with);
+
+    Input::for_day(day, &input).unwrap_or_else(exit_with)
+}
+
+fn no_renderer<T>(day: &str) -> T {
+    eprintln!("No renderer for {}", day);
+    process::exit(1);
+}
+
+fn no_solver<T>(day: &str) -> T {
+    eprintln!("No solver for {}", day);
+    process::exit(1);
+}
+
+fn verify(args: &[String]) {
+    if args.len() != 2 {
+        usage();
+    }
+    let (day, input) = (&args[0], load_file(&args[0], &args[1]));
+    let entries = verify::parse(verify::HASHES).unwrap_or_else(|e| panic!("{}", e));
+    let expected = answers::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("answers.toml"))
+        .unwrap_or_else(|e| panic!("{}", e));
+    let mut results = verify::verify(&entries, day, &input).unwrap_or_else(|| no_solver(day));
+
+    // Answers in answers.toml take precedence over the hashes, they're the
+    // ones for whatever input is being used.
+    for (idx, (answer, verdict)) in results.iter_mut().enumerate() {
+        match expected.check(day, idx + 1, answer) {
+            verify::Verdict::Unknown => (),
+            plain => *verdict = plain,
+        }
+
+        println!("{} star {}: {} ({})", day, idx + 1, answer, verdict);
+    }
+
+    if results
+        .iter()
+        .any(|&(_, verdict)| verdict == verify::Verdict::Incorrect)
+    {
+        process::exit(1);
+    }
+}
+
+fn hash(args: &[String]) {
+    if args.len() != 2 {
+        usage();
+    }
+    let (day, input) = (&args[0], load_file(&args[0], &args[1]));
+
+    for entry in verify::entries(day, &input).unwrap_or_else(|| no_solver(day)) {
+        println!("{}", entry);
+    }
+}
+
+// Runs for as long as it's asked to, with a different seed every time unless
+// one is given to reproduce a failure.
+fn stress(args: &[String]) {
+    if args.is_empty() || args.len() > 3 {
+        usage();
+    }
+    let day = &args[0];
+    let seconds = args.get(1).map_or(Ok(10), |s| s.parse::<u64>());
+    let seed = args.get(2).map_or_else(
+        || {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            Ok(now.as_secs() ^ u64::from(now.subsec_nanos()))
+        },
+        |s| s.parse::<u64>(),
+    );
+    let (seconds, seed) = match (seconds, seed) {
+        (Ok(seconds), Ok(seed)) => (seconds, seed),
+        _ => usage(),
+    };
+
+    match differential::day(day, Limit::Budget(Duration::from_secs(seconds)), seed) {
+        None => {
+            eprintln!("No reference implementation for {}", day);
+            process::exit(1);
+        }
+        Some(Ok(report)) => println!(
+            "{}: {} inputs agreed (seed {})",
+            day, report.iterations, seed
+        ),
+        Some(Err(failure)) => {
+            eprintln!(
+                "{}: input {} of seed {} disagrees\n  input: {}\n  shrunk: {}\n  fast: {}\n  slow: {}",
+                day,
+                failure.iteration,
+                failure.seed,
+                failure.input,
+                failure.shrunk,
+                failure.fast,
+                failure.slow
+            );
+            process::exit(1);
+        }
+    }
+}
+
+fn viz(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let (day, input) = (&args[0], load_file(&args[0], &args[1]));
+
+    match &args[2..] {
+        [] => {
+            let output = viz::render(day, &input).unwrap_or_else(|| no_renderer(day));
+            println!("{}", output);
+        }
+        [flag, path] if flag == "--image" => {
+            let image = viz::render_image(day, &input).unwrap_or_else(|| no_renderer(day));
+            let mut f = File::create(path).expect("Unable to create image file");
+            f.write_all(&image).expect("Unable to write image");
+        }
+        [flag] if flag == "--animate" => {
+            for frame in viz::frames(day, &input).unwrap_or_else(|| no_renderer(day)) {
+                // Clear the terminal and move the cursor to the top left
+                println!("\x1b[2J\x1b[H{}", frame);
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+        [flag, directory] if flag == "--frames" => {
+            let frames = viz::image_frames(day, &input).unwrap_or_else(|| no_renderer(day));
+
+            for (idx, frame) in frames.enumerate() {
+                let path = format!("{}/frame_{:05}.ppm", directory, idx);
+                let mut f = File::create(&path).expect("Unable to create frame file");
+                f.write_all(&frame).expect("Unable to write frame");
+            }
+        }
+        _ => usage(),
+    }
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    match args.first().map(|s| s.as_str()) {
+        Some("viz") => viz(&args[1..]),
+        Some("verify") => verify(&args[1..]),
+        Some("hash") => hash(&args[1..]),
+        Some("stress") => stress(&args[1..]),
+        _ => usage(),
+    }
+}
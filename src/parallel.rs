@@ -0,0 +1,29 @@
+use std::env;
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+// All parallel solvers run on this pool so the degree of parallelism can be
+// controlled in one place. `AOC_THREADS` overrides the number of worker
+// threads, when unset or 0 rayon picks one per logical CPU.
+lazy_static! {
+    static ref POOL: ThreadPool = ThreadPoolBuilder::new()
+        .num_threads(num_threads())
+        .thread_name(|idx| format!("aoc-worker-{}", idx))
+        .build()
+        .expect("Failed to build the shared thread pool");
+}
+
+pub fn num_threads() -> usize {
+    env::var("AOC_THREADS")
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
+pub fn install<F, R>(op: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
+    POOL.install(op)
+}
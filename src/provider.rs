@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use input::Input;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ProviderError {
+    NotFound(String),
+    Unreadable(String, String),
+    DownloadFailed(String, String),
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::NotFound(name) => write!(f, "No input called `{}`", name),
+            ProviderError::Unreadable(name, reason) => {
+                write!(f, "Unable to read `{}`: {}", name, reason)
+            }
+            ProviderError::DownloadFailed(name, reason) => {
+                write!(f, "Unable to download `{}`: {}", name, reason)
+            }
+        }
+    }
+}
+
+// Somewhere puzzle inputs come from, looked up by their file name such as
+// `day1.txt` or `day12_rules.txt`.
+pub trait InputProvider {
+    fn load(&self, name: &str) -> Result<Input, ProviderError>;
+}
+
+// Files in a directory on disk
+pub struct Directory {
+    root: PathBuf,
+}
+
+impl Directory {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    // Where the inputs in this repository are, no matter which directory the
+    // tests or the binary happen to be run from.
+    pub fn crate_root() -> Self {
+        Self::new(env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+}
+
+impl InputProvider for Directory {
+    fn load(&self, name: &str) -> Result<Input, ProviderError> {
+        match fs::read_to_string(self.path(name)) {
+            Ok(raw) => Ok(Input::new(&raw)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                Err(ProviderError::NotFound(name.to_owned()))
+            }
+            Err(e) => Err(ProviderError::Unreadable(name.to_owned(), e.to_string())),
+        }
+    }
+}
+
+// Inputs compiled into the binary with `include_str!`
+pub struct Embedded {
+    files: &'static [(&'static str, &'static str)],
+}
+
+impl Embedded {
+    pub fn new(files: &'static [(&'static str, &'static str)]) -> Self {
+        Self { files }
+    }
+}
+
+impl InputProvider for Embedded {
+    fn load(&self, name: &str) -> Result<Input, ProviderError> {
+        self.files
+            .iter()
+            .find(|&&(file, _)| file == name)
+            .map(|&(_, contents)| Input::new(contents))
+            .ok_or_else(|| ProviderError::NotFound(name.to_owned()))
+    }
+}
+
+// Inputs built by the caller, for tests that generate their own
+#[derive(Default)]
+pub struct InMemory {
+    files: HashMap<String, String>,
+}
+
+impl InMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &str, contents: &str) {
+        self.files.insert(name.to_owned(), contents.to_owned());
+    }
+}
+
+impl InputProvider for InMemory {
+    fn load(&self, name: &str) -> Result<Input, ProviderError> {
+        self.files
+            .get(name)
+            .map(|contents| Input::new(contents))
+            .ok_or_else(|| ProviderError::NotFound(name.to_owned()))
+    }
+}
+
+// Downloads inputs from adventofcode.com with the session cookie of a logged
+// in user, keeping them in `cache` so each one is only downloaded once. Uses
+// curl rather than pulling in an HTTP client.
+pub struct Download {
+    session: String,
+    cache: Directory,
+}
+
+impl Download {
+    pub fn new(session: &str, cache: Directory) -> Self {
+        Self {
+            session: session.to_owned(),
+            cache,
+        }
+    }
+}
+
+// Only the main input of each day, `day7.txt`, can be downloaded
+fn input_url(name: &str) -> Option<String> {
+    let day = name
+        .strip_prefix("day")?
+        .strip_suffix(".txt")?
+        .parse::<usize>()
+        .ok()
+        .filter(|day| (1..=25).contains(day))?;
+
+    Some(format!("https://adventofcode.com/2018/day/{}/input", day))
+}
+
+// Quoted as a string in the format of curl config files. Control characters
+// such as newlines can't be quoted and would end the option early.
+fn curl_config(session: &str) -> Option<String> {
+    if session.chars().any(char::is_control) {
+        return None;
+    }
+
+    let session = session.replace('\\', "\\\\").replace('"', "\\\"");
+
+    Some(format!("cookie = \"session={}\"\n", session))
+}
+
+impl InputProvider for Download {
+    fn load(&self, name: &str) -> Result<Input, ProviderError> {
+        match self.cache.load(name) {
+            Err(ProviderError::NotFound(_)) => (),
+            cached => return cached,
+        }
+
+        let failed = |reason: String| ProviderError::DownloadFailed(name.to_owned(), reason);
+        let url = input_url(name).ok_or_else(|| ProviderError::NotFound(name.to_owned()))?;
+        let config = curl_config(&self.session)
+            .ok_or_else(|| failed(String::from("The session contains control characters")))?;
+        // The cookie is passed as a config on stdin, other users can see the
+        // arguments of running processes.
+        let mut curl = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--config", "-"])
+            .arg(&url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| failed(e.to_string()))?;
+        curl.stdin
+            .take()
+            .unwrap()
+            .write_all(config.as_bytes())
+            .map_err(|e| failed(e.to_string()))?;
+        let output = curl.wait_with_output().map_err(|e| failed(e.to_string()))?;
+
+        if !output.status.success() {
+            return Err(failed(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ));
+        }
+
+        let raw = String::from_utf8(output.stdout).map_err(|e| failed(e.to_string()))?;
+        fs::write(self.cache.path(name), &raw)
+            .map_err(|e| ProviderError::Unreadable(name.to_owned(), e.to_string()))?;
+
+        Ok(Input::new(&raw))
+    }
+}
+
+// `directory`, from which missing inputs are downloaded when there's an
+// AOC_SESSION to download them with.
+pub fn from_env(directory: Directory) -> Box<dyn InputProvider + Sync> {
+    match env::var("AOC_SESSION") {
+        Ok(ref session) if !session.is_empty() => Box::new(Download::new(session, directory)),
+        _ => Box::new(directory),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        curl_config, input_url, Directory, Download, Embedded, InMemory, InputProvider,
+        ProviderError,
+    };
+    use std::env;
+
+    static FIXTURES: &[(&str, &str)] = &[("day1.txt", include_str!("../day1.txt"))];
+
+    #[test]
+    fn test_directory() {
+        let day1 = Directory::crate_root().load("day1.txt").unwrap();
+        assert_eq!(day1.lines().next(), Some("+16"));
+
+        // Relative to the working directory, which isn't necessarily the
+        // crate root.
+        let relative = Directory::new(".").load("day1.txt");
+        if env::current_dir().unwrap().join("day1.txt").exists() {
+            assert_eq!(relative, Ok(day1));
+        } else {
+            assert!(relative.is_err());
+        }
+
+        assert_eq!(
+            Directory::crate_root().load("day99.txt"),
+            Err(ProviderError::NotFound(String::from("day99.txt")))
+        );
+    }
+
+    #[test]
+    fn test_embedded() {
+        let embedded = Embedded::new(FIXTURES);
+
+        assert_eq!(
+            embedded.load("day1.txt"),
+            Directory::crate_root().load("day1.txt")
+        );
+        assert!(embedded.load("day2.txt").is_err());
+    }
+
+    #[test]
+    fn test_in_memory() {
+        let mut files = InMemory::new();
+        files.insert("day1.txt", "+1\r\n-2\r\n");
+
+        assert_eq!(&*files.load("day1.txt").unwrap(), "+1\n-2\n");
+        assert_eq!(
+            files.load("day2.txt").unwrap_err().to_string(),
+            "No input called `day2.txt`"
+        );
+    }
+
+    #[test]
+    fn test_download() {
+        assert_eq!(
+            input_url("day7.txt"),
+            Some(String::from("https://adventofcode.com/2018/day/7/input"))
+        );
+        assert_eq!(input_url("day12_rules.txt"), None);
+        assert_eq!(input_url("day26.txt"), None);
+        assert_eq!(
+            curl_config("53616c74"),
+            Some(String::from("cookie = \"session=53616c74\"\n"))
+        );
+        assert_eq!(
+            curl_config("a\"b\\"),
+            Some(String::from("cookie = \"session=a\\\"b\\\\\"\n"))
+        );
+        assert_eq!(curl_config("53616c74\nurl = \"file:///\""), None);
+        assert_eq!(curl_config("53616c74\r"), None);
+
+        // Rejected before curl is started
+        let download = Download::new(
+            "53616c74\noutput = \"/tmp/x\"",
+            Directory::new(env::temp_dir().join("provider_test_no_cache")),
+        );
+        assert_eq!(
+            download.load("day7.txt"),
+            Err(ProviderError::DownloadFailed(
+                String::from("day7.txt"),
+                String::from("The session contains control characters")
+            ))
+        );
+
+        // Cached inputs are used as they are, without a valid session
+        let download = Download::new("", Directory::crate_root());
+        assert_eq!(
+            download.load("day1.txt"),
+            Directory::crate_root().load("day1.txt")
+        );
+        assert_eq!(
+            download.load("day12_missing.txt"),
+            Err(ProviderError::NotFound(String::from("day12_missing.txt")))
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+
+// Finds the cheapest way from `start` to a state where `is_goal` holds.
+// `neighbours` gives the states reachable in one step and what the step
+// costs, `heuristic` must never overestimate the remaining cost. Returns every
+// state on the way together with the total cost of reaching it.
+pub fn a_star<S, N, H, G>(
+    start: S,
+    mut neighbours: N,
+    heuristic: H,
+    is_goal: G,
+) -> Option<Vec<(S, usize)>>
+where
+    S: Clone + Eq + Hash + Ord,
+    N: FnMut(&S) -> Vec<(S, usize)>,
+    H: Fn(&S) -> usize,
+    G: Fn(&S) -> bool,
+{
+    let mut costs = HashMap::new();
+    let mut previous = HashMap::new();
+    let mut to_visit = BinaryHeap::new();
+
+    costs.insert(start.clone(), 0);
+    to_visit.push(Reverse((heuristic(&start), 0, start)));
+
+    while let Some(Reverse((_, cost, state))) = to_visit.pop() {
+        if costs.get(&state).is_some_and(|&best| best < cost) {
+            continue;
+        }
+
+        if is_goal(&state) {
+            let mut path = vec![(state.clone(), cost)];
+            while let Some(before) = previous.get(&path[path.len() - 1].0) {
+                path.push((S::clone(before), costs[before]));
+            }
+            path.reverse();
+
+            return Some(path);
+        }
+
+        for (next, step) in neighbours(&state) {
+            let next_cost = cost + step;
+
+            if costs.get(&next).is_none_or(|&best| next_cost < best) {
+                costs.insert(next.clone(), next_cost);
+                previous.insert(next.clone(), state.clone());
+                to_visit.push(Reverse((next_cost + heuristic(&next), next_cost, next)));
+            }
+        }
+    }
+
+    None
+}
+
+pub fn dijkstra<S, N, G>(start: S, neighbours: N, is_goal: G) -> Option<Vec<(S, usize)>>
+where
+    S: Clone + Eq + Hash + Ord,
+    N: FnMut(&S) -> Vec<(S, usize)>,
+    G: Fn(&S) -> bool,
+{
+    a_star(start, neighbours, |_| 0, is_goal)
+}
+
+// Smallest value from `low` upwards for which `succeeds` holds. An upper bound
+// is found by doubling and then narrowed down by binary search, which assumes
+// that larger values keep succeeding. That's roughly `2 * log2(result)` calls
+// of `succeeds`, callers that can't rely on the assumption have to check the
+// values below the result themselves.
+pub fn first_success<F>(low: usize, succeeds: &mut F) -> usize
+where
+    F: FnMut(usize) -> bool,
+{
+    // Everything below `unchecked` fails or is below `low`
+    let mut unchecked = low;
+    let mut high = low;
+    while !succeeds(high) {
+        unchecked = high + 1;
+        high = (high * 2).max(1);
+    }
+
+    let (mut search_low, mut search_high) = (unchecked, high);
+    while search_low < search_high {
+        let mid = search_low + (search_high - search_low) / 2;
+
+        if succeeds(mid) {
+            search_high = mid;
+        } else {
+            search_low = mid + 1;
+        }
+    }
+
+    search_low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{a_star, dijkstra, first_success};
+    use differential::Rng;
+
+    // Walls are `#`, moving onto a digit costs that much and anything else
+    // costs one.
+    static MAZE: &str = "
+S..#....
+.#.#.##.
+.#...#..
+.####.#.
+...9.#.G";
+
+    fn neighbours(maze: &[Vec<char>], (x, y): (usize, usize)) -> Vec<((usize, usize), usize)> {
+        let candidates = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+
+        candidates
+            .iter()
+            .filter_map(|&(x, y)| {
+                let tile = *maze.get(y)?.get(x)?;
+
+                match tile {
+                    '#' => None,
+                    _ => Some(((x, y), tile.to_digit(10).map_or(1, |cost| cost as usize))),
+                }
+            }).collect()
+    }
+
+    fn parse(maze: &str) -> Vec<Vec<char>> {
+        maze.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let maze = parse(MAZE);
+        let path = dijkstra((0, 0), |&at| neighbours(&maze, at), |&at| at == (7, 4)).unwrap();
+
+        assert_eq!(path.first(), Some(&((0, 0), 0)));
+        assert_eq!(path.last(), Some(&((7, 4), 15)));
+        assert!(path.windows(2).all(|pair| {
+            let ((from, cost), (to, next_cost)) = (pair[0], pair[1]);
+            neighbours(&maze, from).contains(&(to, next_cost - cost))
+        }));
+        assert_eq!(
+            dijkstra((0, 0), |&at| neighbours(&maze, at), |&at| at == (5, 3)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_a_star() {
+        // A* with the Manhattan distance has to agree with Dijkstra on the
+        // cost of every random maze.
+        let mut rng = Rng::new(22);
+        for _ in 0..50 {
+            let maze = (0..12)
+                .map(|_| {
+                    (0..12)
+                        .map(|_| ['.', '.', '.', '#', '5'][rng.next(5) as usize])
+                        .collect()
+                }).collect::<Vec<Vec<_>>>();
+            let goal = (11, 11);
+            let manhattan = |&(x, y): &(usize, usize)| (goal.0 - x) + (goal.1 - y);
+
+            let expected = dijkstra((0, 0), |&at| neighbours(&maze, at), |&at| at == goal);
+            let actual = a_star(
+                (0, 0),
+                |&at| neighbours(&maze, at),
+                manhattan,
+                |&at| at == goal,
+            );
+
+            assert_eq!(
+                actual.map(|path| path[path.len() - 1].1),
+                expected.map(|path| path[path.len() - 1].1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_first_success() {
+        assert_eq!(first_success(4, &mut |value| value >= 4), 4);
+        assert_eq!(first_success(4, &mut |value| value >= 13), 13);
+        assert_eq!(first_success(4, &mut |value| value >= 200), 200);
+
+        // The binary search never looks at 17
+        assert_eq!(
+            first_success(4, &mut |value| value >= 20 || value == 17),
+            20
+        );
+
+        let mut calls = 0;
+        first_success(4, &mut |value| {
+            calls += 1;
+            value >= 200
+        });
+        assert_eq!(calls, 14);
+
+        assert_eq!(first_success(0, &mut |_| true), 0);
+        assert_eq!(first_success(0, &mut |value| value >= 1), 1);
+        assert_eq!(first_success(0, &mut |value| value >= 51), 51);
+    }
+}
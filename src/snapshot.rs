@@ -0,0 +1,140 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Snapshots live in `snapshots/` at the root of the crate regardless of where
+// the tests are run from.
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots")
+}
+
+fn path(root: &Path, name: &str, extension: &str) -> PathBuf {
+    root.join(format!("{}.{}", name, extension))
+}
+
+// Every line of `expected` and `actual` side by side, with the ones that
+// differ marked by `-` and `+`.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    let mut lines = vec![];
+
+    for idx in 0..expected.len().max(actual.len()) {
+        match (expected.get(idx), actual.get(idx)) {
+            (Some(before), Some(after)) if before == after => lines.push(format!("  {}", before)),
+            (before, after) => {
+                if let Some(before) = before {
+                    lines.push(format!("- {}", before));
+                }
+                if let Some(after) = after {
+                    lines.push(format!("+ {}", after));
+                }
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+// Compares `actual` with `snapshots/<name>.snap`. A missing or changed
+// snapshot writes `snapshots/<name>.snap.new` next to it and fails, either
+// move it over the old one or run the tests with `UPDATE_SNAPSHOTS=1` to
+// accept the change.
+pub fn assert_snapshot(name: &str, actual: &str) {
+    assert_snapshot_in(
+        &root(),
+        name,
+        actual,
+        env::var_os("UPDATE_SNAPSHOTS").is_some(),
+    );
+}
+
+fn assert_snapshot_in(root: &Path, name: &str, actual: &str, update: bool) {
+    let snapshot = path(root, name, "snap");
+    let pending = path(root, name, "snap.new");
+    let actual = format!("{}\n", actual.trim_end_matches('\n'));
+    let expected = fs::read_to_string(&snapshot).ok();
+
+    if expected.as_ref() == Some(&actual) {
+        let _ = fs::remove_file(&pending);
+        return;
+    }
+
+    fs::create_dir_all(root).expect("Unable to create snapshots");
+
+    if update {
+        fs::write(&snapshot, &actual).expect("Unable to write snapshot");
+        let _ = fs::remove_file(&pending);
+        return;
+    }
+
+    fs::write(&pending, &actual).expect("Unable to write snapshot");
+    match expected {
+        Some(expected) => panic!(
+            "Snapshot `{}` changed, the new one is in {}\n{}",
+            name,
+            pending.display(),
+            diff(&expected, &actual)
+        ),
+        None => panic!(
+            "Snapshot `{}` doesn't exist, the new one is in {}",
+            name,
+            pending.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assert_snapshot_in, diff, path};
+    use std::env;
+    use std::fs;
+    use std::panic;
+    use std::process;
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("#.#\n...", "#.#\n..."), "  #.#\n  ...");
+        assert_eq!(
+            diff("#.#\n...\n###", "#|#\n..."),
+            "- #.#\n+ #|#\n  ...\n- ###"
+        );
+    }
+
+    #[test]
+    fn test_assert_snapshot() {
+        let root = env::temp_dir().join(format!("snapshot_self_test_{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let name = "grid";
+
+        // A missing snapshot fails unless snapshots are being updated
+        let missing = panic::catch_unwind(|| assert_snapshot_in(&root, name, "#.#\n...", false));
+        assert!(missing.is_err());
+        assert!(!path(&root, name, "snap").exists());
+        assert_eq!(
+            fs::read_to_string(path(&root, name, "snap.new")).unwrap(),
+            "#.#\n...\n"
+        );
+
+        assert_snapshot_in(&root, name, "#.#\n...", true);
+        assert_eq!(
+            fs::read_to_string(path(&root, name, "snap")).unwrap(),
+            "#.#\n...\n"
+        );
+        assert!(!path(&root, name, "snap.new").exists());
+        assert_snapshot_in(&root, name, "#.#\n...\n", false);
+
+        let changed = panic::catch_unwind(|| assert_snapshot_in(&root, name, "#|#\n...", false));
+        assert!(changed.is_err());
+        assert_eq!(
+            fs::read_to_string(path(&root, name, "snap.new")).unwrap(),
+            "#|#\n...\n"
+        );
+
+        // Matching again cleans up the rejected snapshot
+        assert_snapshot_in(&root, name, "#.#\n...", false);
+        assert!(!path(&root, name, "snap.new").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
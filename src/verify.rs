@@ -0,0 +1,317 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use day01;
+use day02;
+use day03;
+use day04;
+use day05;
+use day06;
+use day07;
+use day08;
+use day10;
+use day13;
+use day15;
+use day17;
+use day18;
+use day19;
+use day21;
+use day22;
+use day23;
+use day24;
+use day25;
+use differential::Rng;
+
+// Salted hashes of known answers, one `<day> <star> <salt> <hash>` per line.
+// The salt keeps equal answers from having equal hashes, though small numeric
+// answers can of course still be found by trying them all.
+pub static HASHES: &str = include_str!("../answers.hashes");
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    InvalidEntry(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidEntry(line) => write!(f, "Invalid answer hash `{}`", line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Verdict {
+    Correct,
+    Incorrect,
+    // There's no hash for this star
+    Unknown,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "correct"),
+            Verdict::Incorrect => write!(f, "incorrect"),
+            Verdict::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Entry {
+    pub day: String,
+    pub star: usize,
+    pub salt: u64,
+    pub hash: u64,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {:016x} {:016x}",
+            self.day, self.star, self.salt, self.hash
+        )
+    }
+}
+
+// 64 bit FNV-1a, unlike the standard library's hasher it's guaranteed to stay
+// the same between Rust versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+pub fn hash(salt: u64, day: &str, star: usize, answer: &str) -> u64 {
+    fnv1a(format!("{:016x}:{}:{}:{}", salt, day, star, answer.trim()).as_bytes())
+}
+
+fn parse_entry(line: &str) -> Result<Entry, ParseError> {
+    let invalid = || ParseError::InvalidEntry(line.to_owned());
+    let parts = line.split_whitespace().collect::<Vec<_>>();
+    let hex = |part: &str| u64::from_str_radix(part, 16).map_err(|_| invalid());
+
+    match parts[..] {
+        [day, star, salt, hash] => Ok(Entry {
+            day: day.to_owned(),
+            star: star.parse().map_err(|_| invalid())?,
+            salt: hex(salt)?,
+            hash: hex(hash)?,
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+pub fn parse(hashes: &str) -> Result<Vec<Entry>, ParseError> {
+    hashes
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_entry)
+        .collect()
+}
+
+// The answer to every star of `day` for the days that are solved from a single
+// input file, with the parameters the puzzles use.
+pub fn answers(day: &str, input: &str) -> Option<Vec<String>> {
+    let location = |(x, y): (usize, usize)| format!("{},{}", x, y);
+
+    let answers = match day {
+        "day01" => vec![
+            day01::star_one(input).to_string(),
+            day01::star_two(input).to_string(),
+        ],
+        "day02" => vec![day02::star_one(input).to_string(), day02::star_two(input)],
+        "day03" => vec![
+            day03::star_one(input).to_string(),
+            day03::star_two(input).to_string(),
+        ],
+        "day04" => vec![
+            day04::star_one(input).to_string(),
+            day04::star_two(input).to_string(),
+        ],
+        "day05" => vec![
+            day05::star_one(input).to_string(),
+            day05::star_two(input).to_string(),
+        ],
+        "day06" => vec![
+            day06::star_one(input).to_string(),
+            day06::star_two(input, 10000).to_string(),
+        ],
+        "day07" => vec![
+            day07::star_one(input),
+            day07::star_two(input, 5, 60).to_string(),
+        ],
+        "day08" => vec![
+            day08::star_one(input).to_string(),
+            day08::star_two(input).to_string(),
+        ],
+        "day10" => vec![day10::star_one(input), day10::star_two(input).to_string()],
+        "day13" => vec![
+            location(day13::star_one(input)),
+            location(day13::star_two(input)),
+        ],
+        "day15" => vec![
+            day15::star_one(input).to_string(),
+            day15::star_two(input).to_string(),
+        ],
+        "day17" => vec![
+            day17::star_one(input).to_string(),
+            day17::star_two(input).to_string(),
+        ],
+        "day18" => vec![
+            day18::star_one(input).to_string(),
+            day18::star_two(input).to_string(),
+        ],
+        "day19" => vec![
+            day19::star_one(input).to_string(),
+            day19::star_two(input).to_string(),
+        ],
+        "day21" => vec![
+            day21::star_one(input).to_string(),
+            day21::star_two(input).to_string(),
+        ],
+        "day22" => vec![
+            day22::star_one(input).to_string(),
+            day22::star_two(input).to_string(),
+        ],
+        "day23" => vec![
+            day23::star_one(input).to_string(),
+            day23::star_two(input).to_string(),
+        ],
+        "day24" => vec![
+            day24::star_one(input).to_string(),
+            day24::star_two(input).to_string(),
+        ],
+        "day25" => vec![day25::star_one(input).to_string()],
+        _ => return None,
+    };
+
+    Some(answers)
+}
+
+pub fn check(entries: &[Entry], day: &str, star: usize, answer: &str) -> Verdict {
+    match entries
+        .iter()
+        .find(|entry| entry.day == day && entry.star == star)
+    {
+        Some(entry) if hash(entry.salt, day, star, answer) == entry.hash => Verdict::Correct,
+        Some(_) => Verdict::Incorrect,
+        None => Verdict::Unknown,
+    }
+}
+
+// Solves `day` and checks every answer, stars are numbered from 1
+pub fn verify(entries: &[Entry], day: &str, input: &str) -> Option<Vec<(String, Verdict)>> {
+    answers(day, input).map(|answers| {
+        answers
+            .into_iter()
+            .enumerate()
+            .map(|(idx, answer)| {
+                let verdict = check(entries, day, idx + 1, &answer);
+                (answer, verdict)
+            }).collect()
+    })
+}
+
+// New entries for the answers to `day`, to be added to `answers.hashes` once
+// they have been accepted.
+pub fn entries(day: &str, input: &str) -> Option<Vec<Entry>> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .subsec_nanos();
+    let mut rng = Rng::new(u64::from(nanos) ^ fnv1a(input.as_bytes()));
+
+    answers(day, input).map(|answers| {
+        answers
+            .iter()
+            .enumerate()
+            .map(|(idx, answer)| {
+                let salt = rng.next_u64();
+
+                Entry {
+                    day: day.to_owned(),
+                    star: idx + 1,
+                    salt,
+                    hash: hash(salt, day, idx + 1, answer),
+                }
+            }).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, entries, fnv1a, hash, parse, verify, Entry, ParseError, Verdict, HASHES};
+
+    static DAY01: &str = "+1\n-2\n+3\n+1";
+
+    #[test]
+    fn test_fnv1a() {
+        // Reference values for FNV-1a 64
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(1, "day01", 1, "510"), hash(1, "day01", 1, "510\n"));
+        assert_ne!(hash(1, "day01", 1, "510"), hash(2, "day01", 1, "510"));
+        assert_ne!(hash(1, "day01", 1, "510"), hash(1, "day01", 2, "510"));
+        assert_ne!(hash(1, "day01", 1, "510"), hash(1, "day04", 1, "510"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let entries = parse("# comment\n\nday01 2 00000000000000ff 0000000000000010\n").unwrap();
+
+        assert_eq!(
+            entries,
+            vec![Entry {
+                day: String::from("day01"),
+                star: 2,
+                salt: 255,
+                hash: 16,
+            }]
+        );
+        assert_eq!(
+            entries[0].to_string(),
+            "day01 2 00000000000000ff 0000000000000010"
+        );
+        assert_eq!(
+            parse("day01 2 ff"),
+            Err(ParseError::InvalidEntry(String::from("day01 2 ff")))
+        );
+        assert!(parse("day01 2 ff zz").is_err());
+        assert!(parse(HASHES).is_ok());
+    }
+
+    #[test]
+    fn test_verify() {
+        let known = entries("day01", DAY01).unwrap();
+        assert_eq!(known.len(), 2);
+        assert_ne!(known[0].salt, known[1].salt);
+
+        assert_eq!(
+            verify(&known, "day01", DAY01),
+            Some(vec![
+                (String::from("3"), Verdict::Correct),
+                (String::from("2"), Verdict::Correct),
+            ])
+        );
+        assert_eq!(check(&known, "day01", 1, "4"), Verdict::Incorrect);
+        assert_eq!(check(&known[1..], "day01", 1, "3"), Verdict::Unknown);
+        assert_eq!(verify(&known, "day09", DAY01), None);
+
+        // Round trips through the file format
+        let file = known
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(parse(&file), Ok(known));
+    }
+}
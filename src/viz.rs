@@ -0,0 +1,119 @@
+use day03;
+use day07;
+use day13;
+use day15;
+use day17;
+use day18;
+use day19;
+use day21;
+use day22;
+
+pub type Color = (u8, u8, u8);
+
+// Binary PPM (P6), about the simplest image format there is and one that
+// most image viewers and converters understand.
+pub fn ppm(width: usize, height: usize, pixels: &[Color]) -> Vec<u8> {
+    assert!(
+        pixels.len() == width * height,
+        "Expected {} pixels for a {}x{} image, got {}",
+        width * height,
+        width,
+        height,
+        pixels.len()
+    );
+
+    let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    image.reserve(pixels.len() * 3);
+
+    for &(r, g, b) in pixels {
+        image.extend_from_slice(&[r, g, b]);
+    }
+
+    image
+}
+
+pub fn render(day: &str, input: &str) -> Option<String> {
+    match day {
+        "day03" => Some(day03::render(input)),
+        "day07" => Some(day07::to_dot(input)),
+        "day17" => Some(day17::render(input)),
+        "day18" => Some(day18::resource_table(input)),
+        "day19" => Some(day19::disassemble(input)),
+        "day21" => Some(day21::value_table(input)),
+        "day22" => Some(day22::render(input)),
+        _ => None,
+    }
+}
+
+pub fn render_image(day: &str, input: &str) -> Option<Vec<u8>> {
+    match day {
+        "day03" => Some(day03::render_image(input)),
+        "day17" => Some(day17::render_image(input)),
+        "day22" => Some(day22::render_image(input)),
+        _ => None,
+    }
+}
+
+// Frames of an animation, one per step of the simulation
+pub fn frames(day: &str, input: &str) -> Option<Box<dyn Iterator<Item = String>>> {
+    match day {
+        "day13" => Some(Box::new(day13::ticks(input).map(|state| state.to_string()))),
+        "day15" => Some(Box::new(
+            day15::replay(input).map(|round| round.to_string()),
+        )),
+        "day17" => Some(Box::new(
+            day17::frames(input).map(|ground| ground.to_string()),
+        )),
+        "day18" => Some(Box::new(
+            day18::until_repeat(input)
+                .into_iter()
+                .map(|forest| forest.to_string()),
+        )),
+        _ => None,
+    }
+}
+
+pub fn image_frames(day: &str, input: &str) -> Option<Box<dyn Iterator<Item = Vec<u8>>>> {
+    match day {
+        "day13" => Some(Box::new(
+            day13::ticks(input).map(|state| state.render_image()),
+        )),
+        "day17" => Some(Box::new(
+            day17::frames(input).map(|ground| ground.render_image()),
+        )),
+        "day18" => Some(Box::new(
+            day18::until_repeat(input)
+                .into_iter()
+                .map(|forest| forest.render_image()),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frames, ppm, render};
+
+    #[test]
+    fn test_ppm() {
+        let image = ppm(2, 1, &[(255, 0, 0), (0, 0, 255)]);
+
+        assert_eq!(&image[..11], b"P6\n2 1\n255\n");
+        assert_eq!(&image[11..], &[255, 0, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_render_unknown_day() {
+        assert_eq!(render("day42", ""), None);
+        assert!(frames("day42", "").is_none());
+    }
+
+    #[test]
+    fn test_frames() {
+        let frames = frames("day13", "/->-<-\\\n\\-----/")
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(frames, vec!["/->-<-\\\n\\-----/", "/--X--\\\n\\-----/"]);
+    }
+}